@@ -1,7 +1,9 @@
-use std::{fs, env};
+use std::{fs, env, fmt};
 use serde::Deserialize;
 use toml;
 use log::{warn, debug};
+use smithy_common::auth::{controller::ApiKey, key::hash_key};
+use containers::RuntimeConfig;
 
 #[derive(Debug, Deserialize)]
 pub struct Config {
@@ -18,11 +20,19 @@ impl Default for Config {
                 mem: None,
                 disk: None,
                 network: None,
+                runtimes: default_runtimes(),
+                inventory: None,
+                disk_health: None,
+                container_stats: None,
             },
             server: ServerConfig {
                 port: 7032,
-                allowed_keys: vec![String::from("toes")],
-                no_auth_operations: vec![String::from("Health")],
+                keys: vec![ApiKey::new("default", &hash_key("toes"), None, None)],
+                allowed_keys: Vec::new(),
+                no_auth_operations: default_no_auth_operations(),
+                tls: None,
+                metrics: false,
+                exec_commands: Vec::new(),
             },
         }
     }
@@ -40,22 +50,90 @@ impl Config {
 #[derive(Clone, Debug, Deserialize)]
 pub struct ServerConfig {
     port: u16,
+    #[serde(default)]
+    keys: Vec<ApiKey>,
+    /// Plaintext keys from configs written before keys were hashed. They are
+    /// hashed into `keys` when the config is loaded.
+    #[serde(default)]
     allowed_keys: Vec<String>,
+    #[serde(default = "default_no_auth_operations")]
     no_auth_operations: Vec<String>,
+    #[serde(default)]
+    tls: Option<TlsConfig>,
+    /// Serves Prometheus metrics at `/metrics`. The endpoint is not
+    /// authenticated.
+    #[serde(default)]
+    metrics: bool,
+    /// Commands `ExecContainer` may run, each matched against the request's
+    /// full argv, or its prefix when the entry ends in `*`. Exec is disabled
+    /// when empty.
+    #[serde(default)]
+    exec_commands: Vec<String>,
+}
+
+fn default_no_auth_operations() -> Vec<String> {
+    vec![String::from("Health")]
 }
 
 impl ServerConfig {
+    /// Moves plaintext `allowed_keys` into `keys`, allowing each every
+    /// operation as they were before.
+    fn migrate_allowed_keys(&mut self) {
+        if self.allowed_keys.is_empty() {
+            return
+        }
+        warn!("server.allowed_keys is deprecated, generate hashed server.keys entries with keygen");
+        for (i, key) in self.allowed_keys.drain(..).enumerate() {
+            self.keys.push(ApiKey::new(&format!("allowed-key-{}", i), &hash_key(&key), None, None));
+        }
+    }
+
     pub fn get_server_port(&self) -> u16 {
         self.port
     }
 
-    pub fn allowed_keys(&self) -> &Vec<String> {
-        &self.allowed_keys
+    pub fn keys(&self) -> &Vec<ApiKey> {
+        &self.keys
     }
 
     pub fn no_auth_operations(&self) -> &Vec<String> {
         &self.no_auth_operations
     }
+
+    pub fn tls(&self) -> Option<&TlsConfig> {
+        self.tls.as_ref()
+    }
+
+    pub fn metrics_enabled(&self) -> bool {
+        self.metrics
+    }
+
+    pub fn exec_commands(&self) -> &Vec<String> {
+        &self.exec_commands
+    }
+}
+
+/// Paths to PEM files used to serve HTTPS. When `client_ca` is set, clients
+/// must present a certificate signed by it.
+#[derive(Clone, Debug, Deserialize)]
+pub struct TlsConfig {
+    cert: String,
+    key: String,
+    client_ca: Option<String>,
+}
+
+impl TlsConfig {
+    pub fn cert(&self) -> &String {
+        &self.cert
+    }
+
+    pub fn key(&self) -> &String {
+        &self.key
+    }
+
+    pub fn client_ca(&self) -> Option<&String> {
+        self.client_ca.as_ref()
+    }
 }
 
 #[derive(Clone, Debug, Deserialize)]
@@ -65,6 +143,20 @@ pub struct AgentConfig {
     mem: Option<u64>,
     disk: Option<u64>,
     network: Option<u64>,
+    /// Container runtimes to read containers from.
+    #[serde(default = "default_runtimes")]
+    runtimes: Vec<RuntimeConfig>,
+    /// Milliseconds between readings of the runtimes' images, volumes,
+    /// networks and disk usage.
+    inventory: Option<u64>,
+    /// Milliseconds between SMART readings of the physical disks.
+    disk_health: Option<u64>,
+    /// Milliseconds between statistics samples of running containers.
+    container_stats: Option<u64>,
+}
+
+fn default_runtimes() -> Vec<RuntimeConfig> {
+    vec![RuntimeConfig::default()]
 }
 
 impl AgentConfig {
@@ -83,34 +175,83 @@ impl AgentConfig {
     pub fn get_network_interval(&self) -> Option<u64> {
         self.network
     }
+    pub fn get_inventory_interval(&self) -> Option<u64> {
+        self.inventory
+    }
+    pub fn get_disk_health_interval(&self) -> Option<u64> {
+        self.disk_health
+    }
+    pub fn get_container_stats_interval(&self) -> Option<u64> {
+        self.container_stats
+    }
+    pub fn get_runtimes(&self) -> &Vec<RuntimeConfig> {
+        &self.runtimes
+    }
+}
+
+#[derive(Debug)]
+pub enum ConfigError {
+    Parse(String, toml::de::Error),
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigError::Parse(path, e) => write!(f, "failed to parse config {}: {}", path, e),
+        }
+    }
 }
 
-pub fn load_config() -> Config {
+impl std::error::Error for ConfigError {}
+
+/// Loads the config from `CONFIG_PATH`, or `config.toml`. The default config
+/// is only used when there is no file; a file that does not parse is an error.
+pub fn load_config() -> Result<Config, ConfigError> {
     let path = env::var("CONFIG_PATH").unwrap_or_else(|_| "config.toml".to_string());
     debug!("Loading config from: {}", path);
-    let config = fs::read_to_string(path);
+    let config = fs::read_to_string(&path);
     match config {
         Ok(config) => {
             debug!("Loaded config from file");
-            parse_config(config)
+            parse_config(&config).map_err(|e| ConfigError::Parse(path, e))
         },
         Err(_) => {
             warn!("Failed to load config from file, using default config");
-            Config::default()
+            Ok(Config::default())
         }
     }
 }
 
-fn parse_config(config: String) -> Config {
-    let config = toml::from_str(&config);
-    match config {
-        Ok(config) => {
-            debug!("Parsed config");
-            return config;
-        },
-        Err(_) => {
-            warn!("Failed to parse config, using default config");
-            return Config::default();
-        }
+fn parse_config(config: &str) -> Result<Config, toml::de::Error> {
+    let mut config: Config = toml::from_str(config)?;
+    debug!("Parsed config");
+    config.server.migrate_allowed_keys();
+    if config.server.keys.is_empty() {
+        warn!("No API keys are configured, only unauthenticated operations can be called");
+    }
+    Ok(config)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::parse_config;
+
+    #[test]
+    fn shipped_config_parses() {
+        let config = parse_config(include_str!("../../config.toml")).unwrap();
+        assert_eq!(config.get_server().get_server_port(), 7032);
+        assert_eq!(config.get_server().no_auth_operations(), &vec![String::from("Health")]);
+    }
+
+    #[test]
+    fn allowed_keys_become_hashed_keys() {
+        let config = parse_config("[server]\nport = 7032\nallowed_keys = [\"first\", \"second\"]\n\n[agent]\ninterval = 10000\n").unwrap();
+        let names: Vec<&str> = config.get_server().keys().iter().map(|k| k.name()).collect();
+        assert_eq!(names, vec!["allowed-key-0", "allowed-key-1"]);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn invalid_config_is_an_error() {
+        assert!(parse_config("[server]\nport = \"not a port\"\n").is_err());
+    }
+}
@@ -11,7 +11,11 @@ mod config;
 mod server;
 mod stats;
 
-use log::{debug, error, info};
+use containers::{ContainerEventAction, Containers};
+use hw_info::{read_disk_health, DiskHealth};
+use std::collections::HashMap;
+use futures_util::StreamExt;
+use log::{debug, error, info, warn};
 use server::http::start_server;
 use stats::controller::SystemController;
 
@@ -21,7 +25,7 @@ fn main() -> Result<(), Box<dyn Error>> {
     }
     env_logger::init();
 
-    let config = config::load_config();
+    let config = config::load_config()?;
 
     let env = env::var("RUNTIME_ENV").unwrap_or("dev".to_string());
 
@@ -52,9 +56,21 @@ fn main() -> Result<(), Box<dyn Error>> {
 async fn tokio_main(config: Config) -> Result<(), Box<dyn Error>> {
     info!("Initializing agent");
 
-    let ctl = Arc::new(Mutex::new(SystemController::new()));
+    let ctl = Arc::new(Mutex::new(SystemController::new(config.get_agent().get_runtimes())));
     let sctl = ctl.clone();
 
+    info!("Starting container event loop");
+    tokio::spawn(container_event_loop(ctl.clone()));
+
+    info!("Starting container inventory loop");
+    tokio::spawn(container_inventory_loop(ctl.clone(), config.get_agent().get_inventory_interval().unwrap_or(DEFAULT_INVENTORY_INTERVAL)));
+
+    info!("Starting container statistics loop");
+    tokio::spawn(container_stats_loop(ctl.clone(), config.get_agent().get_container_stats_interval().unwrap_or(DEFAULT_CONTAINER_STATS_INTERVAL)));
+
+    info!("Starting disk health loop");
+    tokio::spawn(disk_health_loop(ctl.clone(), config.get_agent().get_disk_health_interval().unwrap_or(DEFAULT_DISK_HEALTH_INTERVAL)));
+
     info!("Starting agent loop");
     tokio::spawn(agent_loop(ctl, config.get_agent().clone()));
 
@@ -76,6 +92,138 @@ async fn agent_loop(ctl: Arc<Mutex<SystemController>>, config: AgentConfig) {
     }
 }
 
+const EVENT_RECONNECT_DELAY: Duration = Duration::from_secs(5);
+const DEFAULT_INVENTORY_INTERVAL: u64 = 60000;
+const DEFAULT_DISK_HEALTH_INTERVAL: u64 = 600000;
+const DEFAULT_CONTAINER_STATS_INTERVAL: u64 = 15000;
+
+/// Periodically reads images, volumes, networks and disk usage. Reading disk
+/// usage can take a while, so it is done without holding the controller.
+async fn container_inventory_loop(ctl: Arc<Mutex<SystemController>>, interval: u64) {
+    let runtime = match ctl.lock().await.container_runtime() {
+        Some(r) => r,
+        None => return,
+    };
+
+    loop {
+        let images = runtime.list_images().await;
+        let volumes = runtime.list_volumes().await;
+        let networks = runtime.list_networks().await;
+        let disk_usage = runtime.disk_usage().await;
+        ctl.lock().await.update_container_inventory(images, volumes, networks, disk_usage);
+
+        sleep(Duration::from_millis(interval)).await;
+    }
+}
+
+/// Periodically re-samples statistics of running containers. Events only
+/// arrive on state changes, so without this a container that keeps running
+/// would report the statistics it was first read with. Sampling takes a while
+/// per container, so it is done without holding the controller.
+async fn container_stats_loop(ctl: Arc<Mutex<SystemController>>, interval: u64) {
+    let runtime = match ctl.lock().await.container_runtime() {
+        Some(r) => r,
+        None => return,
+    };
+
+    loop {
+        let running = ctl.lock().await.running_containers();
+        let mut statistics = HashMap::new();
+        for (id, runtime_name) in running {
+            if let Some(s) = runtime.get_container_stats(&runtime_name, &id).await {
+                statistics.insert(id, s);
+            }
+        }
+        ctl.lock().await.update_container_statistics(statistics);
+
+        sleep(Duration::from_millis(interval)).await;
+    }
+}
+
+/// Periodically reads SMART data for the physical disks. smartctl can take a
+/// while per disk, so it runs on a blocking thread without holding the
+/// controller.
+async fn disk_health_loop(ctl: Arc<Mutex<SystemController>>, interval: u64) {
+    loop {
+        let devices: Vec<String> = ctl.lock().await.disks().keys().cloned().collect();
+        let health = tokio::task::spawn_blocking(move || read_disks_health(devices)).await.unwrap_or_default();
+        ctl.lock().await.update_disk_health(health);
+
+        sleep(Duration::from_millis(interval)).await;
+    }
+}
+
+fn read_disks_health(devices: Vec<String>) -> HashMap<String, DiskHealth> {
+    let mut health = HashMap::new();
+    for device in devices {
+        match read_disk_health(&device) {
+            Ok(h) => {
+                health.insert(device, h);
+            }
+            Err(e) => warn!("Failed to read health of disk {}: {}", device, e),
+        }
+    }
+    health
+}
+
+/// Watches events from every container runtime, each in its own loop so a
+/// runtime whose stream fails is reconnected without disturbing the others.
+async fn container_event_loop(ctl: Arc<Mutex<SystemController>>) {
+    let runtime = match ctl.lock().await.container_runtime() {
+        Some(r) => r,
+        None => {
+            info!("No container runtime, not watching container events");
+            return;
+        }
+    };
+
+    for name in runtime.runtimes() {
+        tokio::spawn(runtime_event_loop(ctl.clone(), runtime.clone(), name.to_string()));
+    }
+}
+
+/// Keeps a runtime's containers current from its events. Each time the event
+/// stream is (re)opened the runtime's containers are resynced from a full
+/// listing, so events missed while disconnected don't leave them stale.
+async fn runtime_event_loop(ctl: Arc<Mutex<SystemController>>, runtime: Containers, name: String) {
+    loop {
+        // Subscribe before listing so nothing between the two is lost.
+        let mut events = match runtime.stream_runtime_events(&name).await {
+            Ok(e) => e,
+            Err(e) => {
+                warn!("Unable to watch events of runtime {}: {}", name, e);
+                return;
+            }
+        };
+        let containers = runtime.list_runtime_containers(&name).await;
+        ctl.lock().await.sync_containers(&name, containers);
+
+        while let Some(event) = events.next().await {
+            let event = match event {
+                Ok(e) => e,
+                Err(e) => {
+                    warn!("Event stream of runtime {} failed: {}", name, e);
+                    break;
+                }
+            };
+            debug!("Container {} event {:?}", event.id(), event.action());
+
+            if !event.action().changes_state() {
+                ctl.lock().await.record_container_event(event);
+                continue;
+            }
+            let container = match event.action() {
+                ContainerEventAction::Destroy => None,
+                _ => runtime.get_runtime_container(&name, event.id()).await,
+            };
+            ctl.lock().await.apply_container_event(event, container);
+        }
+
+        warn!("Event stream of runtime {} ended, reconnecting", name);
+        sleep(EVENT_RECONNECT_DELAY).await;
+    }
+}
+
 async fn server_loop(ctl: Arc<Mutex<SystemController>>, config: ServerConfig) {
     start_server(ctl, config).await;
 }
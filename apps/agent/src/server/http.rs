@@ -9,9 +9,9 @@ use aws_smithy_http_server::{
 };
 
 use log::{info, error};
-use tokio::sync::Mutex;
+use tokio::{net::TcpListener, sync::{Mutex, RwLock}};
 
-use crate::{stats::controller::SystemController, config::ServerConfig, server::operation::{disk::{get_disk, list_disks}, container::{stream_container_logs, get_container, list_containers, stream_container_statistics}}};
+use crate::{stats::controller::SystemController, config::ServerConfig, server::operation::{disk::{get_disk, list_disks}, container::{stream_container_logs, get_container, list_containers, stream_container_statistics, list_container_events}}};
 
 use smithy_common::auth::controller::AuthController;
 use smithy_common::auth::plugin::AuthExtension;
@@ -28,20 +28,42 @@ use super::operation::volume::list_volumes;
 use super::operation::network::get_network_interface;
 use super::operation::network::list_network_interfaces;
 use super::operation::cpu::get_cpu;
+use super::operation::lifecycle::{pause_container, remove_container, restart_container, start_container, stop_container, unpause_container};
+use super::operation::exec::exec_container;
+use super::operation::stack::{get_stack, list_stacks};
+use super::operation::metrics::get_metric_history;
+use super::operation::topology::get_storage_topology;
+use super::operation::pci::list_pci_devices;
+use super::operation::inventory::{get_container_disk_usage, list_container_networks, list_container_volumes, list_images};
+use super::metrics::{MetricsLayer, METRICS_PATH};
+use super::tls::{incoming, load_server_config, reload_on_hangup};
 
 pub const DEFAULT_ADDRESS: &str = "0.0.0.0";
 
+/// Operations that change the machine, refused to read-only keys.
+const WRITE_OPERATIONS: [&str; 7] = [
+    "StartContainer",
+    "StopContainer",
+    "RestartContainer",
+    "PauseContainer",
+    "UnpauseContainer",
+    "RemoveContainer",
+    "ExecContainer",
+];
+
 #[derive(Clone)]
 struct Config;
 
 pub struct State {
     pub controller: Arc<Mutex<SystemController>>,
+    pub exec_commands: Vec<String>,
 }
 
 impl State {
-    pub fn new(ctl: Arc<Mutex<SystemController>>) -> State {
+    pub fn new(ctl: Arc<Mutex<SystemController>>, exec_commands: &[String]) -> State {
         State {
             controller: ctl,
+            exec_commands: exec_commands.to_vec(),
         }
     }
 }
@@ -52,7 +74,7 @@ pub async fn check_health(_input: input::HealthInput) -> Result<output::HealthOu
 
 pub async fn start_server(ctl: Arc<Mutex<SystemController>>, config: ServerConfig) {
     // TODO: Add config where keys can be stored and retrived
-    let auth_controller = AuthController::new(config.no_auth_operations(), config.allowed_keys());
+    let auth_controller = AuthController::new(config.no_auth_operations(), config.keys()).with_write_operations(&WRITE_OPERATIONS);
 
     let plugins = PluginPipeline::new()
         .print()
@@ -66,24 +88,50 @@ pub async fn start_server(ctl: Arc<Mutex<SystemController>>, config: ServerConfi
         .get_system(get_system)
         .get_memory(get_memory)
         .get_cpu(get_cpu)
+        .get_metric_history(get_metric_history)
         .get_disk(get_disk)
         .list_disks(list_disks)
+        .get_storage_topology(get_storage_topology)
+        .list_pci_devices(list_pci_devices)
         .get_volume(get_volume)
         .list_volumes(list_volumes)
         .get_network_interface(get_network_interface)
         .list_network_interfaces(list_network_interfaces)
         .stream_container_logs(stream_container_logs)
         .stream_container_statistics(stream_container_statistics)
+        .list_container_events(list_container_events)
         .get_container(get_container)
         .list_containers(list_containers)
+        .get_stack(get_stack)
+        .list_stacks(list_stacks)
+        .start_container(start_container)
+        .stop_container(stop_container)
+        .restart_container(restart_container)
+        .pause_container(pause_container)
+        .unpause_container(unpause_container)
+        .remove_container(remove_container)
+        .exec_container(exec_container)
+        .list_images(list_images)
+        .list_container_volumes(list_container_volumes)
+        .list_container_networks(list_container_networks)
+        .get_container_disk_usage(get_container_disk_usage)
         .build()
         .expect("failed to build an instance of GethAgent");
 
+    let metrics = match config.metrics_enabled() {
+        true => {
+            info!("Serving metrics on {}", METRICS_PATH);
+            Some(ctl.clone())
+        }
+        false => None,
+    };
+
     // create state to add to request
-    let state = State::new(ctl);
+    let state = State::new(ctl, config.exec_commands());
     let app = app
         .layer(&AddExtensionLayer::new(Arc::new(state)))
-        .layer(&ServerRequestIdProviderLayer::new());
+        .layer(&ServerRequestIdProviderLayer::new())
+        .layer(&MetricsLayer::new(metrics));
 
     let make_app = app.into_make_service_with_connect_info::<SocketAddr>();
 
@@ -91,10 +139,26 @@ pub async fn start_server(ctl: Arc<Mutex<SystemController>>, config: ServerConfi
     let bind: SocketAddr = format!("{}:{}", DEFAULT_ADDRESS, config.get_server_port())
         .parse()
         .expect("unable to parse the server bind address and port");
-    let server = hyper::Server::bind(&bind).serve(make_app);
+
+    let result = match config.tls() {
+        Some(tls) => {
+            let current = Arc::new(RwLock::new(load_server_config(tls).expect("unable to load tls configuration")));
+            tokio::spawn(reload_on_hangup(tls.clone(), current.clone()));
+
+            let listener = TcpListener::bind(bind).await.expect("unable to bind the server address");
+            info!("Serving HTTPS");
+            hyper::Server::builder(hyper::server::accept::from_stream(incoming(listener, current)))
+                .serve(make_app)
+                .await
+        }
+        None => {
+            info!("Serving HTTP, no tls configuration was provided");
+            hyper::Server::bind(&bind).serve(make_app).await
+        }
+    };
 
     // Run forever-ish...
-    if let Err(err) = server.await {
+    if let Err(err) = result {
         error!("server error: {}", err);
     }
 }
\ No newline at end of file
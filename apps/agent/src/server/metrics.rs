@@ -0,0 +1,254 @@
+use std::{
+    collections::BTreeMap,
+    fmt::Write,
+    sync::Arc,
+    task::{Context, Poll},
+};
+
+use aws_smithy_http_server::body::{to_boxed, BoxBody};
+use http::{header::CONTENT_TYPE, Method, Request, Response, StatusCode};
+use tokio::sync::Mutex;
+use tower::{Layer, Service};
+
+use crate::stats::controller::SystemController;
+
+pub const METRICS_PATH: &str = "/metrics";
+const CONTENT_TYPE_TEXT: &str = "text/plain; version=0.0.4; charset=utf-8";
+
+enum Kind {
+    Gauge,
+    Counter,
+}
+
+impl Kind {
+    fn as_str(&self) -> &'static str {
+        match self {
+            Kind::Gauge => "gauge",
+            Kind::Counter => "counter",
+        }
+    }
+}
+
+struct Family {
+    help: &'static str,
+    kind: Kind,
+    samples: Vec<(String, f64)>,
+}
+
+/// Collects samples grouped by metric name so each family is written with a
+/// single HELP and TYPE header, in a stable order.
+#[derive(Default)]
+struct Exposition {
+    families: BTreeMap<&'static str, Family>,
+}
+
+impl Exposition {
+    fn add(&mut self, name: &'static str, help: &'static str, kind: Kind, labels: &[(&str, &str)], value: f64) {
+        let family = self.families.entry(name).or_insert(Family {
+            help,
+            kind,
+            samples: Vec::new(),
+        });
+        family.samples.push((format_labels(labels), value));
+    }
+
+    fn gauge(&mut self, name: &'static str, help: &'static str, labels: &[(&str, &str)], value: f64) {
+        self.add(name, help, Kind::Gauge, labels, value)
+    }
+
+    fn counter(&mut self, name: &'static str, help: &'static str, labels: &[(&str, &str)], value: f64) {
+        self.add(name, help, Kind::Counter, labels, value)
+    }
+
+    fn render(mut self) -> String {
+        let mut out = String::new();
+        for (name, family) in self.families.iter_mut() {
+            family.samples.sort_by(|a, b| a.0.cmp(&b.0));
+            let _ = writeln!(out, "# HELP {} {}", name, family.help);
+            let _ = writeln!(out, "# TYPE {} {}", name, family.kind.as_str());
+            for (labels, value) in family.samples.iter() {
+                let _ = writeln!(out, "{}{} {}", name, labels, value);
+            }
+        }
+        out
+    }
+}
+
+fn escape_label(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+fn format_labels(labels: &[(&str, &str)]) -> String {
+    if labels.is_empty() {
+        return String::new();
+    }
+    let pairs: Vec<String> = labels
+        .iter()
+        .map(|(k, v)| format!("{}=\"{}\"", k, escape_label(v)))
+        .collect();
+    format!("{{{}}}", pairs.join(","))
+}
+
+/// Renders the controller's current readings in the Prometheus text
+/// exposition format.
+pub fn render(ctl: &SystemController) -> String {
+    let mut exp = Exposition::default();
+
+    exp.gauge("geth_system_up_time_seconds", "Seconds since the machine booted.", &[], *ctl.system().up_time() as f64);
+    exp.gauge("geth_system_boot_time_seconds", "Unix time the machine booted.", &[], *ctl.system().boot_time() as f64);
+
+    for core in ctl.cpu().cores() {
+        let labels = [("core", core.name().as_str())];
+        exp.gauge("geth_cpu_core_usage_percent", "Utilization of a CPU core.", &labels, *core.usage() as f64);
+        exp.gauge("geth_cpu_core_frequency_megahertz", "Current frequency of a CPU core.", &labels, *core.frequency() as f64);
+    }
+
+    let memory = ctl.memory().memory();
+    exp.gauge("geth_memory_total_bytes", "Total memory.", &[], *memory.total() as f64);
+    exp.gauge("geth_memory_used_bytes", "Memory in use.", &[], *memory.used() as f64);
+    exp.gauge("geth_memory_available_bytes", "Memory available for use.", &[], *memory.available() as f64);
+
+    let swap = ctl.memory().swap();
+    exp.gauge("geth_swap_total_bytes", "Total swap.", &[], *swap.total() as f64);
+    exp.gauge("geth_swap_used_bytes", "Swap in use.", &[], *swap.used() as f64);
+    exp.gauge("geth_swap_available_bytes", "Swap available for use.", &[], *swap.available() as f64);
+
+    for volume in ctl.storage().volumes() {
+        let labels = [("volume", volume.name().as_str()), ("mount_point", volume.mount_point().as_str())];
+        exp.gauge("geth_volume_total_bytes", "Total space on a volume.", &labels, *volume.total_space() as f64);
+        exp.gauge("geth_volume_available_bytes", "Available space on a volume.", &labels, *volume.available_space() as f64);
+    }
+
+    for nic in ctl.network().network_interfaces() {
+        let labels = [("interface", nic.name().as_str())];
+        exp.counter("geth_network_transmitted_bytes_total", "Bytes transmitted by an interface.", &labels, *nic.bytes().transmitted() as f64);
+        exp.counter("geth_network_received_bytes_total", "Bytes received by an interface.", &labels, *nic.bytes().recieved() as f64);
+        exp.counter("geth_network_transmitted_packets_total", "Packets transmitted by an interface.", &labels, *nic.packets().transmitted() as f64);
+        exp.counter("geth_network_received_packets_total", "Packets received by an interface.", &labels, *nic.packets().recieved() as f64);
+    }
+
+    for container in ctl.containers().values() {
+        let stats = match container.statistics() {
+            Some(s) => s,
+            None => continue,
+        };
+        let labels = [("container_id", container.id()), ("container_name", container.name()), ("runtime", container.runtime())];
+        exp.gauge("geth_container_cpu_usage_percent", "CPU utilization of a container.", &labels, stats.cpu_utilization());
+        exp.gauge("geth_container_memory_usage_percent", "Memory utilization of a container.", &labels, stats.memory_utilization());
+        exp.gauge("geth_container_memory_usage_bytes", "Memory used by a container.", &labels, stats.memory_usage() as f64);
+        exp.gauge("geth_container_memory_limit_bytes", "Memory limit of a container.", &labels, stats.memory_limit() as f64);
+        exp.counter("geth_container_network_received_bytes_total", "Bytes received by a container.", &labels, stats.network_rx_bytes() as f64);
+        exp.counter("geth_container_network_transmitted_bytes_total", "Bytes transmitted by a container.", &labels, stats.network_tx_bytes() as f64);
+        exp.counter("geth_container_block_read_bytes_total", "Bytes read from block devices by a container.", &labels, stats.block_read_bytes() as f64);
+        exp.counter("geth_container_block_written_bytes_total", "Bytes written to block devices by a container.", &labels, stats.block_write_bytes() as f64);
+    }
+
+    exp.render()
+}
+
+/// A [`Layer`] that answers `GET /metrics` ahead of the Smithy router. With
+/// no controller every request passes straight through.
+#[derive(Clone)]
+pub struct MetricsLayer {
+    controller: Option<Arc<Mutex<SystemController>>>,
+}
+
+impl MetricsLayer {
+    pub fn new(controller: Option<Arc<Mutex<SystemController>>>) -> MetricsLayer {
+        MetricsLayer { controller }
+    }
+}
+
+impl<S> Layer<S> for MetricsLayer {
+    type Service = MetricsService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        MetricsService {
+            inner,
+            controller: self.controller.clone(),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct MetricsService<S> {
+    inner: S,
+    controller: Option<Arc<Mutex<SystemController>>>,
+}
+
+impl<B, S> Service<Request<B>> for MetricsService<S>
+where
+    B: Send + 'static,
+    S: Service<Request<B>, Response = Response<BoxBody>> + Send + 'static,
+    S::Future: Send + 'static,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = std::pin::Pin<
+        Box<dyn std::future::Future<Output = Result<Self::Response, Self::Error>> + Send>
+    >;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: Request<B>) -> Self::Future {
+        let controller = match &self.controller {
+            Some(c) if req.method() == Method::GET && req.uri().path() == METRICS_PATH => c.clone(),
+            _ => return Box::pin(self.inner.call(req)),
+        };
+
+        Box::pin(async move {
+            let body = render(&*controller.lock().await);
+            let response = Response::builder()
+                .status(StatusCode::OK)
+                .header(CONTENT_TYPE, CONTENT_TYPE_TEXT)
+                .body(to_boxed(body))
+                .expect("metrics response is valid");
+            Ok(response)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{escape_label, format_labels, Exposition};
+
+    #[test]
+    fn escapes_label_values() {
+        assert_eq!(escape_label("plain"), "plain");
+        assert_eq!(escape_label(r#"say "hi""#), r#"say \"hi\""#);
+        assert_eq!(escape_label(r"C:\data"), r"C:\\data");
+        assert_eq!(escape_label("two\nlines"), r"two\nlines");
+        assert_eq!(escape_label("\\\"\n"), r#"\\\"\n"#);
+    }
+
+    #[test]
+    fn formats_labels() {
+        assert_eq!(format_labels(&[]), "");
+        assert_eq!(format_labels(&[("name", "web \"1\"")]), r#"{name="web \"1\""}"#);
+        assert_eq!(format_labels(&[("a", "1"), ("b", "2")]), r#"{a="1",b="2"}"#);
+    }
+
+    #[test]
+    fn renders_each_family_once_in_order() {
+        let mut exp = Exposition::default();
+        exp.gauge("geth_b", "Second.", &[("core", "cpu1")], 2.0);
+        exp.counter("geth_a", "First.", &[], 7.0);
+        exp.gauge("geth_b", "Second.", &[("core", "cpu0")], 1.5);
+
+        let expected = "\
+# HELP geth_a First.
+# TYPE geth_a counter
+geth_a 7
+# HELP geth_b Second.
+# TYPE geth_b gauge
+geth_b{core=\"cpu0\"} 1.5
+geth_b{core=\"cpu1\"} 2
+";
+        assert_eq!(exp.render(), expected);
+    }
+}
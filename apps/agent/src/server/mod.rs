@@ -1,2 +1,4 @@
 pub mod http;
+mod metrics;
 mod operation;
+mod tls;
\ No newline at end of file
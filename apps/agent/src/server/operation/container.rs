@@ -1,9 +1,12 @@
-use std::{sync::Arc, collections::HashMap};
+use std::{sync::Arc, collections::HashMap, time::Duration};
 
 use async_stream::stream;
 use aws_smithy_http_server::Extension;
-use containers::{Container, Port, ContainerProtocol, Volume, Network};
-use geth_agent_server::{output::{StreamContainerLogsOutput, GetContainerOutput, ListContainersOutput, StreamContainerStatisticsOutput}, input::{StreamContainerLogsInput, ListContainersInput, GetContainerInput, StreamContainerStatisticsInput}, error::{self, ResourceNotFoundException}, model::{Logs, LogLine, ContainerSummary, ContainerState, ContainerPortBinding, ContainerPortProtocol, ContainerVolume, ContainerNetwork, ContainerStatistics, ContainerType}};
+use containers::{Container, ContainerLogLine, ContainerLogSource, Port, ContainerProtocol, Volume, Network};
+use futures::{Stream, StreamExt};
+use log::{debug, warn};
+use tokio::time::Instant;
+use geth_agent_server::{output::{StreamContainerLogsOutput, GetContainerOutput, ListContainersOutput, StreamContainerStatisticsOutput, ListContainerEventsOutput}, input::{StreamContainerLogsInput, ListContainersInput, GetContainerInput, StreamContainerStatisticsInput, ListContainerEventsInput}, error::{self, ResourceNotFoundException}, model::{Logs, LogLine, LogSource, StatisticsStream, ContainerSummary, ContainerState, ContainerPortBinding, ContainerPortProtocol, ContainerVolume, ContainerNetwork, ContainerStatistics, ContainerType, ContainerEvent, ContainerEventAction, ContainerHealth, ContainerHealthStatus, ContainerRestartPolicy, ContainerRestartPolicyName}};
 use crate::server::http::State;
 
 pub fn containers_to_summaries(conts: &HashMap<String, Container>) -> Vec<ContainerSummary> {
@@ -16,12 +19,8 @@ pub fn containers_to_summaries(conts: &HashMap<String, Container>) -> Vec<Contai
     summaries
 }
 
-pub fn container_to_summary(container: &Container) -> ContainerSummary {
-    let id = container.id().to_string();
-    let name = container.name().to_string();
-    let image = container.image().to_string();
-    let created = container.created().timestamp() as i64;
-    let state = match container.state() {
+pub fn container_state_to_summary(state: &containers::ContainerState) -> ContainerState {
+    match state {
         containers::ContainerState::Running => ContainerState::Running,
         containers::ContainerState::Created => ContainerState::Created,
         containers::ContainerState::Restarting => ContainerState::Restarting,
@@ -31,7 +30,15 @@ pub fn container_to_summary(container: &Container) -> ContainerSummary {
         containers::ContainerState::Dead => ContainerState::Dead,
         containers::ContainerState::Empty => ContainerState::Empty,
         _ => ContainerState::Unknown,
-    };
+    }
+}
+
+pub fn container_to_summary(container: &Container) -> ContainerSummary {
+    let id = container.id().to_string();
+    let name = container.name().to_string();
+    let image = container.image().to_string();
+    let created = container.created().timestamp() as i64;
+    let state = container_state_to_summary(container.state());
     let ports = ports_to_port_summaries(container.ports());
     let volumes = volume_to_volume_summaries(container.volumes());
     let networks = network_to_network_summaries(container.networks());
@@ -58,9 +65,12 @@ pub fn container_to_summary(container: &Container) -> ContainerSummary {
     };
     let container_type = match container.type_() {
         containers::ContainerType::Docker => ContainerType::Docker,
-        _ => ContainerType::Unknown,
+        containers::ContainerType::Podman => ContainerType::Podman,
+        containers::ContainerType::Containerd => ContainerType::Containerd,
     };
-
+    let runtime = container.runtime().to_string();
+    let health = container.health().and_then(health_to_summary);
+    let restart_policy = container.restart_policy().map(restart_policy_to_summary);
 
     ContainerSummary {
         id,
@@ -74,6 +84,12 @@ pub fn container_to_summary(container: &Container) -> ContainerSummary {
         finished,
         statistics,
         container_type,
+        runtime,
+        health,
+        restart_count: container.restart_count() as i64,
+        exit_code: container.exit_code(),
+        oom_killed: container.oom_killed(),
+        restart_policy,
         ports: match ports.len() {
             0 => None,
             _ => Some(ports),
@@ -93,6 +109,30 @@ pub fn container_to_summary(container: &Container) -> ContainerSummary {
     }
 }
 
+fn health_to_summary(health: &containers::ContainerHealth) -> Option<ContainerHealth> {
+    let status = match health.status() {
+        containers::HealthStatus::Starting => ContainerHealthStatus::Starting,
+        containers::HealthStatus::Healthy => ContainerHealthStatus::Healthy,
+        containers::HealthStatus::Unhealthy => ContainerHealthStatus::Unhealthy,
+        containers::HealthStatus::None => return None,
+    };
+    Some(ContainerHealth {
+        status,
+        failing_streak: health.failing_streak() as i64,
+        last_output: health.last_output().map(String::from),
+    })
+}
+
+fn restart_policy_to_summary(policy: &containers::RestartPolicy) -> ContainerRestartPolicy {
+    let (name, maximum_retry_count) = match policy {
+        containers::RestartPolicy::No => (ContainerRestartPolicyName::No, None),
+        containers::RestartPolicy::Always => (ContainerRestartPolicyName::Always, None),
+        containers::RestartPolicy::UnlessStopped => (ContainerRestartPolicyName::UnlessStopped, None),
+        containers::RestartPolicy::OnFailure(retries) => (ContainerRestartPolicyName::OnFailure, retries.map(|r| r as i64)),
+    };
+    ContainerRestartPolicy { name, maximum_retry_count }
+}
+
 fn optional_timestamp(ts: i64) -> Option<i64> {
     if ts < 0 {
         None
@@ -227,49 +267,160 @@ pub async fn list_containers(input: ListContainersInput, state: Extension<Arc<St
     Ok(output)
 }
 
-pub async fn stream_container_statistics(input: StreamContainerStatisticsInput, state: Extension<Arc<State>>) -> Result<StreamContainerStatisticsOutput, error::StreamContainerStatisticsError> {
-    // let ctl = state.controller.lock().await;
-    // let containers = ctl.containers();
-
-    // let id = input.id.to_string();
-
-    // do this better
-    // for container in containers {
-    //     if container.id() == &id {
-    //         let stats = container.statistics();
-    //         let summary = container_stats_to_summary(stats);
-    //         let output = StreamContainerStatisticsOutput { stats };
-    //         return Ok(output)
-    //     }
-    // }
-
-    Err(error::StreamContainerStatisticsError::ResourceNotFoundException(ResourceNotFoundException { message: format!("Container not found") }))
-
-    // let container = match containers.get(id) {
-    //     Some(c) => {
-    //         let stats = c.stats();
-    //         let output = StreamContainerStatisticsOutput { stats };
-    //         return Ok(output)
-    //     },
-    //     None => return Err(error::StreamContainerStatisticsError::ResourceNotFoundException(ResourceNotFoundException { message: format!("Container not found") })),
-    // };
+fn event_to_summary(event: &containers::ContainerEvent) -> ContainerEvent {
+    let (action, detail) = match event.action() {
+        containers::ContainerEventAction::Create => (ContainerEventAction::Create, None),
+        containers::ContainerEventAction::Start => (ContainerEventAction::Start, None),
+        containers::ContainerEventAction::Stop => (ContainerEventAction::Stop, None),
+        containers::ContainerEventAction::Die => (ContainerEventAction::Die, None),
+        containers::ContainerEventAction::Destroy => (ContainerEventAction::Destroy, None),
+        containers::ContainerEventAction::Pause => (ContainerEventAction::Pause, None),
+        containers::ContainerEventAction::Unpause => (ContainerEventAction::Unpause, None),
+        containers::ContainerEventAction::Rename => (ContainerEventAction::Rename, None),
+        containers::ContainerEventAction::HealthStatus(s) => (ContainerEventAction::HealthStatus, Some(s.to_string())),
+        containers::ContainerEventAction::Other(a) => (ContainerEventAction::Other, Some(a.to_string())),
+    };
+
+    ContainerEvent {
+        action,
+        detail,
+        time: event.time().timestamp_millis(),
+        runtime: event.runtime().to_string(),
+        attributes: Some(event.attributes().to_owned()),
+    }
 }
 
-pub async fn stream_container_logs(input: StreamContainerLogsInput, state: Extension<Arc<State>>) -> Result<StreamContainerLogsOutput, error::StreamContainerLogsError> {
+pub async fn list_container_events(input: ListContainerEventsInput, state: Extension<Arc<State>>) -> Result<ListContainerEventsOutput, error::ListContainerEventsError> {
     let ctl = state.controller.lock().await;
-    let containers = ctl.containers();
+    let id = input.id.to_string();
 
-    // let id = input.id.to_string();
+    let events = match ctl.container_events(&id) {
+        Some(e) => e,
+        None if ctl.containers().contains_key(&id) => Vec::new(),
+        None => return Err(error::ListContainerEventsError::ResourceNotFoundException(ResourceNotFoundException { message: format!("Container {} not found", id) })),
+    };
 
-    let output_stream = stream! {
-        let mut i = 0;
-        loop {
-            yield Ok(Logs::Line(LogLine { message: Some("line".to_string()), timestamp: None }));
+    let skip = match input.limit {
+        Some(l) if l >= 0 => events.len().saturating_sub(l as usize),
+        _ => 0,
+    };
+    let events = events.iter().skip(skip).map(event_to_summary).collect();
 
-            if i == 10 {
+    Ok(ListContainerEventsOutput { events })
+}
+
+const DEFAULT_STATISTICS_INTERVAL: u64 = 1;
+/// Samples arriving this much early still count as a full interval apart, so
+/// jitter in the runtime's once a second samples doesn't drop every other one.
+const STATISTICS_JITTER: Duration = Duration::from_millis(100);
+
+/// Forwards the first item, then the first to arrive once `interval`, less
+/// the jitter tolerance, has passed since the last one forwarded.
+fn throttle<S: Stream + Unpin>(mut items: S, interval: Duration) -> impl Stream<Item = S::Item> {
+    let gap = interval.saturating_sub(STATISTICS_JITTER);
+    stream! {
+        let mut last_sent: Option<Instant> = None;
+        while let Some(item) = items.next().await {
+            if let Some(sent) = last_sent {
+                if sent.elapsed() < gap {
+                    continue;
+                }
+            }
+            last_sent = Some(Instant::now());
+            yield item;
+        }
+    }
+}
+
+pub async fn stream_container_statistics(input: StreamContainerStatisticsInput, state: Extension<Arc<State>>) -> Result<StreamContainerStatisticsOutput, error::StreamContainerStatisticsError> {
+    let runtime = state.controller.lock().await.container_runtime();
+
+    let id = input.id.to_string();
+    let runtime = match runtime {
+        Some(r) if r.container_exists(&id).await => r,
+        _ => return Err(error::StreamContainerStatisticsError::ResourceNotFoundException(ResourceNotFoundException { message: format!("Container {} not found", id) })),
+    };
+
+    let interval = match input.interval {
+        Some(i) if i > 0 => Duration::from_secs(i as u64),
+        _ => Duration::from_secs(DEFAULT_STATISTICS_INTERVAL),
+    };
+
+    // Runtimes emit roughly one sample a second; forward one per interval and
+    // stop once the container is no longer running.
+    let output_stream = stream! {
+        let stats = match runtime.stream_container_stats(&id).await {
+            Ok(s) => s,
+            Err(e) => {
+                warn!("Failed to stream statistics for container {}: {}", id, e);
+                return;
+            }
+        };
+        let mut stats = Box::pin(throttle(stats, interval));
+        while let Some(sample) = stats.next().await {
+            let sample = match sample {
+                Ok(s) => s,
+                Err(e) => {
+                    warn!("Statistics stream for container {} ended: {}", id, e);
+                    break;
+                }
+            };
+
+            if !runtime.is_running(&id).await {
+                debug!("Container {} stopped, ending statistics stream", id);
                 break;
             }
-            i += 1;
+
+            yield Ok(StatisticsStream::Statistics(container_stats_to_summary(&sample)));
+        }
+    };
+    Ok(StreamContainerStatisticsOutput::builder()
+        .stream(output_stream.into())
+        .build()
+        .unwrap())
+}
+
+fn log_line_to_event(line: &ContainerLogLine) -> LogLine {
+    let source = match line.source() {
+        ContainerLogSource::StdOut => LogSource::Stdout,
+        ContainerLogSource::StdErr => LogSource::Stderr,
+    };
+
+    LogLine {
+        message: Some(line.line().to_string()),
+        timestamp: Some(line.timestamp().timestamp_millis()),
+        source: Some(source),
+    }
+}
+
+pub async fn stream_container_logs(input: StreamContainerLogsInput, state: Extension<Arc<State>>) -> Result<StreamContainerLogsOutput, error::StreamContainerLogsError> {
+    let runtime = state.controller.lock().await.container_runtime();
+
+    let id = input.id.to_string();
+    let runtime = match runtime {
+        Some(r) if r.container_exists(&id).await => r,
+        _ => return Err(error::StreamContainerLogsError::ResourceNotFoundException(ResourceNotFoundException { message: format!("Container {} not found", id) })),
+    };
+
+    let follow = input.follow.unwrap_or(false);
+    let (limit, since, until) = (input.limit, input.since, input.until);
+
+    let output_stream = stream! {
+        let mut logs = match runtime.stream_container_logs(&id, follow, limit, since, until).await {
+            Ok(l) => l,
+            Err(e) => {
+                warn!("Failed to stream logs for container {}: {}", id, e);
+                return;
+            }
+        };
+        while let Some(line) = logs.next().await {
+            match line {
+                Ok(l) => yield Ok(Logs::Line(log_line_to_event(&l))),
+                Err(e) => {
+                    warn!("Log stream for container {} ended: {}", id, e);
+                    break;
+                }
+            }
         }
     };
     Ok(StreamContainerLogsOutput::builder()
@@ -338,4 +489,39 @@ pub async fn stream_container_logs(input: StreamContainerLogsInput, state: Exten
 //         .build()
 //         .unwrap())
 //     // Err(error::StreamContainerLogEventsError::ResourceNotFoundException(error::ResourceNotFoundException { message: format!("no events") }))
-// }
\ No newline at end of file
+// }
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use async_stream::stream;
+    use futures::StreamExt;
+    use tokio::time::sleep;
+
+    use super::throttle;
+
+    /// Emits `count` samples, the first immediately and the rest `every` apart.
+    fn samples(count: u32, every: Duration) -> impl futures::Stream<Item = u32> {
+        stream! {
+            for i in 0..count {
+                if i > 0 {
+                    sleep(every).await;
+                }
+                yield i;
+            }
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn early_samples_within_jitter_are_forwarded() {
+        let forwarded: Vec<u32> = throttle(Box::pin(samples(6, Duration::from_millis(990))), Duration::from_secs(1)).collect().await;
+        assert_eq!(forwarded, vec![0, 1, 2, 3, 4, 5]);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn samples_inside_the_interval_are_dropped() {
+        let forwarded: Vec<u32> = throttle(Box::pin(samples(7, Duration::from_millis(990))), Duration::from_secs(3)).collect().await;
+        assert_eq!(forwarded, vec![0, 3, 6]);
+    }
+}
@@ -1,8 +1,8 @@
 use std::{sync::Arc, str::FromStr, collections::HashMap};
 
 use aws_smithy_http_server::Extension;
-use geth_agent_server::{output::GetDiskOutput, output::ListDisksOutput, model::{DiskSummary, DiskType, DiskInterface as SmithyDiskInterface}, input::GetDiskInput, input::ListDisksInput, error};
-use hw_info::{Disk, DiskInterface, DiskKind};
+use geth_agent_server::{output::GetDiskOutput, output::ListDisksOutput, model::{DiskSummary, DiskHealthSummary, DiskType, DiskInterface as SmithyDiskInterface}, input::GetDiskInput, input::ListDisksInput, error};
+use hw_info::{Disk, DiskHealth, DiskInterface, DiskKind};
 
 use crate::server::http::State;
 
@@ -15,7 +15,7 @@ pub async fn get_disk(input: GetDiskInput, state: Extension<Arc<State>>) -> Resu
 
     let disk = match disks.get(dev) {
         Some(d) => {
-            let summary = disk_to_summary(d);
+            let summary = disk_to_summary(d, ctl.disk_health().get(dev));
             let output = GetDiskOutput { summary };
             return Ok(output)
         },
@@ -26,22 +26,22 @@ pub async fn get_disk(input: GetDiskInput, state: Extension<Arc<State>>) -> Resu
 pub async fn list_disks(_input: ListDisksInput, state: Extension<Arc<State>>) -> Result<ListDisksOutput, error::ListDisksError> {
     let ctl = state.controller.lock().await;
     let disks = ctl.disks();
-    let sums = disks_to_summaries(disks);
+    let sums = disks_to_summaries(disks, ctl.disk_health());
     let output = ListDisksOutput { summaries: sums };
     Ok(output)
 }
 
-pub fn disks_to_summaries(disks: &HashMap<String, Disk>) -> Vec<DiskSummary> {
+pub fn disks_to_summaries(disks: &HashMap<String, Disk>, health: &HashMap<String, DiskHealth>) -> Vec<DiskSummary> {
     let mut summaries = Vec::new();
-    for (_, disk) in disks {
-        let sum = disk_to_summary(disk);
+    for (dev, disk) in disks {
+        let sum = disk_to_summary(disk, health.get(dev));
         summaries.push(sum);
     }
 
     summaries
 }
 
-pub fn disk_to_summary(disk: &Disk) -> DiskSummary {
+pub fn disk_to_summary(disk: &Disk, health: Option<&DiskHealth>) -> DiskSummary {
     let device = disk.get_device().to_owned();
     let model = disk.get_model().to_owned();
     let serial = disk.get_serial().to_owned();
@@ -74,5 +74,18 @@ pub fn disk_to_summary(disk: &Disk) -> DiskSummary {
         sector_size,
         size_raw,
         size_actual: *size,
+        health: health.map(health_to_summary),
+    }
+}
+
+fn health_to_summary(health: &DiskHealth) -> DiskHealthSummary {
+    DiskHealthSummary {
+        passed: health.get_passed(),
+        temperature: health.get_temperature().map(|t| t as i32),
+        power_on_hours: health.get_power_on_hours().map(|h| h as i64),
+        reallocated_sectors: health.get_reallocated_sectors().map(|s| s as i64),
+        pending_sectors: health.get_pending_sectors().map(|s| s as i64),
+        percentage_used: health.get_percentage_used().map(|p| p as i32),
+        media_errors: health.get_media_errors().map(|e| e as i64),
     }
 }
\ No newline at end of file
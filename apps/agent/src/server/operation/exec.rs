@@ -0,0 +1,159 @@
+use std::{sync::Arc, time::Duration};
+
+use async_stream::stream;
+use aws_smithy_http_server::Extension;
+use aws_smithy_types::Blob;
+use containers::{ExecControl, ExecInput, ExecOutput};
+use futures::StreamExt;
+use geth_agent_server::{
+    error::{self, CommandNotAllowedException},
+    input::ExecContainerInput,
+    model::{ExecData, ExecExit, ExecInputStream, ExecOutputStream},
+    output::ExecContainerOutput,
+};
+use log::{debug, info, warn};
+use tokio::{io::AsyncWriteExt, time::sleep};
+
+use crate::server::http::State;
+
+use super::lifecycle::{map_error, runtime};
+
+const EXIT_POLL_INTERVAL: Duration = Duration::from_millis(50);
+const EXIT_POLL_ATTEMPTS: u32 = 40;
+
+/// Whether `command` matches one of the allowed entries. An entry is a full
+/// argv split on whitespace; one ending in `*` also allows any further
+/// arguments after its prefix.
+fn command_allowed(allowed: &[String], command: &[String]) -> bool {
+    !command.is_empty() && allowed.iter().any(|a| command_matches(a, command))
+}
+
+fn command_matches(entry: &str, command: &[String]) -> bool {
+    let words: Vec<&str> = entry.split_whitespace().collect();
+    match words.split_last() {
+        Some((&"*", prefix)) => !prefix.is_empty() && command.len() >= prefix.len() && prefix.iter().zip(command).all(|(p, c)| p == c),
+        _ => words.len() == command.len() && words.iter().zip(command).all(|(w, c)| w == c),
+    }
+}
+
+/// Forwards the client's input events to the exec session until the client
+/// closes its stream, then closes the process's stdin.
+async fn forward_input(mut events: aws_smithy_http::event_stream::Receiver<ExecInputStream, error::ExecInputStreamError>, mut stdin: ExecInput, control: ExecControl) {
+    loop {
+        match events.recv().await {
+            Ok(Some(ExecInputStream::Stdin(data))) => {
+                if let Err(e) = stdin.write_all(data.data.as_ref()).await {
+                    debug!("Exec {} stdin closed: {}", control.id(), e);
+                    break;
+                }
+            },
+            Ok(Some(ExecInputStream::Resize(size))) => {
+                if let Err(e) = control.resize(size.width as u16, size.height as u16).await {
+                    warn!("Failed to resize exec {}: {}", control.id(), e);
+                }
+            },
+            Ok(Some(_)) => {},
+            Ok(None) => break,
+            Err(e) => {
+                warn!("Exec {} input stream failed: {:?}", control.id(), e);
+                break;
+            }
+        }
+    }
+    let _ = stdin.shutdown().await;
+}
+
+/// Reads the exec's exit code. The runtime can still report the process as
+/// running just after its output closes, so this polls briefly before giving
+/// up.
+async fn wait_exit_code(control: &ExecControl) -> Option<i64> {
+    for _ in 0..EXIT_POLL_ATTEMPTS {
+        match control.exit_code().await {
+            Ok(Some(c)) => return Some(c),
+            Ok(None) => sleep(EXIT_POLL_INTERVAL).await,
+            Err(e) => {
+                warn!("Failed to read exit code of exec {}: {}", control.id(), e);
+                return None;
+            }
+        }
+    }
+    warn!("Exec {} still running after its output closed", control.id());
+    None
+}
+
+pub async fn exec_container(input: ExecContainerInput, state: Extension<Arc<State>>) -> Result<ExecContainerOutput, error::ExecContainerError> {
+    if !command_allowed(&state.exec_commands, &input.command) {
+        warn!("Refusing to exec {:?} in container {}", input.command, input.id);
+        return Err(error::ExecContainerError::CommandNotAllowedException(CommandNotAllowedException { message: format!("Command {:?} is not allowed", input.command.first().map(String::as_str).unwrap_or_default()) }));
+    }
+
+    info!("Executing {:?} in container {}", input.command, input.id);
+    let tty = input.tty.unwrap_or(false);
+    let result = match runtime(&state, &input.id).await {
+        Ok(r) => r.exec(&input.id, &input.command, tty).await,
+        Err(e) => Err(e),
+    };
+    let session = match result {
+        Ok(s) => s,
+        Err(e) => return Err(map_error(e, error::ExecContainerError::ResourceNotFoundException, error::ExecContainerError::ConflictException, error::ExecContainerError::InternalServerException)),
+    };
+
+    tokio::spawn(forward_input(input.events, session.input, session.control.clone()));
+
+    let control = session.control;
+    let mut output = session.output;
+    let output_stream = stream! {
+        while let Some(chunk) = output.next().await {
+            match chunk {
+                Ok(ExecOutput::StdOut(data)) => yield Ok(ExecOutputStream::Stdout(ExecData { data: Blob::new(data) })),
+                Ok(ExecOutput::StdErr(data)) => yield Ok(ExecOutputStream::Stderr(ExecData { data: Blob::new(data) })),
+                Err(e) => {
+                    warn!("Exec {} output stream failed: {}", control.id(), e);
+                    break;
+                }
+            }
+        }
+
+        let exit_code = wait_exit_code(&control).await;
+        yield Ok(ExecOutputStream::Exit(ExecExit { exit_code }));
+    };
+
+    Ok(ExecContainerOutput::builder()
+        .output(output_stream.into())
+        .build()
+        .unwrap())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::command_allowed;
+
+    fn allowed(entries: &[&str], command: &[&str]) -> bool {
+        let entries: Vec<String> = entries.iter().map(|e| e.to_string()).collect();
+        let command: Vec<String> = command.iter().map(|c| c.to_string()).collect();
+        command_allowed(&entries, &command)
+    }
+
+    #[test]
+    fn matches_the_full_argv() {
+        assert!(allowed(&["df -h"], &["df", "-h"]));
+        assert!(!allowed(&["df -h"], &["df"]));
+        assert!(!allowed(&["df -h"], &["df", "-h", "/"]));
+        assert!(!allowed(&["sh"], &["sh", "-c", "rm -rf /"]));
+    }
+
+    #[test]
+    fn wildcard_allows_arguments_after_the_prefix() {
+        assert!(allowed(&["ls *"], &["ls"]));
+        assert!(allowed(&["ls *"], &["ls", "-la", "/var"]));
+        assert!(!allowed(&["ls *"], &["lsblk"]));
+        assert!(!allowed(&["*"], &["sh"]));
+    }
+
+    #[test]
+    fn empty_commands_and_lists_are_refused() {
+        assert!(!allowed(&["ls *"], &[]));
+        assert!(!allowed(&[], &["ls"]));
+        assert!(!allowed(&[""], &[]));
+    }
+}
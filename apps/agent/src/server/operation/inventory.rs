@@ -0,0 +1,126 @@
+use std::sync::Arc;
+
+use aws_smithy_http_server::Extension;
+use containers::{DiskUsage, Image, RuntimeNetwork, RuntimeVolume};
+use geth_agent_server::{output::{ListImagesOutput, ListContainerVolumesOutput, ListContainerNetworksOutput, GetContainerDiskUsageOutput}, input::{ListImagesInput, ListContainerVolumesInput, ListContainerNetworksInput, GetContainerDiskUsageInput}, error, model::{ImageSummary, ContainerVolumeSummary, ContainerNetworkSummary, ContainerDiskUsageSummary, DiskUsageEntry}};
+
+use crate::server::http::State;
+
+fn optional_string(s: &str) -> Option<String> {
+    match s.is_empty() {
+        true => None,
+        false => Some(s.to_string()),
+    }
+}
+
+pub fn image_to_summary(image: &Image) -> ImageSummary {
+    ImageSummary {
+        id: image.id().to_string(),
+        tags: image.tags().to_owned(),
+        size: image.size() as i64,
+        created: image.created().timestamp(),
+        dangling: image.dangling(),
+        containers: image.containers().map(|c| c as i64),
+        runtime: image.runtime().to_string(),
+    }
+}
+
+pub fn images_to_summaries(images: &[Image]) -> Vec<ImageSummary> {
+    images.iter().map(image_to_summary).collect()
+}
+
+pub fn volume_to_summary(volume: &RuntimeVolume) -> ContainerVolumeSummary {
+    ContainerVolumeSummary {
+        name: volume.name().to_string(),
+        driver: volume.driver().to_string(),
+        mount_point: volume.mount_point().to_string(),
+        created: volume.created().map(|c| c.timestamp()),
+        size: volume.size().map(|s| s as i64),
+        labels: match volume.labels().is_empty() {
+            true => None,
+            false => Some(volume.labels().to_owned()),
+        },
+        runtime: volume.runtime().to_string(),
+    }
+}
+
+pub fn volumes_to_summaries(volumes: &[RuntimeVolume]) -> Vec<ContainerVolumeSummary> {
+    volumes.iter().map(volume_to_summary).collect()
+}
+
+pub fn network_to_summary(network: &RuntimeNetwork) -> ContainerNetworkSummary {
+    ContainerNetworkSummary {
+        id: network.id().to_string(),
+        name: network.name().to_string(),
+        driver: optional_string(network.driver()),
+        scope: optional_string(network.scope()),
+        internal: network.internal(),
+        subnets: match network.subnets().is_empty() {
+            true => None,
+            false => Some(network.subnets().to_owned()),
+        },
+        runtime: network.runtime().to_string(),
+    }
+}
+
+pub fn networks_to_summaries(networks: &[RuntimeNetwork]) -> Vec<ContainerNetworkSummary> {
+    networks.iter().map(network_to_summary).collect()
+}
+
+fn disk_usage_entry_to_summary(entry: &containers::DiskUsageEntry) -> DiskUsageEntry {
+    DiskUsageEntry {
+        total: entry.total() as i64,
+        active: entry.active() as i64,
+        size: entry.size() as i64,
+        reclaimable: entry.reclaimable() as i64,
+    }
+}
+
+pub fn disk_usage_to_summary(usage: &DiskUsage) -> ContainerDiskUsageSummary {
+    ContainerDiskUsageSummary {
+        runtime: usage.runtime().to_string(),
+        images: disk_usage_entry_to_summary(usage.images()),
+        containers: disk_usage_entry_to_summary(usage.containers()),
+        volumes: disk_usage_entry_to_summary(usage.volumes()),
+        build_cache: disk_usage_entry_to_summary(usage.build_cache()),
+    }
+}
+
+pub fn disk_usages_to_summaries(usages: &[DiskUsage]) -> Vec<ContainerDiskUsageSummary> {
+    usages.iter().map(disk_usage_to_summary).collect()
+}
+
+pub async fn list_images(input: ListImagesInput, state: Extension<Arc<State>>) -> Result<ListImagesOutput, error::ListImagesError> {
+    let ctl = state.controller.lock().await;
+    let mut summaries = images_to_summaries(ctl.images());
+    if let Some(dangling) = input.dangling {
+        summaries.retain(|i| i.dangling == dangling);
+    }
+
+    let output = ListImagesOutput { summaries };
+    Ok(output)
+}
+
+pub async fn list_container_volumes(_input: ListContainerVolumesInput, state: Extension<Arc<State>>) -> Result<ListContainerVolumesOutput, error::ListContainerVolumesError> {
+    let ctl = state.controller.lock().await;
+    let summaries = volumes_to_summaries(ctl.runtime_volumes());
+
+    let output = ListContainerVolumesOutput { summaries };
+    Ok(output)
+}
+
+pub async fn list_container_networks(_input: ListContainerNetworksInput, state: Extension<Arc<State>>) -> Result<ListContainerNetworksOutput, error::ListContainerNetworksError> {
+    let ctl = state.controller.lock().await;
+    let summaries = networks_to_summaries(ctl.runtime_networks());
+
+    let output = ListContainerNetworksOutput { summaries };
+    Ok(output)
+}
+
+pub async fn get_container_disk_usage(_input: GetContainerDiskUsageInput, state: Extension<Arc<State>>) -> Result<GetContainerDiskUsageOutput, error::GetContainerDiskUsageError> {
+    let ctl = state.controller.lock().await;
+    let summaries = disk_usages_to_summaries(ctl.disk_usage());
+
+    let output = GetContainerDiskUsageOutput { summaries };
+    Ok(output)
+}
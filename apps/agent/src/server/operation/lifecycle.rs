@@ -0,0 +1,154 @@
+use std::sync::Arc;
+
+use aws_smithy_http_server::Extension;
+use containers::{ContainerError, Containers};
+use geth_agent_server::{
+    error::{self, ConflictException, InternalServerException, ResourceNotFoundException},
+    input::{PauseContainerInput, RemoveContainerInput, RestartContainerInput, StartContainerInput, StopContainerInput, UnpauseContainerInput},
+    output::{PauseContainerOutput, RemoveContainerOutput, RestartContainerOutput, StartContainerOutput, StopContainerOutput, UnpauseContainerOutput},
+};
+use log::info;
+
+use crate::server::http::State;
+
+/// Maps a runtime failure onto whichever modeled error the operation uses.
+pub(super) fn map_error<E>(
+    err: ContainerError,
+    not_found: fn(ResourceNotFoundException) -> E,
+    conflict: fn(ConflictException) -> E,
+    internal: fn(InternalServerException) -> E,
+) -> E {
+    match err {
+        ContainerError::NotFound(message) => not_found(ResourceNotFoundException { message }),
+        ContainerError::Conflict(message) => conflict(ConflictException { message }),
+        ContainerError::Runtime(message) => internal(InternalServerException { message }),
+    }
+}
+
+/// Returns the container runtime, or a not found error when the agent has no
+/// runtime to manage containers with.
+pub(super) async fn runtime(state: &State, id: &str) -> Result<Containers, ContainerError> {
+    match state.controller.lock().await.container_runtime() {
+        Some(r) => Ok(r),
+        None => Err(ContainerError::NotFound(format!("Container {} not found", id))),
+    }
+}
+
+pub async fn start_container(input: StartContainerInput, state: Extension<Arc<State>>) -> Result<StartContainerOutput, error::StartContainerError> {
+    info!("Starting container {}", input.id);
+    let result = match runtime(&state, &input.id).await {
+        Ok(r) => r.start_container(&input.id).await,
+        Err(e) => Err(e),
+    };
+
+    match result {
+        Ok(_) => {
+            state.controller.lock().await.refresh_container(&input.id).await;
+            Ok(StartContainerOutput {})
+        },
+        Err(e) => Err(map_error(e, error::StartContainerError::ResourceNotFoundException, error::StartContainerError::ConflictException, error::StartContainerError::InternalServerException)),
+    }
+}
+
+pub async fn stop_container(input: StopContainerInput, state: Extension<Arc<State>>) -> Result<StopContainerOutput, error::StopContainerError> {
+    info!("Stopping container {}", input.id);
+    let timeout = input.timeout.map(|t| t as i64);
+    let result = match runtime(&state, &input.id).await {
+        Ok(r) => r.stop_container(&input.id, timeout).await,
+        Err(e) => Err(e),
+    };
+
+    match result {
+        Ok(_) => {
+            state.controller.lock().await.refresh_container(&input.id).await;
+            Ok(StopContainerOutput {})
+        },
+        Err(e) => Err(map_error(e, error::StopContainerError::ResourceNotFoundException, error::StopContainerError::ConflictException, error::StopContainerError::InternalServerException)),
+    }
+}
+
+pub async fn restart_container(input: RestartContainerInput, state: Extension<Arc<State>>) -> Result<RestartContainerOutput, error::RestartContainerError> {
+    info!("Restarting container {}", input.id);
+    let timeout = input.timeout.map(|t| t as i64);
+    let result = match runtime(&state, &input.id).await {
+        Ok(r) => r.restart_container(&input.id, timeout).await,
+        Err(e) => Err(e),
+    };
+
+    match result {
+        Ok(_) => {
+            state.controller.lock().await.refresh_container(&input.id).await;
+            Ok(RestartContainerOutput {})
+        },
+        Err(e) => Err(map_error(e, error::RestartContainerError::ResourceNotFoundException, error::RestartContainerError::ConflictException, error::RestartContainerError::InternalServerException)),
+    }
+}
+
+pub async fn pause_container(input: PauseContainerInput, state: Extension<Arc<State>>) -> Result<PauseContainerOutput, error::PauseContainerError> {
+    info!("Pausing container {}", input.id);
+    let result = match runtime(&state, &input.id).await {
+        Ok(r) => r.pause_container(&input.id).await,
+        Err(e) => Err(e),
+    };
+
+    match result {
+        Ok(_) => {
+            state.controller.lock().await.refresh_container(&input.id).await;
+            Ok(PauseContainerOutput {})
+        },
+        Err(e) => Err(map_error(e, error::PauseContainerError::ResourceNotFoundException, error::PauseContainerError::ConflictException, error::PauseContainerError::InternalServerException)),
+    }
+}
+
+pub async fn unpause_container(input: UnpauseContainerInput, state: Extension<Arc<State>>) -> Result<UnpauseContainerOutput, error::UnpauseContainerError> {
+    info!("Unpausing container {}", input.id);
+    let result = match runtime(&state, &input.id).await {
+        Ok(r) => r.unpause_container(&input.id).await,
+        Err(e) => Err(e),
+    };
+
+    match result {
+        Ok(_) => {
+            state.controller.lock().await.refresh_container(&input.id).await;
+            Ok(UnpauseContainerOutput {})
+        },
+        Err(e) => Err(map_error(e, error::UnpauseContainerError::ResourceNotFoundException, error::UnpauseContainerError::ConflictException, error::UnpauseContainerError::InternalServerException)),
+    }
+}
+
+pub async fn remove_container(input: RemoveContainerInput, state: Extension<Arc<State>>) -> Result<RemoveContainerOutput, error::RemoveContainerError> {
+    info!("Removing container {}", input.id);
+    let force = input.force.unwrap_or(false);
+    let volumes = input.volumes.unwrap_or(false);
+    let result = match runtime(&state, &input.id).await {
+        Ok(r) => r.remove_container(&input.id, force, volumes).await,
+        Err(e) => Err(e),
+    };
+
+    match result {
+        Ok(_) => {
+            state.controller.lock().await.forget_container(&input.id);
+            Ok(RemoveContainerOutput {})
+        },
+        Err(e) => Err(map_error(e, error::RemoveContainerError::ResourceNotFoundException, error::RemoveContainerError::ConflictException, error::RemoveContainerError::InternalServerException)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use containers::ContainerError;
+    use geth_agent_server::error::StopContainerError;
+
+    use super::map_error;
+
+    fn map(err: ContainerError) -> StopContainerError {
+        map_error(err, StopContainerError::ResourceNotFoundException, StopContainerError::ConflictException, StopContainerError::InternalServerException)
+    }
+
+    #[test]
+    fn maps_runtime_errors_to_modeled_errors() {
+        assert!(matches!(map(ContainerError::NotFound("gone".to_string())), StopContainerError::ResourceNotFoundException(e) if e.message == "gone"));
+        assert!(matches!(map(ContainerError::Conflict("busy".to_string())), StopContainerError::ConflictException(e) if e.message == "busy"));
+        assert!(matches!(map(ContainerError::Runtime("boom".to_string())), StopContainerError::InternalServerException(e) if e.message == "boom"));
+    }
+}
@@ -0,0 +1,49 @@
+use std::{sync::Arc, time::{SystemTime, UNIX_EPOCH}};
+
+use aws_smithy_http_server::Extension;
+use geth_agent_server::{output::GetMetricHistoryOutput, input::GetMetricHistoryInput, error::{self, InvalidInputException}, model::{MetricName, MetricPoint}};
+
+use crate::{server::http::State, stats::history::Metric};
+
+const DEFAULT_WINDOW: i64 = 60 * 60;
+
+fn metric_from_name(name: &MetricName) -> Option<Metric> {
+    match name {
+        MetricName::CpuUtilization => Some(Metric::CpuUtilization),
+        MetricName::MemoryUsed => Some(Metric::MemoryUsed),
+        MetricName::SwapUsed => Some(Metric::SwapUsed),
+        MetricName::NetworkReceived => Some(Metric::NetworkReceived),
+        MetricName::NetworkTransmitted => Some(Metric::NetworkTransmitted),
+        MetricName::StorageUsed => Some(Metric::StorageUsed),
+        _ => None,
+    }
+}
+
+fn invalid_input(message: String) -> error::GetMetricHistoryError {
+    error::GetMetricHistoryError::InvalidInputException(InvalidInputException { message })
+}
+
+pub async fn get_metric_history(input: GetMetricHistoryInput, state: Extension<Arc<State>>) -> Result<GetMetricHistoryOutput, error::GetMetricHistoryError> {
+    let metric = match metric_from_name(&input.metric) {
+        Some(m) => m,
+        None => return Err(invalid_input(format!("Unknown metric {}", input.metric.as_str()))),
+    };
+
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs() as i64).unwrap_or_default();
+    let end = input.end.unwrap_or(now);
+    let start = input.start.unwrap_or(end - DEFAULT_WINDOW);
+    if start > end {
+        return Err(invalid_input(format!("start {} is after end {}", start, end)));
+    }
+
+    let ctl = state.controller.lock().await;
+    let points = ctl.metric_history(metric, start, end, input.step)
+        .iter()
+        .map(|p| MetricPoint { timestamp: p.time, value: p.value })
+        .collect();
+
+    Ok(GetMetricHistoryOutput {
+        metric: input.metric,
+        points,
+    })
+}
@@ -5,4 +5,11 @@ pub mod cpu;
 pub mod disk;
 pub mod network;
 pub mod volume;
-pub mod container;
\ No newline at end of file
+pub mod container;
+pub mod lifecycle;
+pub mod exec;
+pub mod inventory;
+pub mod stack;
+pub mod metrics;
+pub mod topology;
+pub mod pci;
\ No newline at end of file
@@ -5,7 +5,7 @@ use geth_agent_server::{output::GetOverviewOutput, model::{OverviewSummary, Disk
 
 use crate::server::http::State;
 
-use super::{system::system_to_summary, volume::volumes_to_summaries, memory::memory_to_summary, cpu::cpu_to_summary, network::network_interfaces_to_summaries, disk::disks_to_summaries, container::containers_to_summaries};
+use super::{system::system_to_summary, volume::volumes_to_summaries, memory::memory_to_summary, cpu::cpu_to_summary, network::network_interfaces_to_summaries, disk::disks_to_summaries, container::containers_to_summaries, inventory::{images_to_summaries, volumes_to_summaries as container_volumes_to_summaries, networks_to_summaries, disk_usages_to_summaries}, stack::stacks_to_summaries};
 
 
 pub async fn get_overview(_input: GetOverviewInput, state: Extension<Arc<State>>) -> Result<GetOverviewOutput, error::GetOverviewError> {
@@ -23,8 +23,13 @@ pub async fn get_overview(_input: GetOverviewInput, state: Extension<Arc<State>>
     let memory = memory_to_summary(mem);
     let system = system_to_summary(sys);
     let volumes = volumes_to_summaries(storage.volumes());
-    let disks: Vec<DiskSummary> = disks_to_summaries(disks);
+    let disks: Vec<DiskSummary> = disks_to_summaries(disks, ctl.disk_health());
     let containers = containers_to_summaries(conts);
+    let images = images_to_summaries(ctl.images());
+    let container_volumes = container_volumes_to_summaries(ctl.runtime_volumes());
+    let container_networks = networks_to_summaries(ctl.runtime_networks());
+    let container_disk_usage = disk_usages_to_summaries(ctl.disk_usage());
+    let stacks = stacks_to_summaries(&ctl.stacks());
 
     let sum = OverviewSummary {
         network,
@@ -36,6 +41,26 @@ pub async fn get_overview(_input: GetOverviewInput, state: Extension<Arc<State>>
         containers: match containers.len() {
             0 => None,
             _ => Some(containers)
+        },
+        images: match images.len() {
+            0 => None,
+            _ => Some(images)
+        },
+        container_volumes: match container_volumes.len() {
+            0 => None,
+            _ => Some(container_volumes)
+        },
+        container_networks: match container_networks.len() {
+            0 => None,
+            _ => Some(container_networks)
+        },
+        container_disk_usage: match container_disk_usage.len() {
+            0 => None,
+            _ => Some(container_disk_usage)
+        },
+        stacks: match stacks.len() {
+            0 => None,
+            _ => Some(stacks)
         }
     };
 
@@ -0,0 +1,43 @@
+use std::sync::Arc;
+
+use aws_smithy_http_server::Extension;
+use geth_agent_server::{output::ListPciDevicesOutput, input::ListPciDevicesInput, error::{self, InternalServerException}, model::PciDeviceSummary};
+use hw_info::{load_pci_devices, PciDevice};
+use log::warn;
+
+use crate::server::http::State;
+
+pub async fn list_pci_devices(_input: ListPciDevicesInput, _state: Extension<Arc<State>>) -> Result<ListPciDevicesOutput, error::ListPciDevicesError> {
+    let devices = match load_pci_devices() {
+        Ok(d) => d,
+        Err(e) => return Err(error::ListPciDevicesError::InternalServerException(InternalServerException { message: format!("Failed to read PCI devices: {}", e) })),
+    };
+    for e in devices.errors() {
+        warn!("Skipping PCI device: {}", e);
+    }
+
+    Ok(ListPciDevicesOutput {
+        summaries: devices.entries().iter().map(pci_device_to_summary).collect(),
+    })
+}
+
+pub fn pci_device_to_summary(device: &PciDevice) -> PciDeviceSummary {
+    PciDeviceSummary {
+        address: device.address().to_owned(),
+        vendor_id: format!("0x{:04x}", device.vendor_id()),
+        device_id: format!("0x{:04x}", device.device_id()),
+        subsystem_vendor_id: device.subsystem_vendor_id().map(|id| format!("0x{:04x}", id)),
+        subsystem_device_id: device.subsystem_device_id().map(|id| format!("0x{:04x}", id)),
+        class_id: format!("0x{:06x}", device.class_id()),
+        vendor: device.vendor().cloned(),
+        device: device.device().cloned(),
+        class: device.class().cloned(),
+        subclass: device.subclass().cloned(),
+        driver: device.driver().cloned(),
+        numa_node: device.numa_node().map(|n| n as i32),
+        link_speed: device.link_speed().cloned(),
+        link_width: device.link_width().map(|w| w as i32),
+        max_link_speed: device.max_link_speed().cloned(),
+        max_link_width: device.max_link_width().map(|w| w as i32),
+    }
+}
@@ -0,0 +1,62 @@
+use std::sync::Arc;
+
+use aws_smithy_http_server::Extension;
+use containers::Stack;
+use geth_agent_server::{output::{ListStacksOutput, GetStackOutput}, input::{ListStacksInput, GetStackInput}, error::{self, ResourceNotFoundException}, model::{StackSummary, StackMember, StackHealth}};
+
+use crate::server::http::State;
+
+use super::container::container_state_to_summary;
+
+pub fn stack_to_summary(stack: &Stack) -> StackSummary {
+    let members = stack.members().iter().map(|m| StackMember {
+        id: m.id().to_string(),
+        name: m.name().to_string(),
+        service: m.service().map(String::from),
+        state: container_state_to_summary(m.state()),
+    }).collect();
+    let health = match stack.health() {
+        containers::StackHealth::Healthy => StackHealth::Healthy,
+        containers::StackHealth::Degraded => StackHealth::Degraded,
+        containers::StackHealth::Stopped => StackHealth::Stopped,
+    };
+
+    StackSummary {
+        project: stack.project().to_string(),
+        runtime: stack.runtime().to_string(),
+        health,
+        services: stack.services().iter().map(|s| s.to_string()).collect(),
+        running: stack.running() as i32,
+        members,
+        working_dir: stack.working_dir().map(String::from),
+        config_files: match stack.config_files().is_empty() {
+            true => None,
+            false => Some(stack.config_files().to_owned()),
+        },
+    }
+}
+
+pub fn stacks_to_summaries(stacks: &[Stack]) -> Vec<StackSummary> {
+    stacks.iter().map(stack_to_summary).collect()
+}
+
+pub async fn list_stacks(_input: ListStacksInput, state: Extension<Arc<State>>) -> Result<ListStacksOutput, error::ListStacksError> {
+    let ctl = state.controller.lock().await;
+    let summaries = stacks_to_summaries(&ctl.stacks());
+
+    let output = ListStacksOutput { summaries };
+    Ok(output)
+}
+
+pub async fn get_stack(input: GetStackInput, state: Extension<Arc<State>>) -> Result<GetStackOutput, error::GetStackError> {
+    let ctl = state.controller.lock().await;
+    let stacks = ctl.stacks();
+
+    let stack = stacks.iter().find(|s| {
+        s.project() == input.project && input.runtime.as_deref().map_or(true, |r| s.runtime() == r)
+    });
+    match stack {
+        Some(s) => Ok(GetStackOutput { summary: stack_to_summary(s) }),
+        None => Err(error::GetStackError::ResourceNotFoundException(ResourceNotFoundException { message: format!("Stack {} not found", input.project) })),
+    }
+}
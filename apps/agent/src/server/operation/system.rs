@@ -1,7 +1,9 @@
 use std::sync::Arc;
 
 use aws_smithy_http_server::Extension;
-use geth_agent_server::{input::GetSystemInput, output::GetSystemOutput, model::SystemSummary, error};
+use geth_agent_server::{input::GetSystemInput, output::GetSystemOutput, model::{SystemSummary, VirtualizationSummary}, error};
+
+use hw_info::Virtualization;
 
 use crate::{server::http::State, stats::system::System};
 
@@ -29,6 +31,7 @@ pub fn system_to_summary(system: &System) -> SystemSummary {
     let hostname = system.hostname().to_owned();
     let boot_time = system.boot_time().to_owned();
     let up_time = system.up_time().to_owned();
+    let virtualization = virtualization_to_summary(system.virtualization());
 
     SystemSummary {
         machine_id,
@@ -40,5 +43,15 @@ pub fn system_to_summary(system: &System) -> SystemSummary {
         hostname,
         boot_time: boot_time as i64,
         up_time: up_time as i64,
+        virtualization: Some(virtualization),
+    }
+}
+
+fn virtualization_to_summary(virt: &Virtualization) -> VirtualizationSummary {
+    VirtualizationSummary {
+        hypervisor: virt.guest_of().map(|h| h.name().to_owned()),
+        container: virt.container().map(|c| c.name().to_owned()),
+        host_hypervisor: virt.host_of().map(|h| h.name().to_owned()),
+        guests: virt.guests() as i32,
     }
 }
\ No newline at end of file
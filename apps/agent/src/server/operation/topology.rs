@@ -0,0 +1,86 @@
+use std::sync::Arc;
+
+use aws_smithy_http_server::Extension;
+use geth_agent_server::{output::GetStorageTopologyOutput, input::GetStorageTopologyInput, error::{self, InternalServerException}, model::{BlockDeviceSummary, BlockDeviceType, RaidState as SmithyRaidState, RaidSummary, VolumePlacementSummary}};
+use hw_info::{load_block_topology, BlockDevice, BlockDeviceKind, BlockTopology, RaidState, RaidStatus};
+use log::warn;
+
+use crate::{server::http::State, stats::disk::Disk};
+
+pub async fn get_storage_topology(_input: GetStorageTopologyInput, state: Extension<Arc<State>>) -> Result<GetStorageTopologyOutput, error::GetStorageTopologyError> {
+    let (devices, errors) = match load_block_topology() {
+        Ok(d) => d.into_parts(),
+        Err(e) => return Err(error::GetStorageTopologyError::InternalServerException(InternalServerException { message: format!("Failed to read block devices: {}", e) })),
+    };
+    for e in errors {
+        warn!("Skipping block device: {}", e);
+    }
+    let topology = BlockTopology::new(devices);
+
+    let ctl = state.controller.lock().await;
+    let volumes = ctl.storage().volumes()
+        .into_iter()
+        .map(|v| volume_to_placement(v, &topology))
+        .collect();
+
+    Ok(GetStorageTopologyOutput {
+        devices: topology.devices().map(block_device_to_summary).collect(),
+        volumes,
+    })
+}
+
+fn volume_to_placement(volume: &Disk, topology: &BlockTopology) -> VolumePlacementSummary {
+    let device = topology.resolve(volume.name());
+
+    VolumePlacementSummary {
+        mount_point: volume.mount_point().to_owned(),
+        device: volume.name().to_owned(),
+        block_device: device.map(|d| d.name().to_owned()),
+        disks: device.map(|d| topology.physical_disks(d.name())).unwrap_or_default(),
+    }
+}
+
+fn block_device_to_summary(device: &BlockDevice) -> BlockDeviceSummary {
+    let t = match device.kind() {
+        BlockDeviceKind::Disk => BlockDeviceType::Disk,
+        BlockDeviceKind::Partition => BlockDeviceType::Partition,
+        BlockDeviceKind::Raid => BlockDeviceType::Raid,
+        BlockDeviceKind::LVM => BlockDeviceType::Lvm,
+        BlockDeviceKind::Crypt => BlockDeviceType::Crypt,
+        BlockDeviceKind::DeviceMapper => BlockDeviceType::DeviceMapper,
+        BlockDeviceKind::Loop => BlockDeviceType::Loop,
+        BlockDeviceKind::Unknown => BlockDeviceType::Unknown,
+    };
+
+    BlockDeviceSummary {
+        name: device.name().to_owned(),
+        r#type: t,
+        mapper_name: device.mapper_name().cloned(),
+        size: device.size(),
+        parents: device.parents().to_owned(),
+        children: device.children().to_owned(),
+        raid: device.raid().map(raid_to_summary),
+    }
+}
+
+fn raid_to_summary(raid: &RaidStatus) -> RaidSummary {
+    let state = match raid.state() {
+        RaidState::Clean => SmithyRaidState::Clean,
+        RaidState::Degraded => SmithyRaidState::Degraded,
+        RaidState::Resyncing => SmithyRaidState::Resyncing,
+        RaidState::Recovering => SmithyRaidState::Recovering,
+        RaidState::Inactive => SmithyRaidState::Inactive,
+    };
+
+    RaidSummary {
+        level: raid.level().to_owned(),
+        state,
+        devices: raid.devices() as i32,
+        working_devices: raid.working() as i32,
+        members: raid.members().to_owned(),
+        failed_members: raid.failed().to_owned(),
+        spare_members: raid.spares().to_owned(),
+        sync_action: raid.sync_action().cloned(),
+        sync_progress: raid.sync_progress(),
+    }
+}
@@ -0,0 +1,282 @@
+use std::{
+    fmt,
+    fs::File,
+    io,
+    io::BufReader,
+    net::SocketAddr,
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll},
+    time::Duration,
+};
+
+use async_stream::stream;
+use aws_smithy_http_server::request::connect_info::Connected;
+use futures::Stream;
+use log::{debug, error, info, warn};
+use rustls::{
+    server::AllowAnyAuthenticatedClient, Certificate, PrivateKey, RootCertStore, ServerConfig,
+};
+use rustls_pemfile::Item;
+use tokio::{
+    io::{AsyncRead, AsyncWrite, ReadBuf},
+    net::{TcpListener, TcpStream},
+    signal::unix::{signal, SignalKind},
+    sync::{mpsc, RwLock},
+};
+use tokio_rustls::{server::TlsStream, TlsAcceptor};
+
+use crate::config::TlsConfig;
+
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Debug)]
+pub enum TlsError {
+    Io(String, io::Error),
+    InvalidPem(String),
+    Rustls(rustls::Error),
+}
+
+impl fmt::Display for TlsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TlsError::Io(path, e) => write!(f, "failed to read {}: {}", path, e),
+            TlsError::InvalidPem(m) => write!(f, "invalid pem: {}", m),
+            TlsError::Rustls(e) => write!(f, "invalid tls configuration: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for TlsError {}
+
+/// A TLS stream accepted by the agent, carrying the peer address so handlers
+/// still receive `ConnectInfo<SocketAddr>`.
+pub struct TlsConnection {
+    stream: TlsStream<TcpStream>,
+    remote_addr: SocketAddr,
+}
+
+impl Connected<&TlsConnection> for SocketAddr {
+    fn connect_info(target: &TlsConnection) -> Self {
+        target.remote_addr
+    }
+}
+
+impl AsyncRead for TlsConnection {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.stream).poll_read(cx, buf)
+    }
+}
+
+impl AsyncWrite for TlsConnection {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.stream).poll_write(cx, buf)
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.stream).poll_flush(cx)
+    }
+
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.stream).poll_shutdown(cx)
+    }
+}
+
+fn open(path: &str) -> Result<BufReader<File>, TlsError> {
+    File::open(path)
+        .map(BufReader::new)
+        .map_err(|e| TlsError::Io(path.to_string(), e))
+}
+
+fn load_certs(path: &str) -> Result<Vec<Certificate>, TlsError> {
+    let certs =
+        rustls_pemfile::certs(&mut open(path)?).map_err(|e| TlsError::Io(path.to_string(), e))?;
+
+    if certs.is_empty() {
+        return Err(TlsError::InvalidPem(format!(
+            "no certificates found in {}",
+            path
+        )));
+    }
+
+    Ok(certs.into_iter().map(Certificate).collect())
+}
+
+fn load_key(path: &str) -> Result<PrivateKey, TlsError> {
+    let items = rustls_pemfile::read_all(&mut open(path)?)
+        .map_err(|e| TlsError::Io(path.to_string(), e))?;
+
+    for item in items {
+        match item {
+            Item::PKCS8Key(k) | Item::RSAKey(k) | Item::ECKey(k) => return Ok(PrivateKey(k)),
+            _ => continue,
+        }
+    }
+
+    Err(TlsError::InvalidPem(format!(
+        "no private key found in {}",
+        path
+    )))
+}
+
+/// Reads the certificate, key and optional client CA from disk and builds a
+/// rustls server config from them.
+pub fn load_server_config(config: &TlsConfig) -> Result<Arc<ServerConfig>, TlsError> {
+    let certs = load_certs(config.cert())?;
+    let key = load_key(config.key())?;
+
+    let builder = ServerConfig::builder().with_safe_defaults();
+
+    let mut server_config = match config.client_ca() {
+        Some(ca) => {
+            let mut roots = RootCertStore::empty();
+            for cert in load_certs(ca)? {
+                roots
+                    .add(&cert)
+                    .map_err(|e| TlsError::InvalidPem(e.to_string()))?;
+            }
+            builder
+                .with_client_cert_verifier(AllowAnyAuthenticatedClient::new(roots).boxed())
+                .with_single_cert(certs, key)
+        }
+        None => builder.with_no_client_auth().with_single_cert(certs, key),
+    }
+    .map_err(TlsError::Rustls)?;
+
+    server_config.alpn_protocols = vec![b"http/1.1".to_vec()];
+
+    Ok(Arc::new(server_config))
+}
+
+/// Swaps in freshly loaded certificates whenever the process receives SIGHUP.
+/// A bad reload is logged and the previous certificates stay in use.
+pub async fn reload_on_hangup(config: TlsConfig, current: Arc<RwLock<Arc<ServerConfig>>>) {
+    let mut hangup = match signal(SignalKind::hangup()) {
+        Ok(s) => s,
+        Err(e) => {
+            error!(
+                "Unable to listen for SIGHUP, certificate reload disabled: {}",
+                e
+            );
+            return;
+        }
+    };
+
+    while hangup.recv().await.is_some() {
+        info!("Received SIGHUP, reloading certificates");
+        match load_server_config(&config) {
+            Ok(c) => {
+                *current.write().await = c;
+                info!("Certificates reloaded");
+            }
+            Err(e) => warn!("Failed to reload certificates, keeping previous: {}", e),
+        }
+    }
+}
+
+/// Accepts TCP connections and completes TLS handshakes off the accept loop,
+/// yielding only established connections to hyper. Each handshake uses
+/// whichever certificates are current when the connection arrives.
+pub fn incoming(
+    listener: TcpListener,
+    current: Arc<RwLock<Arc<ServerConfig>>>,
+) -> impl Stream<Item = Result<TlsConnection, io::Error>> {
+    let (tx, mut rx) = mpsc::channel::<TlsConnection>(64);
+
+    tokio::spawn(async move {
+        loop {
+            let (tcp, remote_addr) = match listener.accept().await {
+                Ok(c) => c,
+                Err(e) => {
+                    warn!("Failed to accept connection: {}", e);
+                    tokio::time::sleep(Duration::from_millis(100)).await;
+                    continue;
+                }
+            };
+
+            let acceptor = TlsAcceptor::from(current.read().await.clone());
+            let tx = tx.clone();
+
+            tokio::spawn(async move {
+                match tokio::time::timeout(HANDSHAKE_TIMEOUT, acceptor.accept(tcp)).await {
+                    Ok(Ok(stream)) => {
+                        let _ = tx
+                            .send(TlsConnection {
+                                stream,
+                                remote_addr,
+                            })
+                            .await;
+                    }
+                    Ok(Err(e)) => debug!("TLS handshake with {} failed: {}", remote_addr, e),
+                    Err(_) => debug!("TLS handshake with {} timed out", remote_addr),
+                }
+            });
+        }
+    });
+
+    stream! {
+        while let Some(conn) = rx.recv().await {
+            yield Ok(conn);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fixture(name: &str) -> String {
+        format!("{}/fixtures/tls/{}", env!("CARGO_MANIFEST_DIR"), name)
+    }
+
+    fn tls_config(cert: &str, key: &str, client_ca: Option<&str>) -> TlsConfig {
+        let mut config = format!("cert = {:?}\nkey = {:?}\n", fixture(cert), fixture(key));
+        if let Some(ca) = client_ca {
+            config.push_str(&format!("client_ca = {:?}\n", fixture(ca)));
+        }
+        toml::from_str(&config).unwrap()
+    }
+
+    #[test]
+    fn loads_cert_and_key() {
+        let config = load_server_config(&tls_config("cert.pem", "key.pem", None)).unwrap();
+        assert_eq!(config.alpn_protocols, vec![b"http/1.1".to_vec()]);
+    }
+
+    #[test]
+    fn loads_client_ca() {
+        assert!(load_server_config(&tls_config("cert.pem", "key.pem", Some("ca.pem"))).is_ok());
+    }
+
+    #[test]
+    fn missing_file_is_an_io_error() {
+        match load_server_config(&tls_config("missing.pem", "key.pem", None)) {
+            Err(TlsError::Io(path, _)) => assert!(path.ends_with("missing.pem")),
+            other => panic!("expected an io error, got {:?}", other.err()),
+        }
+    }
+
+    #[test]
+    fn key_file_without_a_key_is_invalid() {
+        match load_server_config(&tls_config("cert.pem", "cert.pem", None)) {
+            Err(TlsError::InvalidPem(m)) => assert!(m.contains("no private key")),
+            other => panic!("expected invalid pem, got {:?}", other.err()),
+        }
+    }
+
+    #[test]
+    fn cert_file_without_a_cert_is_invalid() {
+        match load_server_config(&tls_config("key.pem", "key.pem", None)) {
+            Err(TlsError::InvalidPem(m)) => assert!(m.contains("no certificates")),
+            other => panic!("expected invalid pem, got {:?}", other.err()),
+        }
+    }
+}
@@ -1,12 +1,16 @@
 use std::collections::HashMap;
+use std::time::{SystemTime, UNIX_EPOCH};
 
 use sysinfo::SystemExt;
-use hw_info::{Disk, load_disks};
-use containers::{Containers, Container};
+use hw_info::{Disk, DiskHealth, load_disks};
+use containers::{Containers, Container, ContainerEvent, ContainerEventAction, ContainerState, ContainerStatistics, DiskUsage, Image, RuntimeConfig, RuntimeNetwork, RuntimeVolume, Stack};
 use sysinfo::System as Sys;
+use log::warn;
 
 use super::cpu::Cpu;
 use super::disk::Storage;
+use super::events::EventHistory;
+use super::history::{Metric, MetricHistory, Point};
 use super::memory::Memory;
 use super::network::Network;
 use super::system::System;
@@ -20,14 +24,21 @@ pub struct SystemController {
     network: Network,
     storage: Storage,
     disks: HashMap<String, Disk>,
-    containers: HashMap<String, Container>
+    disk_health: HashMap<String, DiskHealth>,
+    containers: HashMap<String, Container>,
+    events: EventHistory,
+    images: Vec<Image>,
+    runtime_volumes: Vec<RuntimeVolume>,
+    runtime_networks: Vec<RuntimeNetwork>,
+    disk_usage: Vec<DiskUsage>,
+    history: MetricHistory,
 }
 
 impl SystemController {
-    pub fn new() -> SystemController {
+    pub fn new(runtimes: &[RuntimeConfig]) -> SystemController {
         let mut sys = Sys::new_all();
         sys.refresh_all();
-        let container_controller = Containers::new();
+        let container_controller = Containers::new(runtimes);
         
         let system = System::new(&sys);
         let memory = Memory::new(&sys);
@@ -35,8 +46,17 @@ impl SystemController {
         let network = Network::new(&sys);
         let storage = Storage::new(&sys);
         let mut disks = HashMap::<String, Disk>::new();
-        for disk in load_disks() {
-            disks.insert(disk.get_device().to_string(), disk);
+        match load_disks() {
+            Ok(loaded) => {
+                let (loaded, errors) = loaded.into_parts();
+                for e in errors {
+                    warn!("Skipping physical disk: {}", e);
+                }
+                for disk in loaded {
+                    disks.insert(disk.get_device().to_string(), disk);
+                }
+            }
+            Err(e) => warn!("Failed to load physical disks: {}", e),
         }
         let containers = HashMap::<String, Container>::new();
 
@@ -49,7 +69,14 @@ impl SystemController {
             network,
             storage,
             disks,
+            disk_health: HashMap::new(),
             containers,
+            events: EventHistory::new(),
+            images: Vec::new(),
+            runtime_volumes: Vec::new(),
+            runtime_networks: Vec::new(),
+            disk_usage: Vec::new(),
+            history: MetricHistory::new(),
         }
     }
 
@@ -77,22 +104,95 @@ impl SystemController {
         &self.disks
     }
 
+    pub fn disk_health(&self) -> &HashMap<String, DiskHealth> {
+        &self.disk_health
+    }
+
+    /// Replaces the disks' health with a fresh reading keyed by device.
+    pub fn update_disk_health(&mut self, health: HashMap<String, DiskHealth>) {
+        self.disk_health = health;
+    }
+
     pub fn containers(&self) -> &HashMap<String, Container> {
         &self.containers
     }
 
+    /// Compose stacks formed by the known containers.
+    pub fn stacks(&self) -> Vec<Stack> {
+        Stack::group(self.containers.values())
+    }
+
+    pub fn images(&self) -> &Vec<Image> {
+        &self.images
+    }
+
+    pub fn runtime_volumes(&self) -> &Vec<RuntimeVolume> {
+        &self.runtime_volumes
+    }
+
+    pub fn runtime_networks(&self) -> &Vec<RuntimeNetwork> {
+        &self.runtime_networks
+    }
+
+    pub fn disk_usage(&self) -> &Vec<DiskUsage> {
+        &self.disk_usage
+    }
+
+    /// Replaces the runtimes' images, volumes, networks and disk usage with a
+    /// fresh reading.
+    pub fn update_container_inventory(&mut self, images: Vec<Image>, volumes: Vec<RuntimeVolume>, networks: Vec<RuntimeNetwork>, disk_usage: Vec<DiskUsage>) {
+        self.images = images;
+        self.runtime_volumes = volumes;
+        self.runtime_networks = networks;
+        self.disk_usage = disk_usage;
+    }
+
+    /// A handle to the container runtime, for work that shouldn't hold the
+    /// controller lock such as streaming.
+    pub fn container_runtime(&self) -> Option<Containers> {
+        self.container_controller.clone()
+    }
+
     pub async fn refresh(&mut self) {
         self.refresh_system().await;
         self.refresh_memory().await;
         self.refresh_cpu().await;
         self.refresh_network().await;
         self.refresh_storage().await;
-        self.refresh_containers().await;
+        self.record_history();
+    }
+
+    /// Samples the current metrics into the history.
+    fn record_history(&mut self) {
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs() as i64).unwrap_or_default();
+
+        let cores = self.cpu.cores();
+        let cpu = match cores.len() {
+            0 => 0.0,
+            n => cores.iter().map(|c| *c.usage() as f64).sum::<f64>() / n as f64,
+        };
+        let interfaces: Vec<_> = self.network.network_interfaces().into_iter().filter(|i| !*i.is_virtual()).collect();
+        let received: u64 = interfaces.iter().map(|i| *i.bytes().recieved()).sum();
+        let transmitted: u64 = interfaces.iter().map(|i| *i.bytes().transmitted()).sum();
+        let storage: u64 = self.storage.volumes().iter().map(|v| v.total_space().saturating_sub(*v.available_space())).sum();
+
+        self.history.record(Metric::CpuUtilization, now, cpu);
+        self.history.record(Metric::MemoryUsed, now, *self.memory.memory().used() as f64);
+        self.history.record(Metric::SwapUsed, now, *self.memory.swap().used() as f64);
+        self.history.record(Metric::NetworkReceived, now, received as f64);
+        self.history.record(Metric::NetworkTransmitted, now, transmitted as f64);
+        self.history.record(Metric::StorageUsed, now, storage as f64);
+    }
+
+    /// Recorded points for a metric. See `MetricHistory::query`.
+    pub fn metric_history(&self, metric: Metric, start: i64, end: i64, step: Option<i64>) -> Vec<Point> {
+        self.history.query(metric, start, end, step)
     }
 
     async fn refresh_system(&mut self) {
         self.system_controller.refresh_system();
-        self.system.update_up_time(&self.system_controller)
+        self.system.update_up_time(&self.system_controller);
+        self.system.update_virtualization();
     }
 
     async fn refresh_memory(&mut self) {
@@ -117,13 +217,80 @@ impl SystemController {
         self.storage.update(&self.system_controller);
     }
 
-    async fn refresh_containers(&mut self) {
-        if let Some(ref mut container_controller) = self.container_controller {
-            let containers = container_controller.list_containers().await;
-            for container in containers {
-                self.containers.insert(container.id().to_string(), container);
+    /// Replaces a runtime's containers with a full listing of it, pruning
+    /// containers that no longer exist.
+    pub fn sync_containers(&mut self, runtime: &str, containers: Vec<Container>) {
+        let mut current = HashMap::<String, Container>::new();
+        for container in containers {
+            current.insert(container.id().to_string(), container);
+        }
+        for (id, container) in self.containers.iter() {
+            if container.runtime() == runtime && !current.contains_key(id) {
+                self.events.mark_removed(id);
+            }
+        }
+        self.containers.retain(|_, c| c.runtime() != runtime);
+        self.containers.extend(current);
+    }
+
+    /// The ids of running containers with the runtime each was read from.
+    pub fn running_containers(&self) -> Vec<(String, String)> {
+        self.containers.values()
+            .filter(|c| matches!(c.state(), ContainerState::Running))
+            .map(|c| (c.id().to_string(), c.runtime().to_string()))
+            .collect()
+    }
+
+    /// Stores freshly sampled statistics for containers still being tracked.
+    pub fn update_container_statistics(&mut self, statistics: HashMap<String, ContainerStatistics>) {
+        for (id, stats) in statistics {
+            if let Some(container) = self.containers.get_mut(&id) {
+                container.set_statistics(Some(stats));
             }
         }
     }
 
+    /// Records an event and applies it to the container map. `container` is
+    /// the container as read after the event, or `None` if it is gone.
+    pub fn apply_container_event(&mut self, event: ContainerEvent, container: Option<Container>) {
+        let id = event.id().to_string();
+        let destroyed = *event.action() == ContainerEventAction::Destroy;
+        self.events.record(event);
+
+        match container {
+            Some(c) if !destroyed => {
+                self.containers.insert(id, c);
+            },
+            _ => self.forget_container(&id),
+        }
+    }
+
+    /// Records an event that doesn't change the container, such as an exec.
+    pub fn record_container_event(&mut self, event: ContainerEvent) {
+        self.events.record(event);
+    }
+
+    pub fn container_events(&self, id: &str) -> Option<Vec<ContainerEvent>> {
+        self.events.events(id).map(|e| e.iter().cloned().collect())
+    }
+
+    /// Re-reads a single container after it has been acted on, so callers
+    /// don't see stale state until the next refresh.
+    pub async fn refresh_container(&mut self, id: &str) {
+        if let Some(ref container_controller) = self.container_controller {
+            match container_controller.get_container(id).await {
+                Some(c) => {
+                    self.containers.insert(c.id().to_string(), c);
+                },
+                None => self.forget_container(id),
+            }
+        }
+    }
+
+    pub fn forget_container(&mut self, id: &str) {
+        if self.containers.remove(id).is_some() {
+            self.events.mark_removed(id);
+        }
+    }
+
 }
\ No newline at end of file
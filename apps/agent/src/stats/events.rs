@@ -0,0 +1,140 @@
+use std::collections::{HashMap, VecDeque};
+
+use containers::{ContainerEvent, ContainerEventAction};
+
+const MAX_EVENTS_PER_CONTAINER: usize = 100;
+const MAX_REMOVED_CONTAINERS: usize = 50;
+
+/// Recent events for each container. History for a removed container is kept
+/// until enough other containers have been removed after it. Exec sessions
+/// are left out, as a few health checks or shells would push out everything
+/// else.
+pub struct EventHistory {
+    events: HashMap<String, VecDeque<ContainerEvent>>,
+    removed: VecDeque<String>,
+}
+
+impl EventHistory {
+    pub fn new() -> EventHistory {
+        EventHistory {
+            events: HashMap::new(),
+            removed: VecDeque::new(),
+        }
+    }
+
+    pub fn record(&mut self, event: ContainerEvent) {
+        if event.action().is_exec() {
+            return;
+        }
+        let id = event.id().to_string();
+        let destroyed = *event.action() == ContainerEventAction::Destroy;
+
+        let events = self.events.entry(id.clone()).or_default();
+        if events.len() == MAX_EVENTS_PER_CONTAINER {
+            events.pop_front();
+        }
+        events.push_back(event);
+
+        if destroyed {
+            self.mark_removed(&id);
+        }
+    }
+
+    /// Marks a container as gone, expiring the oldest removed container's
+    /// history once too many are held.
+    pub fn mark_removed(&mut self, id: &str) {
+        if self.removed.iter().any(|r| r == id) {
+            return;
+        }
+        self.removed.push_back(id.to_string());
+        if self.removed.len() > MAX_REMOVED_CONTAINERS {
+            if let Some(expired) = self.removed.pop_front() {
+                self.events.remove(&expired);
+            }
+        }
+    }
+
+    /// Events for a container, oldest first.
+    pub fn events(&self, id: &str) -> Option<&VecDeque<ContainerEvent>> {
+        self.events.get(id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+
+    fn event(id: &str, action: ContainerEventAction) -> ContainerEvent {
+        ContainerEvent::new(id, action, Default::default(), "docker", HashMap::new())
+    }
+
+    #[test]
+    fn keeps_most_recent_events() {
+        let mut history = EventHistory::new();
+        history.record(event("web", ContainerEventAction::Create));
+        for _ in 0..MAX_EVENTS_PER_CONTAINER {
+            history.record(event("web", ContainerEventAction::Start));
+        }
+
+        let events = history.events("web").unwrap();
+        assert_eq!(events.len(), MAX_EVENTS_PER_CONTAINER);
+        assert!(events.iter().all(|e| *e.action() == ContainerEventAction::Start));
+    }
+
+    #[test]
+    fn exec_events_are_not_kept() {
+        let mut history = EventHistory::new();
+        history.record(event("web", ContainerEventAction::Start));
+        for action in ["exec_create: sh -c true", "exec_start: sh -c true", "exec_die", "tasks/exec-added", "tasks/exec-started"] {
+            history.record(event("web", ContainerEventAction::from_action(action)));
+        }
+        history.record(event("web", ContainerEventAction::Other("kill".to_string())));
+
+        let events = history.events("web").unwrap();
+        assert_eq!(events.len(), 2);
+        assert_eq!(*events[1].action(), ContainerEventAction::Other("kill".to_string()));
+    }
+
+    #[test]
+    fn unknown_container_has_no_events() {
+        let history = EventHistory::new();
+        assert!(history.events("web").is_none());
+    }
+
+    #[test]
+    fn destroyed_container_history_outlives_removal() {
+        let mut history = EventHistory::new();
+        history.record(event("web", ContainerEventAction::Start));
+        history.record(event("web", ContainerEventAction::Destroy));
+
+        assert_eq!(history.events("web").unwrap().len(), 2);
+        assert_eq!(history.removed.len(), 1);
+    }
+
+    #[test]
+    fn oldest_removed_container_expires() {
+        let mut history = EventHistory::new();
+        history.record(event("web", ContainerEventAction::Destroy));
+        for i in 0..MAX_REMOVED_CONTAINERS {
+            let id = format!("job-{}", i);
+            history.record(event(&id, ContainerEventAction::Die));
+            history.mark_removed(&id);
+        }
+
+        assert!(history.events("web").is_none());
+        assert!(history.events("job-0").is_some());
+        assert_eq!(history.removed.len(), MAX_REMOVED_CONTAINERS);
+    }
+
+    #[test]
+    fn removing_twice_counts_once() {
+        let mut history = EventHistory::new();
+        history.record(event("web", ContainerEventAction::Stop));
+        history.mark_removed("web");
+        history.mark_removed("web");
+
+        assert_eq!(history.removed.len(), 1);
+    }
+}
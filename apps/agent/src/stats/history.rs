@@ -0,0 +1,224 @@
+use std::collections::{HashMap, VecDeque};
+
+/// Points newer than this are kept at the sampling resolution.
+const RAW_RETENTION: i64 = 60 * 60;
+/// Older points are averaged into buckets of this many seconds.
+const ROLLUP_STEP: i64 = 5 * 60;
+/// Points older than this are dropped.
+const RETENTION: i64 = 24 * 60 * 60;
+
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum Metric {
+    CpuUtilization,
+    MemoryUsed,
+    SwapUsed,
+    NetworkReceived,
+    NetworkTransmitted,
+    StorageUsed,
+}
+
+impl Metric {
+    /// Counters only ever grow, so they are combined by keeping the latest
+    /// value rather than averaging.
+    pub fn is_counter(&self) -> bool {
+        matches!(self, Metric::NetworkReceived | Metric::NetworkTransmitted)
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct Point {
+    /// Unix timestamp in seconds.
+    pub time: i64,
+    pub value: f64,
+}
+
+fn bucket_start(time: i64, step: i64) -> i64 {
+    time - time.rem_euclid(step)
+}
+
+fn combine(points: &[Point], counter: bool) -> f64 {
+    match counter {
+        true => points.last().map(|p| p.value).unwrap_or_default(),
+        false => points.iter().map(|p| p.value).sum::<f64>() / points.len() as f64,
+    }
+}
+
+/// Combines points into `step` second buckets stamped with the bucket's
+/// start time.
+fn downsample(points: &[Point], step: i64, counter: bool) -> Vec<Point> {
+    let mut result = Vec::new();
+    let mut start = 0;
+    while start < points.len() {
+        let bucket = bucket_start(points[start].time, step);
+        let mut end = start;
+        while end < points.len() && bucket_start(points[end].time, step) == bucket {
+            end += 1;
+        }
+        result.push(Point { time: bucket, value: combine(&points[start..end], counter) });
+        start = end;
+    }
+    result
+}
+
+#[derive(Default)]
+struct Series {
+    rolled: VecDeque<Point>,
+    raw: VecDeque<Point>,
+}
+
+impl Series {
+    fn push(&mut self, point: Point, counter: bool) {
+        self.raw.push_back(point);
+
+        // Roll up whole buckets once every point in them is past raw retention.
+        let cutoff = point.time - RAW_RETENTION;
+        while let Some(first) = self.raw.front() {
+            let bucket = bucket_start(first.time, ROLLUP_STEP);
+            if bucket + ROLLUP_STEP > cutoff {
+                break;
+            }
+            let mut expired = Vec::new();
+            while let Some(p) = self.raw.front() {
+                if p.time >= bucket + ROLLUP_STEP {
+                    break;
+                }
+                expired.push(*p);
+                self.raw.pop_front();
+            }
+            self.rolled.push_back(Point { time: bucket, value: combine(&expired, counter) });
+        }
+
+        while let Some(first) = self.rolled.front() {
+            if first.time >= point.time - RETENTION {
+                break;
+            }
+            self.rolled.pop_front();
+        }
+    }
+
+    fn points(&self, start: i64, end: i64) -> Vec<Point> {
+        self.rolled.iter()
+            .chain(self.raw.iter())
+            .filter(|p| p.time >= start && p.time <= end)
+            .copied()
+            .collect()
+    }
+}
+
+/// A bounded in-memory time series for each metric. Recent samples are kept
+/// as taken, older ones are downsampled and anything past a day is dropped.
+pub struct MetricHistory {
+    series: HashMap<Metric, Series>,
+}
+
+impl MetricHistory {
+    pub fn new() -> MetricHistory {
+        MetricHistory {
+            series: HashMap::new(),
+        }
+    }
+
+    pub fn record(&mut self, metric: Metric, time: i64, value: f64) {
+        self.series.entry(metric).or_default().push(Point { time, value }, metric.is_counter());
+    }
+
+    /// Points between `start` and `end` inclusive, oldest first. When `step`
+    /// is set points are combined into buckets of that many seconds.
+    pub fn query(&self, metric: Metric, start: i64, end: i64, step: Option<i64>) -> Vec<Point> {
+        let points = match self.series.get(&metric) {
+            Some(s) => s.points(start, end),
+            None => return Vec::new(),
+        };
+        match step {
+            Some(s) if s > 0 => downsample(&points, s, metric.is_counter()),
+            _ => points,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn series(history: &MetricHistory, metric: Metric) -> &Series {
+        &history.series[&metric]
+    }
+
+    #[test]
+    fn rolls_up_once_bucket_passes_raw_retention() {
+        let mut history = MetricHistory::new();
+        for i in 0..5 {
+            history.record(Metric::CpuUtilization, i * 60, i as f64);
+        }
+
+        // The bucket ends at ROLLUP_STEP, so it stays raw one second short.
+        history.record(Metric::CpuUtilization, ROLLUP_STEP + RAW_RETENTION - 1, 0.0);
+        assert!(series(&history, Metric::CpuUtilization).rolled.is_empty());
+        assert_eq!(series(&history, Metric::CpuUtilization).raw.len(), 6);
+
+        history.record(Metric::CpuUtilization, ROLLUP_STEP + RAW_RETENTION, 0.0);
+        let s = series(&history, Metric::CpuUtilization);
+        assert_eq!(s.rolled.len(), 1);
+        assert_eq!(s.rolled[0].time, 0);
+        assert_eq!(s.rolled[0].value, 2.0);
+        assert_eq!(s.raw.len(), 2);
+    }
+
+    #[test]
+    fn drops_points_past_retention() {
+        let mut history = MetricHistory::new();
+        history.record(Metric::MemoryUsed, 0, 1.0);
+        history.record(Metric::MemoryUsed, ROLLUP_STEP + RAW_RETENTION, 2.0);
+        assert_eq!(history.query(Metric::MemoryUsed, 0, 0, None).len(), 1);
+
+        history.record(Metric::MemoryUsed, RETENTION + 1, 3.0);
+        assert!(history.query(Metric::MemoryUsed, 0, 0, None).is_empty());
+        assert_eq!(history.query(Metric::MemoryUsed, 0, RETENTION + 1, None).len(), 2);
+    }
+
+    #[test]
+    fn counters_keep_last_value() {
+        let mut history = MetricHistory::new();
+        for (i, value) in [100.0, 250.0, 400.0].iter().enumerate() {
+            history.record(Metric::NetworkReceived, i as i64 * 60, *value);
+            history.record(Metric::CpuUtilization, i as i64 * 60, *value);
+        }
+        history.record(Metric::NetworkReceived, ROLLUP_STEP + RAW_RETENTION, 500.0);
+        history.record(Metric::CpuUtilization, ROLLUP_STEP + RAW_RETENTION, 0.0);
+
+        assert_eq!(series(&history, Metric::NetworkReceived).rolled[0].value, 400.0);
+        assert_eq!(series(&history, Metric::CpuUtilization).rolled[0].value, 250.0);
+    }
+
+    #[test]
+    fn query_without_step_returns_points_in_range() {
+        let mut history = MetricHistory::new();
+        for i in 0..10 {
+            history.record(Metric::StorageUsed, i * 10, i as f64);
+        }
+
+        let points = history.query(Metric::StorageUsed, 20, 50, None);
+        let times: Vec<i64> = points.iter().map(|p| p.time).collect();
+        assert_eq!(times, vec![20, 30, 40, 50]);
+        assert!(history.query(Metric::SwapUsed, 0, 100, None).is_empty());
+    }
+
+    #[test]
+    fn query_with_step_combines_buckets() {
+        let mut history = MetricHistory::new();
+        for i in 0..6 {
+            history.record(Metric::CpuUtilization, i * 10, i as f64);
+            history.record(Metric::NetworkTransmitted, i * 10, i as f64);
+        }
+
+        let cpu = history.query(Metric::CpuUtilization, 0, 50, Some(30));
+        assert_eq!(cpu.len(), 2);
+        assert_eq!((cpu[0].time, cpu[0].value), (0, 1.0));
+        assert_eq!((cpu[1].time, cpu[1].value), (30, 4.0));
+
+        let sent = history.query(Metric::NetworkTransmitted, 0, 50, Some(30));
+        assert_eq!((sent[0].value, sent[1].value), (2.0, 5.0));
+
+        assert_eq!(history.query(Metric::CpuUtilization, 0, 50, Some(0)).len(), 6);
+    }
+}
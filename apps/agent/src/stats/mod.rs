@@ -4,4 +4,6 @@ pub mod controller;
 pub mod system;
 pub mod util;
 pub mod disk;
-pub mod cpu;
\ No newline at end of file
+pub mod cpu;
+pub mod events;
+pub mod history;
\ No newline at end of file
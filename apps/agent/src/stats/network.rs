@@ -1,7 +1,7 @@
 use std::collections::HashMap;
 use std::net::IpAddr;
 
-use log::info;
+use log::{info, warn};
 use hw_info::load_nics;
 use sysinfo::{System, SystemExt, NetworkData, NetworkExt};
 
@@ -93,7 +93,7 @@ pub struct NetworkInterface {
     vendor: Option<String>,
     mtu: Option<u16>,
     duplex: Option<String>,
-    speed: Option<u16>,
+    speed: Option<u32>,
 }
 
 impl NetworkInterface {
@@ -123,7 +123,7 @@ impl NetworkInterface {
             None => None,
         };
 
-        let speed: Option<u16> = match phys {
+        let speed: Option<u32> = match phys {
             Some(phys) => Some(phys.speed().to_owned()),
             None => None,
         };
@@ -180,7 +180,7 @@ impl NetworkInterface {
         &self.duplex
     }
 
-    pub fn speed(&self) -> &Option<u16> {
+    pub fn speed(&self) -> &Option<u32> {
         &self.speed
     }
 
@@ -207,7 +207,19 @@ impl Network {
         let mut phys_interfaces = HashMap::new();
         let network_interfaces = NetIface::show().unwrap_or_default();
 
-        let binding = load_nics();
+        let binding = match load_nics() {
+            Ok(nics) => {
+                let (nics, errors) = nics.into_parts();
+                for e in errors {
+                    warn!("Skipping physical network interface: {}", e);
+                }
+                nics
+            }
+            Err(e) => {
+                warn!("Failed to load physical network interfaces: {}", e);
+                Vec::new()
+            }
+        };
         for nic in binding.iter() {
             phys_interfaces.insert(nic.name().clone(), nic);
         }
@@ -2,6 +2,7 @@ use std::io::Read;
 
 use sysinfo::SystemExt;
 use sysinfo::System as Sys;
+use hw_info::{detect_virtualization, Virtualization};
 
 use super::util::handle_optional_string;
 
@@ -15,6 +16,7 @@ pub struct System {
     hostname: String,
     boot_time: u64,
     up_time: u64,
+    virtualization: Virtualization,
 }
 
 fn get_machine_id() -> String {
@@ -48,6 +50,7 @@ impl System {
             hostname,
             boot_time,
             up_time,
+            virtualization: detect_virtualization(),
         }
     }
 
@@ -87,7 +90,17 @@ impl System {
         &self.up_time
     }
 
+    pub fn virtualization(&self) -> &Virtualization {
+        &self.virtualization
+    }
+
     pub fn update_up_time(&mut self, system: &Sys) {
         self.up_time = system.uptime();
     }
+
+    /// Re-detects virtualization so guests started after the agent are
+    /// counted.
+    pub fn update_virtualization(&mut self) {
+        self.virtualization = detect_virtualization();
+    }
 }
\ No newline at end of file
@@ -1,12 +1,20 @@
-use std::{fs, env};
+use std::{env, fmt, fs};
 use serde::Deserialize;
 use toml;
 use log::{warn, debug};
+use smithy_common::auth::{controller::ApiKey, key::hash_key};
 
 #[derive(Debug, Deserialize)]
 pub struct Config {
     server: ServerConfig,
     db: DatabaseConfig,
+    #[serde(default)]
+    health: HealthConfig,
+    #[serde(default)]
+    sync: SyncConfig,
+    #[serde(default)]
+    secrets: SecretsConfig,
+    agent_tls: Option<AgentTlsConfig>,
 }
 
 impl Default for Config {
@@ -14,13 +22,18 @@ impl Default for Config {
         Config {
             server: ServerConfig {
                 port: 8032,
-                allowed_keys: vec![String::from("toes")],
-                no_auth_operations: vec![String::from("Health")],
+                keys: vec![ApiKey::new("default", &hash_key("toes"), None, None)],
+                allowed_keys: Vec::new(),
+                no_auth_operations: default_no_auth_operations(),
             },
             db: DatabaseConfig {
                 database_type: String::from("postgresql"),
                 url: env::var("DATABASE_URL").expect("DATABASE_URL must be set"),
             },
+            health: HealthConfig::default(),
+            sync: SyncConfig::default(),
+            secrets: SecretsConfig::default(),
+            agent_tls: None,
         }
     }
 
@@ -33,22 +46,65 @@ impl Config {
     pub fn database(&self) -> &DatabaseConfig {
         &self.db
     }
+
+    pub fn health(&self) -> &HealthConfig {
+        &self.health
+    }
+
+    pub fn sync(&self) -> &SyncConfig {
+        &self.sync
+    }
+
+    pub fn secrets(&self) -> &SecretsConfig {
+        &self.secrets
+    }
+
+    pub fn agent_tls(&self) -> Option<&AgentTlsConfig> {
+        self.agent_tls.as_ref()
+    }
 }
 
 #[derive(Clone, Debug, Deserialize)]
 pub struct ServerConfig {
     port: u16,
+    #[serde(default)]
+    keys: Vec<ApiKey>,
+    /// Plaintext keys from configs written before keys were hashed. They are
+    /// hashed into `keys` when the config is loaded.
+    #[serde(default)]
     allowed_keys: Vec<String>,
+    #[serde(default = "default_no_auth_operations")]
     no_auth_operations: Vec<String>,
 }
 
+fn default_no_auth_operations() -> Vec<String> {
+    vec![String::from("Health")]
+}
+
 impl ServerConfig {
+    /// Moves plaintext `allowed_keys` into `keys`, allowing each every
+    /// operation as they were before.
+    fn migrate_allowed_keys(&mut self) {
+        if self.allowed_keys.is_empty() {
+            return;
+        }
+        warn!("server.allowed_keys is deprecated, generate hashed server.keys entries with keygen");
+        for (i, key) in self.allowed_keys.drain(..).enumerate() {
+            self.keys.push(ApiKey::new(
+                &format!("allowed-key-{}", i),
+                &hash_key(&key),
+                None,
+                None,
+            ));
+        }
+    }
+
     pub fn get_server_port(&self) -> u16 {
         self.port
     }
 
-    pub fn allowed_keys(&self) -> &Vec<String> {
-        &self.allowed_keys
+    pub fn keys(&self) -> &Vec<ApiKey> {
+        &self.keys
     }
 
     pub fn no_auth_operations(&self) -> &Vec<String> {
@@ -72,33 +128,199 @@ impl DatabaseConfig {
     }
 }
 
+/// Controls how often registered agents are polled and how many consecutive
+/// results are needed before a machine changes state.
+#[derive(Clone, Debug, Deserialize)]
+pub struct HealthConfig {
+    interval: u64,
+    timeout: u64,
+    healthy_threshold: u32,
+    unhealthy_threshold: u32,
+}
+
+impl Default for HealthConfig {
+    fn default() -> Self {
+        HealthConfig {
+            interval: 30000,
+            timeout: 5000,
+            healthy_threshold: 2,
+            unhealthy_threshold: 3,
+        }
+    }
+}
 
-pub fn load_config() -> Config {
+impl HealthConfig {
+    pub fn interval(&self) -> u64 {
+        self.interval
+    }
+
+    pub fn timeout(&self) -> u64 {
+        self.timeout
+    }
+
+    pub fn healthy_threshold(&self) -> u32 {
+        self.healthy_threshold
+    }
+
+    pub fn unhealthy_threshold(&self) -> u32 {
+        self.unhealthy_threshold
+    }
+}
+
+/// Controls how often each machine's inventory is refreshed from its agent.
+#[derive(Clone, Debug, Deserialize)]
+pub struct SyncConfig {
+    enabled: bool,
+    interval: u64,
+}
+
+impl Default for SyncConfig {
+    fn default() -> Self {
+        SyncConfig {
+            enabled: true,
+            interval: 300000,
+        }
+    }
+}
+
+impl SyncConfig {
+    pub fn enabled(&self) -> bool {
+        self.enabled
+    }
+
+    pub fn interval(&self) -> u64 {
+        self.interval
+    }
+}
+
+/// Holds the base64 encoded 32 byte key used to encrypt agent credentials at
+/// rest. Falls back to the `SECRET_KEY` environment variable.
+/// `legacy_agent_key` is the key agents registered before credentials were
+/// stored per machine expect.
+#[derive(Clone, Debug, Default, Deserialize)]
+pub struct SecretsConfig {
+    key: Option<String>,
+    legacy_agent_key: Option<String>,
+}
+
+impl SecretsConfig {
+    pub fn key(&self) -> Result<String, ConfigError> {
+        match &self.key {
+            Some(key) => Ok(key.clone()),
+            None => env::var("SECRET_KEY").map_err(|_| ConfigError::MissingSecretKey),
+        }
+    }
+
+    pub fn legacy_agent_key(&self) -> Option<&String> {
+        self.legacy_agent_key.as_ref()
+    }
+}
+
+/// Paths to the PEM encoded certificate and key presented to agents that
+/// require client certificates.
+#[derive(Clone, Debug, Deserialize)]
+pub struct AgentTlsConfig {
+    cert: String,
+    key: String,
+}
+
+impl AgentTlsConfig {
+    pub fn cert(&self) -> &String {
+        &self.cert
+    }
+
+    pub fn key(&self) -> &String {
+        &self.key
+    }
+}
+
+#[derive(Debug)]
+pub enum ConfigError {
+    Parse(String, toml::de::Error),
+    MissingSecretKey,
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigError::Parse(path, e) => write!(f, "failed to parse config {}: {}", path, e),
+            ConfigError::MissingSecretKey => write!(
+                f,
+                "no secret key is configured, set secrets.key or the SECRET_KEY environment variable"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+/// Loads the config from `CONFIG_PATH`, or `config.toml`. The default config
+/// is only used when there is no file; a file that does not parse is an error.
+pub fn load_config() -> Result<Config, ConfigError> {
     let path = env::var("CONFIG_PATH").unwrap_or_else(|_| "config.toml".to_string());
     debug!("Loading config from: {}", path);
-    let config = fs::read_to_string(path);
+    let config = fs::read_to_string(&path);
     match config {
         Ok(config) => {
             debug!("Loaded config from file");
-            parse_config(config)
-        },
+            parse_config(&config).map_err(|e| ConfigError::Parse(path, e))
+        }
         Err(_) => {
             warn!("Failed to load config from file, using default config");
-            Config::default()
+            Ok(Config::default())
         }
     }
 }
 
-fn parse_config(config: String) -> Config {
-    let config = toml::from_str(&config);
-    match config {
-        Ok(config) => {
-            debug!("Parsed config");
-            return config;
-        },
-        Err(_) => {
-            warn!("Failed to parse config, using default config");
-            return Config::default();
-        }
+fn parse_config(config: &str) -> Result<Config, toml::de::Error> {
+    let mut config: Config = toml::from_str(config)?;
+    debug!("Parsed config");
+    config.server.migrate_allowed_keys();
+    if config.server.keys.is_empty() {
+        warn!("No API keys are configured, only unauthenticated operations can be called");
     }
-}
\ No newline at end of file
+    Ok(config)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::parse_config;
+
+    const REQUIRED: &str =
+        "[db]\ndatabase_type = \"postgresql\"\nurl = \"postgresql://localhost/geth\"\n\n\
+        [secrets]\nkey = \"AAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAA=\"\n";
+
+    #[test]
+    fn allowed_keys_become_hashed_keys() {
+        let config = format!(
+            "[server]\nport = 8032\nallowed_keys = [\"first\"]\n\n{}",
+            REQUIRED
+        );
+        let config = parse_config(&config).unwrap();
+        let names: Vec<&str> = config
+            .get_server()
+            .keys()
+            .iter()
+            .map(|k| k.name())
+            .collect();
+        assert_eq!(names, vec!["allowed-key-0"]);
+        assert_eq!(
+            config.get_server().no_auth_operations(),
+            &vec![String::from("Health")]
+        );
+    }
+
+    #[test]
+    fn missing_secrets_is_not_fatal_to_parsing() {
+        let config = parse_config(
+            "[server]\nport = 8032\n\n[db]\ndatabase_type = \"postgresql\"\nurl = \"postgresql://localhost/geth\"\n",
+        )
+        .unwrap();
+        assert!(config.secrets().legacy_agent_key().is_none());
+    }
+
+    #[test]
+    fn invalid_config_is_an_error() {
+        assert!(parse_config("[server]\nport = \"not a port\"\n").is_err());
+    }
+}
@@ -1,44 +1,91 @@
-use std::sync::Arc;
+use std::{future::Future, sync::Arc, time::Duration};
 
+use chrono::{TimeZone, Utc};
+use geth_agent_client::operation::get_overview::GetOverviewOutput;
 use http::Version;
+use log::{debug, info, warn};
 
 use crate::{
-    model::machine::{AddressVersion, Machine},
-    persistence::{machine_repo::MachinePrismaRepository, repository::Repository},
-    service::agent::AgentService,
+    model::{
+        container::ContainerAction,
+        machine::{AddressVersion, AgentEndpoint, Machine, MachineState, MachineStatusSummary},
+        utilization::{MachineUtilization, MetricPoint},
+    },
+    persistence::{
+        group_repo::GroupPrismaRepository, machine_repo::MachinePrismaRepository,
+        repository::Repository,
+    },
+    service::agent::{error::AgentServiceError, AgentClient, AgentService},
 };
 
+use super::{
+    error::{from_agent_error, ControllerError},
+    health::HealthTracker,
+};
+
+/// How long a request to an agent may take before the agent is treated as
+/// unreachable.
+const AGENT_REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+
 pub struct AgentController {
     service: AgentService,
     repo: MachinePrismaRepository,
+    groups: GroupPrismaRepository,
+    health: HealthTracker,
 }
 
 impl AgentController {
-    pub fn new(service: AgentService, repo: MachinePrismaRepository) -> AgentController {
-        AgentController { service, repo }
+    pub fn new(
+        service: AgentService,
+        repo: MachinePrismaRepository,
+        groups: GroupPrismaRepository,
+        health: HealthTracker,
+    ) -> AgentController {
+        AgentController {
+            service,
+            repo,
+            groups,
+            health,
+        }
     }
 
     pub async fn register_machine(
         &mut self,
-        address: &str,
+        endpoint: AgentEndpoint,
         group: &str,
-    ) -> Result<Machine, String> {
-        let overview = self.service.get_server_overview(address).await;
-
-        match overview {
-            Ok(o) => match o.summary() {
-                Some(s) => {
-                    let machine = Machine::new_from_agent_overview(s, address, group);
-                    let insert_result = self.repo.insert(machine.clone()).await;
-                    match insert_result {
-                        Ok(_) => Ok(machine),
-                        Err(e) => Err(e),
-                    }
-                }
-                None => Err("No summary found".to_string()),
-            },
-            Err(e) => Err(e.to_string()),
+    ) -> Result<Machine, ControllerError> {
+        if self.groups.find_by_id(group.to_string()).await.is_none() {
+            return Err(ControllerError::InvalidInput(format!(
+                "Group {} does not exist",
+                group
+            )));
         }
+
+        self.service.evict(&endpoint);
+
+        let overview = self
+            .service
+            .get_server_overview(&endpoint)
+            .await
+            .map_err(|e| from_agent_error(&endpoint, e))?;
+
+        let summary = match overview.summary() {
+            Some(s) => s,
+            None => {
+                return Err(ControllerError::Agent(format!(
+                    "Agent at {} returned no summary",
+                    endpoint
+                )))
+            }
+        };
+
+        let machine = Machine::new_from_agent_overview(summary, &endpoint, group);
+        self.repo
+            .insert(machine.clone())
+            .await
+            .map_err(ControllerError::Persistence)?;
+
+        Ok(machine)
     }
 
     pub async fn get_machine(&mut self, machine_id: &str) -> Result<Machine, String> {
@@ -49,11 +96,77 @@ impl AgentController {
         }
     }
 
-    // pub async fn get_machine_utilization(&mut self, machine_id: &str) {
-    //     let machine = self.repo.find_by_id(machine_id.to_string()).await;
+    /// Persists the inventory changes in an overview fetched from the
+    /// machine's agent. The machine is reloaded first so changes made while
+    /// the overview was in flight aren't overwritten.
+    pub async fn persist_overview(
+        &mut self,
+        machine_id: &str,
+        overview: &GetOverviewOutput,
+    ) -> Result<Machine, ControllerError> {
+        let machine = match self.repo.find_by_id(machine_id.to_string()).await {
+            Some(m) => m,
+            None => {
+                return Err(ControllerError::NotFound(format!(
+                    "Machine {} not found",
+                    machine_id
+                )))
+            }
+        };
+
+        let summary = match overview.summary() {
+            Some(s) => s,
+            None => {
+                return Err(ControllerError::Agent(format!(
+                    "Agent at {} returned no summary",
+                    machine.endpoint
+                )))
+            }
+        };
 
-    //     let overview = self.service.get_server_overview(endpoint).await;
-    // }
+        let refreshed = machine.refresh_from_agent_overview(summary);
+        self.repo
+            .modify(refreshed.clone())
+            .await
+            .map_err(ControllerError::Persistence)?;
+
+        Ok(refreshed)
+    }
+
+    /// Looks up a machine's agent so a request to it can be made outside
+    /// the controller lock.
+    pub async fn agent_target(&mut self, machine_id: &str) -> Result<AgentTarget, ControllerError> {
+        let machine = match self.repo.find_by_id(machine_id.to_string()).await {
+            Some(m) => m,
+            None => {
+                return Err(ControllerError::NotFound(format!(
+                    "Machine {} not found",
+                    machine_id
+                )))
+            }
+        };
+
+        Ok(AgentTarget {
+            machine_id: machine.id.to_string(),
+            client: self.service.client(&machine.endpoint),
+            endpoint: machine.endpoint,
+        })
+    }
+
+    /// Snapshots every registered machine for a sync. Only this runs under the
+    /// controller lock; the overviews are fetched without it.
+    pub async fn sync_targets(&mut self) -> Vec<AgentTarget> {
+        let machines = self.repo.find_all().await;
+
+        machines
+            .iter()
+            .map(|m| AgentTarget {
+                machine_id: m.id.to_string(),
+                endpoint: m.endpoint.clone(),
+                client: self.service.client(&m.endpoint),
+            })
+            .collect()
+    }
 
     pub async fn list_machines(&mut self) -> Arc<[Machine]> {
         self.repo.find_all().await
@@ -66,4 +179,205 @@ impl AgentController {
             Err(e) => Err(e),
         }
     }
+
+    /// Snapshots every registered machine for a health check. Only this runs
+    /// under the controller lock; the probes themselves run without it.
+    pub async fn health_probes(&mut self) -> Vec<HealthProbe> {
+        let machines = self.repo.find_all().await;
+        let ids: Vec<String> = machines.iter().map(|m| m.id.to_string()).collect();
+        self.health.retain(&ids);
+
+        machines
+            .iter()
+            .map(|m| HealthProbe {
+                machine_id: m.id.to_string(),
+                endpoint: m.endpoint.clone(),
+                state: m.status.state.clone(),
+                client: self.service.client(&m.endpoint),
+            })
+            .collect()
+    }
+
+    /// Feeds a probe's observation into the health tracker and persists the
+    /// resulting state.
+    pub async fn record_health(&mut self, probe: &HealthProbe, observed: MachineState) {
+        let state = self
+            .health
+            .observe(&probe.machine_id, &probe.state, observed);
+
+        if state != probe.state {
+            info!(
+                "Machine {} changed from {:?} to {:?}",
+                probe.machine_id, probe.state, state
+            );
+        }
+
+        let status = MachineStatusSummary {
+            state,
+            last_checked: Utc::now(),
+        };
+        if let Err(e) = self.repo.update_status(&probe.machine_id, &status).await {
+            warn!("Failed to persist status for {}: {}", probe.machine_id, e);
+        }
+    }
+}
+
+/// A machine's agent endpoint and last known state, captured so its health
+/// can be probed outside the controller lock.
+pub struct HealthProbe {
+    machine_id: String,
+    endpoint: AgentEndpoint,
+    state: MachineState,
+    client: Result<AgentClient, AgentServiceError>,
+}
+
+impl HealthProbe {
+    pub async fn run(&self, timeout: Duration) -> MachineState {
+        let client = match &self.client {
+            Ok(c) => c,
+            Err(e) => {
+                debug!("Cannot probe agent at {}: {}", self.endpoint, e);
+                return MachineState::Unknown;
+            }
+        };
+
+        let result = tokio::time::timeout(timeout, AgentService::check_health(client)).await;
+
+        match result {
+            Ok(Ok(o)) if o.success => MachineState::Running,
+            Ok(Ok(_)) => {
+                debug!("Agent at {} reported itself unhealthy", self.endpoint);
+                MachineState::Unknown
+            }
+            Ok(Err(AgentServiceError::Unreachable(_))) => {
+                debug!("Agent at {} is unreachable", self.endpoint);
+                MachineState::Stopped
+            }
+            Ok(Err(e)) => {
+                debug!("Unexpected health response from {}: {}", self.endpoint, e);
+                MachineState::Unknown
+            }
+            Err(_) => {
+                debug!("Health check to {} timed out", self.endpoint);
+                MachineState::Stopped
+            }
+        }
+    }
+}
+
+/// A machine's agent endpoint and client, captured so requests to the agent
+/// can be made outside the controller lock.
+pub struct AgentTarget {
+    pub machine_id: String,
+    endpoint: AgentEndpoint,
+    client: Result<AgentClient, AgentServiceError>,
+}
+
+impl AgentTarget {
+    pub async fn fetch(&self) -> Result<GetOverviewOutput, ControllerError> {
+        let client = self.client()?;
+        self.within(AGENT_REQUEST_TIMEOUT, AgentService::overview(client))
+            .await
+    }
+
+    pub async fn container_action(
+        &self,
+        container_id: &str,
+        action: &ContainerAction,
+    ) -> Result<(), ControllerError> {
+        let client = self.client()?;
+
+        info!(
+            "Requesting {} of container {} on machine {}",
+            action.verb(),
+            container_id,
+            self.machine_id
+        );
+        self.within(
+            AGENT_REQUEST_TIMEOUT + action.grace_period(),
+            AgentService::container_action(client, container_id, action),
+        )
+        .await
+    }
+
+    /// Reads a metric's recorded history from the machine's agent. Times are
+    /// unix timestamps in seconds.
+    pub async fn metric_history(
+        &self,
+        metric: &str,
+        start: Option<i64>,
+        end: Option<i64>,
+        step: Option<i64>,
+    ) -> Result<Vec<MetricPoint>, ControllerError> {
+        if let (Some(s), Some(e)) = (start, end) {
+            if s > e {
+                return Err(ControllerError::InvalidInput(format!(
+                    "start {} is after end {}",
+                    s, e
+                )));
+            }
+        }
+
+        let client = self.client()?;
+        let history = self
+            .within(
+                AGENT_REQUEST_TIMEOUT,
+                AgentService::metric_history(client, metric, start, end, step),
+            )
+            .await?;
+
+        Ok(history
+            .points()
+            .unwrap_or_default()
+            .iter()
+            .map(|p| MetricPoint {
+                timestamp: Utc
+                    .timestamp_opt(p.timestamp().unwrap_or(0), 0)
+                    .single()
+                    .unwrap_or_default(),
+                value: p.value().unwrap_or(0.0),
+            })
+            .collect())
+    }
+
+    pub async fn utilization(&self) -> Result<MachineUtilization, ControllerError> {
+        let overview = self.fetch().await?;
+
+        match overview.summary() {
+            Some(s) => Ok(MachineUtilization::new_from_agent_overview(
+                &self.machine_id,
+                s,
+            )),
+            None => Err(ControllerError::Agent(format!(
+                "Agent at {} returned no summary",
+                self.endpoint
+            ))),
+        }
+    }
+
+    fn client(&self) -> Result<&AgentClient, ControllerError> {
+        self.client.as_ref().map_err(|e| {
+            ControllerError::InvalidInput(format!(
+                "Agent at {} is misconfigured: {}",
+                self.endpoint, e
+            ))
+        })
+    }
+
+    /// Runs a request to the agent, treating one that doesn't finish within
+    /// `timeout` as unreachable.
+    async fn within<T>(
+        &self,
+        timeout: Duration,
+        request: impl Future<Output = Result<T, AgentServiceError>>,
+    ) -> Result<T, ControllerError> {
+        match tokio::time::timeout(timeout, request).await {
+            Ok(result) => result.map_err(|e| from_agent_error(&self.endpoint, e)),
+            Err(_) => Err(ControllerError::AgentUnreachable(format!(
+                "Agent at {} did not respond within {}s",
+                self.endpoint,
+                timeout.as_secs()
+            ))),
+        }
+    }
 }
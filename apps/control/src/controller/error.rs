@@ -0,0 +1,49 @@
+use std::{error::Error, fmt};
+
+use crate::{model::machine::AgentEndpoint, service::agent::error::AgentServiceError};
+
+#[derive(Debug)]
+pub enum ControllerError {
+    NotFound(String),
+    InvalidInput(String),
+    Conflict(String),
+    AgentUnreachable(String),
+    Agent(String),
+    Persistence(String),
+}
+
+impl fmt::Display for ControllerError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ControllerError::NotFound(m) => write!(f, "{}", m),
+            ControllerError::InvalidInput(m) => write!(f, "{}", m),
+            ControllerError::Conflict(m) => write!(f, "{}", m),
+            ControllerError::AgentUnreachable(m) => write!(f, "{}", m),
+            ControllerError::Agent(m) => write!(f, "{}", m),
+            ControllerError::Persistence(m) => write!(f, "{}", m),
+        }
+    }
+}
+
+impl Error for ControllerError {}
+
+/// Maps a failed agent call to a controller error, separating agents that
+/// could not be reached from agents that answered with an error.
+pub fn from_agent_error(endpoint: &AgentEndpoint, err: AgentServiceError) -> ControllerError {
+    match err {
+        AgentServiceError::Unreachable(_) => {
+            ControllerError::AgentUnreachable(format!("Agent at {} is unreachable", endpoint))
+        }
+        AgentServiceError::InvalidAddress(_)
+        | AgentServiceError::InvalidCaBundle(_)
+        | AgentServiceError::InvalidClientIdentity(_) => ControllerError::InvalidInput(format!(
+            "Agent at {} is misconfigured: {}",
+            endpoint, err
+        )),
+        AgentServiceError::NotFound(m) => ControllerError::NotFound(m),
+        AgentServiceError::Conflict(m) => ControllerError::Conflict(m),
+        AgentServiceError::Request(_) => {
+            ControllerError::Agent(format!("Agent at {} returned an error: {}", endpoint, err))
+        }
+    }
+}
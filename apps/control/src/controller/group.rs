@@ -0,0 +1,96 @@
+use std::sync::Arc;
+
+use crate::{
+    model::group::Group,
+    persistence::{
+        group_repo::GroupPrismaRepository, machine_repo::MachinePrismaRepository,
+        repository::Repository,
+    },
+};
+
+use super::error::ControllerError;
+
+pub struct GroupController {
+    repo: GroupPrismaRepository,
+    machines: MachinePrismaRepository,
+}
+
+impl GroupController {
+    pub fn new(repo: GroupPrismaRepository, machines: MachinePrismaRepository) -> GroupController {
+        GroupController { repo, machines }
+    }
+
+    pub async fn create_group(
+        &mut self,
+        name: &str,
+        platform: Option<&str>,
+        location: Option<&str>,
+    ) -> Result<Group, ControllerError> {
+        if name.trim().is_empty() {
+            return Err(ControllerError::InvalidInput(
+                "Group name cannot be empty".to_string(),
+            ));
+        }
+
+        if self.repo.find_by_name(name).await.is_some() {
+            return Err(ControllerError::InvalidInput(format!(
+                "Group {} already exists",
+                name
+            )));
+        }
+
+        let group = Group::new(name, platform, location);
+        self.repo
+            .insert(group.clone())
+            .await
+            .map_err(ControllerError::Persistence)?;
+
+        Ok(group)
+    }
+
+    pub async fn get_group(&mut self, group_id: &str) -> Result<Group, ControllerError> {
+        match self.repo.find_by_id(group_id.to_string()).await {
+            Some(g) => Ok(g),
+            None => Err(ControllerError::NotFound(format!(
+                "Group {} not found",
+                group_id
+            ))),
+        }
+    }
+
+    pub async fn list_groups(&mut self) -> Arc<[Group]> {
+        self.repo.find_all().await
+    }
+
+    /// Deletes a group, refusing while any machine is still registered to it.
+    /// The database restricts the delete as well, so a machine registered
+    /// after the count still keeps its group.
+    pub async fn delete_group(&mut self, group_id: &str) -> Result<(), ControllerError> {
+        self.get_group(group_id).await?;
+        self.ensure_empty(group_id).await?;
+
+        if let Err(e) = self.repo.delete(group_id.to_string()).await {
+            self.ensure_empty(group_id).await?;
+            return Err(ControllerError::Persistence(e));
+        }
+
+        Ok(())
+    }
+
+    async fn ensure_empty(&self, group_id: &str) -> Result<(), ControllerError> {
+        let members = self
+            .machines
+            .count_in_group(group_id)
+            .await
+            .map_err(ControllerError::Persistence)?;
+
+        if members > 0 {
+            return Err(ControllerError::InvalidInput(format!(
+                "Group {} still has {} machine(s)",
+                group_id, members
+            )));
+        }
+
+        Ok(())
+    }
+}
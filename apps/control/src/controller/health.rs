@@ -0,0 +1,118 @@
+use std::collections::HashMap;
+
+use crate::model::machine::MachineState;
+
+struct Streak {
+    observed: MachineState,
+    count: u32,
+}
+
+/// Tracks consecutive health observations per machine so a single missed
+/// check doesn't flip a machine between states.
+pub struct HealthTracker {
+    healthy_threshold: u32,
+    unhealthy_threshold: u32,
+    streaks: HashMap<String, Streak>,
+}
+
+impl HealthTracker {
+    pub fn new(healthy_threshold: u32, unhealthy_threshold: u32) -> HealthTracker {
+        HealthTracker {
+            healthy_threshold: healthy_threshold.max(1),
+            unhealthy_threshold: unhealthy_threshold.max(1),
+            streaks: HashMap::new(),
+        }
+    }
+
+    /// Records an observation for a machine and returns the state the machine
+    /// should now be in. The state only changes once the same observation has
+    /// been seen enough times in a row.
+    pub fn observe(
+        &mut self,
+        machine_id: &str,
+        current: &MachineState,
+        observed: MachineState,
+    ) -> MachineState {
+        let streak = self
+            .streaks
+            .entry(machine_id.to_string())
+            .or_insert(Streak {
+                observed: current.clone(),
+                count: 0,
+            });
+
+        if streak.observed == observed {
+            streak.count = streak.count.saturating_add(1);
+        } else {
+            streak.observed = observed.clone();
+            streak.count = 1;
+        }
+
+        if *current == observed {
+            return current.clone();
+        }
+
+        let threshold = match observed {
+            MachineState::Running => self.healthy_threshold,
+            _ => self.unhealthy_threshold,
+        };
+
+        if streak.count >= threshold {
+            observed
+        } else {
+            current.clone()
+        }
+    }
+
+    /// Drops tracking for machines that are no longer registered.
+    pub fn retain(&mut self, machine_ids: &[String]) {
+        self.streaks.retain(|id, _| machine_ids.contains(id));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stays_running_until_threshold() {
+        let mut tracker = HealthTracker::new(2, 3);
+        let current = MachineState::Running;
+
+        assert_eq!(
+            tracker.observe("m-1", &current, MachineState::Stopped),
+            MachineState::Running
+        );
+        assert_eq!(
+            tracker.observe("m-1", &current, MachineState::Stopped),
+            MachineState::Running
+        );
+        assert_eq!(
+            tracker.observe("m-1", &current, MachineState::Stopped),
+            MachineState::Stopped
+        );
+    }
+
+    #[test]
+    fn interrupted_streak_resets() {
+        let mut tracker = HealthTracker::new(2, 2);
+        let current = MachineState::Stopped;
+
+        assert_eq!(
+            tracker.observe("m-1", &current, MachineState::Running),
+            MachineState::Stopped
+        );
+        assert_eq!(
+            tracker.observe("m-1", &current, MachineState::Unknown),
+            MachineState::Stopped
+        );
+        assert_eq!(
+            tracker.observe("m-1", &current, MachineState::Running),
+            MachineState::Stopped
+        );
+        assert_eq!(
+            tracker.observe("m-1", &current, MachineState::Running),
+            MachineState::Running
+        );
+    }
+}
@@ -1 +1,4 @@
 pub mod agent;
+pub mod error;
+pub mod group;
+pub mod health;
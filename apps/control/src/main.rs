@@ -5,16 +5,22 @@ mod persistence;
 mod server;
 mod service;
 
-use config::ServerConfig;
-use persistence::machine_repo::MachinePrismaRepository;
+use config::{HealthConfig, ServerConfig, SyncConfig};
+use persistence::{
+    group_repo::GroupPrismaRepository, machine_repo::MachinePrismaRepository,
+    secret::SecretCipher,
+};
 use prisma::PrismaClient;
 use std::{env, error::Error, sync::Arc};
 use tokio::sync::Mutex;
+use tokio::time::{sleep, Duration};
 
-use crate::controller::agent::AgentController;
+use crate::controller::{agent::AgentController, group::GroupController, health::HealthTracker};
 use dotenv::dotenv;
-use log::info;
+use futures::future::join_all;
+use log::{debug, info, warn};
 use server::http::start_server;
+use service::agent::ClientIdentity;
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn Error>> {
@@ -25,29 +31,116 @@ async fn main() -> Result<(), Box<dyn Error>> {
     }
     env_logger::init();
 
-    let config = config::load_config();
+    let config = config::load_config()?;
 
-    let prisma_conn = PrismaClient::_builder()
-        .with_url(config.database().url().to_owned())
-        .build()
-        .await
-        .unwrap();
+    let prisma_conn = Arc::new(
+        PrismaClient::_builder()
+            .with_url(config.database().url().to_owned())
+            .build()
+            .await
+            .unwrap(),
+    );
 
-    let machine_repo = MachinePrismaRepository::new(prisma_conn);
+    let secrets = SecretCipher::new(&config.secrets().key()?)
+        .map_err(|e| format!("invalid secret key: {}", e))?;
 
-    let agent_service = service::agent::AgentService::new();
+    let machine_repo = MachinePrismaRepository::new(prisma_conn.clone(), secrets.clone());
+    let group_repo = GroupPrismaRepository::new(prisma_conn.clone());
+
+    let migrated = machine_repo
+        .migrate_legacy_endpoints(config.secrets().legacy_agent_key().map(|k| k.as_str()))
+        .await?;
+    if migrated > 0 {
+        info!("Migrated {} machines to stored agent endpoints", migrated);
+    }
+    machine_repo.check_endpoints().await?;
+
+    let identity = match config.agent_tls() {
+        Some(tls) => Some(ClientIdentity::load(tls.cert(), tls.key())?),
+        None => None,
+    };
+    let agent_service = service::agent::AgentService::new(identity);
+
+    let health_tracker = HealthTracker::new(
+        config.health().healthy_threshold(),
+        config.health().unhealthy_threshold(),
+    );
 
     let controller = Arc::new(Mutex::new(AgentController::new(
         agent_service,
         machine_repo,
+        group_repo,
+        health_tracker,
+    )));
+
+    let group_controller = Arc::new(Mutex::new(GroupController::new(
+        GroupPrismaRepository::new(prisma_conn.clone()),
+        MachinePrismaRepository::new(prisma_conn, secrets),
     )));
 
+    info!("Starting health loop");
+    tokio::spawn(health_loop(controller.clone(), config.health().clone()));
+
+    if config.sync().enabled() {
+        info!("Starting sync loop");
+        tokio::spawn(sync_loop(controller.clone(), config.sync().clone()));
+    }
+
     info!("Starting server loop");
-    server_loop(controller, config.get_server().clone()).await;
+    server_loop(controller, group_controller, config.get_server().clone()).await;
 
     Ok(())
 }
 
-async fn server_loop(controller: Arc<Mutex<AgentController>>, config: ServerConfig) {
-    start_server(controller, config).await;
+async fn health_loop(controller: Arc<Mutex<AgentController>>, config: HealthConfig) {
+    let timeout = Duration::from_millis(config.timeout());
+    loop {
+        let probes = controller.lock().await.health_probes().await;
+
+        let observed = join_all(probes.iter().map(|p| p.run(timeout))).await;
+
+        let mut ctl = controller.lock().await;
+        for (probe, state) in probes.iter().zip(observed) {
+            ctl.record_health(probe, state).await;
+        }
+        drop(ctl);
+
+        sleep(Duration::from_millis(config.interval())).await;
+    }
+}
+
+async fn sync_loop(controller: Arc<Mutex<AgentController>>, config: SyncConfig) {
+    loop {
+        sleep(Duration::from_millis(config.interval())).await;
+
+        let targets = controller.lock().await.sync_targets().await;
+
+        let overviews = join_all(targets.iter().map(|t| t.fetch())).await;
+
+        for (target, overview) in targets.iter().zip(overviews) {
+            let result = match overview {
+                Ok(o) => {
+                    controller
+                        .lock()
+                        .await
+                        .persist_overview(&target.machine_id, &o)
+                        .await
+                }
+                Err(e) => Err(e),
+            };
+
+            match result {
+                Ok(_) => debug!("Synced machine {}", target.machine_id),
+                Err(e) => warn!("Failed to sync machine {}: {}", target.machine_id, e),
+            }
+        }
+    }
+}
+
+async fn server_loop(
+    controller: Arc<Mutex<AgentController>>,
+    group_controller: Arc<Mutex<GroupController>>,
+    config: ServerConfig,
+) {
+    start_server(controller, group_controller, config).await;
 }
@@ -0,0 +1,41 @@
+use std::time::Duration;
+
+/// Seconds runtimes wait for a container to exit before killing it when the
+/// request doesn't say.
+const DEFAULT_STOP_TIMEOUT: i32 = 10;
+
+/// A lifecycle change the control plane can ask an agent to make to one of
+/// its containers.
+#[derive(Clone, Debug)]
+pub enum ContainerAction {
+    Start,
+    Stop { timeout: Option<i32> },
+    Restart { timeout: Option<i32> },
+    Pause,
+    Unpause,
+    Remove { force: bool, volumes: bool },
+}
+
+impl ContainerAction {
+    pub fn verb(&self) -> &'static str {
+        match self {
+            ContainerAction::Start => "start",
+            ContainerAction::Stop { .. } => "stop",
+            ContainerAction::Restart { .. } => "restart",
+            ContainerAction::Pause => "pause",
+            ContainerAction::Unpause => "unpause",
+            ContainerAction::Remove { .. } => "remove",
+        }
+    }
+
+    /// How long the runtime may wait for the container to exit before killing
+    /// it. Requests to the agent have to outlast it.
+    pub fn grace_period(&self) -> Duration {
+        match self {
+            ContainerAction::Stop { timeout } | ContainerAction::Restart { timeout } => {
+                Duration::from_secs(timeout.unwrap_or(DEFAULT_STOP_TIMEOUT).max(0) as u64)
+            }
+            _ => Duration::ZERO,
+        }
+    }
+}
@@ -0,0 +1,35 @@
+use std::sync::Arc;
+
+use chrono::{DateTime, Utc};
+use uuid::Uuid;
+
+#[derive(Clone, Debug)]
+pub struct Group {
+    pub(crate) id: Arc<str>,
+    pub(crate) name: Arc<str>,
+    pub(crate) platform: Option<Arc<str>>,
+    pub(crate) location: Option<Arc<str>>,
+    pub(crate) added: DateTime<Utc>,
+}
+
+impl Group {
+    fn make_id() -> Arc<str> {
+        let uuid = Uuid::new_v4();
+        let short_uuid = uuid.simple().to_string();
+        let truncated = &short_uuid[..16];
+
+        let id = format!("g-{}", truncated);
+
+        id.into()
+    }
+
+    pub fn new(name: &str, platform: Option<&str>, location: Option<&str>) -> Group {
+        Group {
+            id: Group::make_id(),
+            name: Arc::from(name),
+            platform: platform.map(Arc::from),
+            location: location.map(Arc::from),
+            added: Utc::now(),
+        }
+    }
+}
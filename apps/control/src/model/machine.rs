@@ -1,11 +1,16 @@
-use std::sync::Arc;
+use std::{fmt, sync::Arc};
 
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, TimeZone, Utc};
 use geth_agent_client::types::{
-    AddressVersion as AgentAddressVersion, DiskInterface as AgentDiskInterface,
-    DiskSummary as AgentDiskSummary, DiskType as AgentDiskType, MemoryTypeSummary,
+    AddressVersion as AgentAddressVersion,
+    ContainerDiskUsageSummary as AgentContainerDiskUsageSummary,
+    ContainerNetworkSummary as AgentContainerNetworkSummary,
+    ContainerSummary as AgentContainerSummary,
+    ContainerVolumeSummary as AgentContainerVolumeSummary, DiskInterface as AgentDiskInterface,
+    DiskSummary as AgentDiskSummary, DiskType as AgentDiskType,
+    DiskUsageEntry as AgentDiskUsageEntry, ImageSummary as AgentImageSummary, MemoryTypeSummary,
     NetworkInterfaceSummary as AgentNetworkInterfaceSummary, OverviewSummary,
-    VolumeSummary as AgentVolumeSummary,
+    StackSummary as AgentStackSummary, VolumeSummary as AgentVolumeSummary,
 };
 use uuid::Uuid;
 
@@ -16,13 +21,82 @@ pub enum MachineType {
     Hypervisor,
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq)]
 pub enum MachineState {
     Running,
     Stopped,
     Unknown,
 }
 
+/// The port agents listen on unless registered with another.
+pub const DEFAULT_AGENT_PORT: u16 = 7032;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum AgentScheme {
+    Http,
+    Https,
+}
+
+impl fmt::Display for AgentScheme {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AgentScheme::Http => write!(f, "http"),
+            AgentScheme::Https => write!(f, "https"),
+        }
+    }
+}
+
+/// Where and how to reach a machine's agent. `ca_bundle` is a PEM encoded
+/// set of certificates trusted in place of the system roots.
+#[derive(Clone)]
+pub struct AgentEndpoint {
+    pub(crate) scheme: AgentScheme,
+    pub(crate) address: Arc<str>,
+    pub(crate) port: u16,
+    pub(crate) api_key: Arc<str>,
+    pub(crate) ca_bundle: Option<Arc<str>>,
+}
+
+impl AgentEndpoint {
+    pub fn new(
+        scheme: AgentScheme,
+        address: &str,
+        port: u16,
+        api_key: &str,
+        ca_bundle: Option<&str>,
+    ) -> AgentEndpoint {
+        AgentEndpoint {
+            scheme,
+            address: Arc::from(address),
+            port,
+            api_key: Arc::from(api_key),
+            ca_bundle: ca_bundle.map(Arc::from),
+        }
+    }
+
+    pub fn authority(&self) -> String {
+        format!("{}:{}", self.address, self.port)
+    }
+}
+
+impl fmt::Display for AgentEndpoint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}://{}:{}", self.scheme, self.address, self.port)
+    }
+}
+
+// The api key is deliberately left out so endpoints can be logged.
+impl fmt::Debug for AgentEndpoint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AgentEndpoint")
+            .field("scheme", &self.scheme)
+            .field("address", &self.address)
+            .field("port", &self.port)
+            .field("ca_bundle", &self.ca_bundle.is_some())
+            .finish()
+    }
+}
+
 #[derive(Clone, Debug)]
 pub struct Tag {
     pub(crate) key: Arc<str>,
@@ -131,12 +205,110 @@ pub struct ContainerSummary {
     pub(crate) image: Arc<str>,
     pub(crate) created: DateTime<Utc>,
     pub(crate) state: Arc<str>,
+    pub(crate) health: Option<Arc<str>>,
+    pub(crate) restart_count: u64,
+    pub(crate) exit_code: Option<i64>,
+    pub(crate) oom_killed: bool,
+}
+
+/// Restarts after which a container that keeps failing is treated as crash
+/// looping.
+const CRASH_LOOP_RESTARTS: u64 = 3;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum ContainerProblem {
+    Unhealthy,
+    CrashLooping,
+    OomKilled,
+}
+
+impl ContainerSummary {
+    /// The most pressing problem with the container, if any. Only containers
+    /// the runtime is still restarting can be crash looping; one left stopped,
+    /// on purpose or because its restart policy gave up, is not.
+    pub fn problem(&self) -> Option<ContainerProblem> {
+        let failing = match &*self.state {
+            "Restarting" => true,
+            "Running" => matches!(self.exit_code, Some(c) if c != 0),
+            _ => false,
+        };
+        if self.restart_count >= CRASH_LOOP_RESTARTS && failing {
+            return Some(ContainerProblem::CrashLooping);
+        }
+        if self.health.as_deref() == Some("Unhealthy") {
+            return Some(ContainerProblem::Unhealthy);
+        }
+        if self.oom_killed {
+            return Some(ContainerProblem::OomKilled);
+        }
+        None
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct ImageSummary {
+    pub(crate) image_id: Arc<str>,
+    pub(crate) runtime: Arc<str>,
+    pub(crate) tags: Arc<[Arc<str>]>,
+    pub(crate) size: u64,
+    pub(crate) created: DateTime<Utc>,
+    pub(crate) dangling: bool,
+    pub(crate) containers: Option<u64>,
+}
+
+#[derive(Clone, Debug)]
+pub struct ContainerVolumeSummary {
+    pub(crate) name: Arc<str>,
+    pub(crate) runtime: Arc<str>,
+    pub(crate) driver: Arc<str>,
+    pub(crate) mount_point: Arc<str>,
+    pub(crate) size: Option<u64>,
+}
+
+#[derive(Clone, Debug)]
+pub struct ContainerNetworkSummary {
+    pub(crate) network_id: Arc<str>,
+    pub(crate) name: Arc<str>,
+    pub(crate) runtime: Arc<str>,
+    pub(crate) internal: bool,
+    pub(crate) subnets: Arc<[Arc<str>]>,
+    pub(crate) driver: Option<Arc<str>>,
+    pub(crate) scope: Option<Arc<str>>,
+}
+
+/// Bytes used by one category of a runtime's storage and how much of it
+/// pruning would free.
+#[derive(Clone, Debug, Default)]
+pub struct DiskUsageEntry {
+    pub(crate) size: u64,
+    pub(crate) reclaimable: u64,
+}
+
+#[derive(Clone, Debug)]
+pub struct ContainerDiskUsageSummary {
+    pub(crate) runtime: Arc<str>,
+    pub(crate) images: DiskUsageEntry,
+    pub(crate) containers: DiskUsageEntry,
+    pub(crate) volumes: DiskUsageEntry,
+    pub(crate) build_cache: DiskUsageEntry,
+}
+
+/// A compose project running on the machine.
+#[derive(Clone, Debug)]
+pub struct StackSummary {
+    pub(crate) project: Arc<str>,
+    pub(crate) runtime: Arc<str>,
+    pub(crate) health: Arc<str>,
+    pub(crate) services: Arc<[Arc<str>]>,
+    pub(crate) running: u64,
+    pub(crate) total: u64,
 }
 
 #[derive(Clone, Debug)]
 pub struct Machine {
     pub(crate) id: Arc<str>,
     pub(crate) address: Arc<str>,
+    pub(crate) endpoint: AgentEndpoint,
     pub(crate) group: Arc<str>,
     pub(crate) status: MachineStatusSummary,
     pub(crate) added: DateTime<Utc>,
@@ -151,6 +323,11 @@ pub struct Machine {
     pub(crate) network_interfaces: Option<Arc<[NetworkInterfaceSummary]>>,
     pub(crate) addresses: Option<Arc<[AddressSummary]>>,
     pub(crate) containers: Option<Arc<[ContainerSummary]>>,
+    pub(crate) images: Option<Arc<[ImageSummary]>>,
+    pub(crate) container_volumes: Option<Arc<[ContainerVolumeSummary]>>,
+    pub(crate) container_networks: Option<Arc<[ContainerNetworkSummary]>>,
+    pub(crate) container_disk_usage: Option<Arc<[ContainerDiskUsageSummary]>>,
+    pub(crate) stacks: Option<Arc<[StackSummary]>>,
 }
 
 impl Machine {
@@ -164,6 +341,23 @@ impl Machine {
         id.into()
     }
 
+    /// Hosts running guests are hypervisors even when they are guests
+    /// themselves, as with nested virtualization.
+    fn get_machine_type_from_summary(overview: &OverviewSummary) -> MachineType {
+        let virt = match overview.system().and_then(|s| s.virtualization()) {
+            Some(v) => v,
+            None => return MachineType::BareMetal,
+        };
+
+        if virt.host_hypervisor().is_some() {
+            MachineType::Hypervisor
+        } else if virt.hypervisor().is_some() {
+            MachineType::VirtualMachine
+        } else {
+            MachineType::BareMetal
+        }
+    }
+
     fn get_memory_value(memory: Option<&MemoryTypeSummary>) -> u64 {
         if memory.is_none() {
             return 0;
@@ -324,16 +518,212 @@ impl Machine {
         Some(summaries.into())
     }
 
+    fn get_containers_from_summary(
+        containers: Option<&[AgentContainerSummary]>,
+    ) -> Option<Arc<[ContainerSummary]>> {
+        containers?;
+
+        let containers = containers.unwrap();
+
+        if containers.is_empty() {
+            return None;
+        }
+
+        let mut summaries = Vec::new();
+
+        for container in containers {
+            summaries.push(ContainerSummary {
+                container_id: Arc::from(container.id().unwrap_or("")),
+                name: Arc::from(container.name().unwrap_or("")),
+                image: Arc::from(container.image().unwrap_or("")),
+                created: Utc
+                    .timestamp_opt(container.created().unwrap_or(0), 0)
+                    .single()
+                    .unwrap_or_default(),
+                state: Arc::from(container.state().map(|s| s.as_str()).unwrap_or("Unknown")),
+                health: container
+                    .health()
+                    .and_then(|h| h.status())
+                    .map(|s| Arc::from(s.as_str())),
+                restart_count: container.restart_count().unwrap_or(0) as u64,
+                exit_code: container.exit_code(),
+                oom_killed: container.oom_killed().unwrap_or(false),
+            });
+        }
+
+        Some(summaries.into())
+    }
+
+    fn get_images_from_summary(
+        images: Option<&[AgentImageSummary]>,
+    ) -> Option<Arc<[ImageSummary]>> {
+        let images = images?;
+        if images.is_empty() {
+            return None;
+        }
+
+        let summaries: Vec<ImageSummary> = images
+            .iter()
+            .map(|image| ImageSummary {
+                image_id: Arc::from(image.id().unwrap_or("")),
+                runtime: Arc::from(image.runtime().unwrap_or("")),
+                tags: image
+                    .tags()
+                    .unwrap_or_default()
+                    .iter()
+                    .map(|t| Arc::from(t.as_str()))
+                    .collect(),
+                size: image.size().unwrap_or(0) as u64,
+                created: Utc
+                    .timestamp_opt(image.created().unwrap_or(0), 0)
+                    .single()
+                    .unwrap_or_default(),
+                dangling: image.dangling().unwrap_or(false),
+                containers: image.containers().map(|c| c as u64),
+            })
+            .collect();
+
+        Some(summaries.into())
+    }
+
+    fn get_container_volumes_from_summary(
+        volumes: Option<&[AgentContainerVolumeSummary]>,
+    ) -> Option<Arc<[ContainerVolumeSummary]>> {
+        let volumes = volumes?;
+        if volumes.is_empty() {
+            return None;
+        }
+
+        let summaries: Vec<ContainerVolumeSummary> = volumes
+            .iter()
+            .map(|volume| ContainerVolumeSummary {
+                name: Arc::from(volume.name().unwrap_or("")),
+                runtime: Arc::from(volume.runtime().unwrap_or("")),
+                driver: Arc::from(volume.driver().unwrap_or("")),
+                mount_point: Arc::from(volume.mount_point().unwrap_or("")),
+                size: volume.size().map(|s| s as u64),
+            })
+            .collect();
+
+        Some(summaries.into())
+    }
+
+    fn get_container_networks_from_summary(
+        networks: Option<&[AgentContainerNetworkSummary]>,
+    ) -> Option<Arc<[ContainerNetworkSummary]>> {
+        let networks = networks?;
+        if networks.is_empty() {
+            return None;
+        }
+
+        let summaries: Vec<ContainerNetworkSummary> = networks
+            .iter()
+            .map(|network| ContainerNetworkSummary {
+                network_id: Arc::from(network.id().unwrap_or("")),
+                name: Arc::from(network.name().unwrap_or("")),
+                runtime: Arc::from(network.runtime().unwrap_or("")),
+                internal: network.internal().unwrap_or(false),
+                subnets: network
+                    .subnets()
+                    .unwrap_or_default()
+                    .iter()
+                    .map(|s| Arc::from(s.as_str()))
+                    .collect(),
+                driver: network.driver().map(Arc::from),
+                scope: network.scope().map(Arc::from),
+            })
+            .collect();
+
+        Some(summaries.into())
+    }
+
+    fn get_disk_usage_entry(entry: Option<&AgentDiskUsageEntry>) -> DiskUsageEntry {
+        match entry {
+            None => DiskUsageEntry::default(),
+            Some(e) => DiskUsageEntry {
+                size: e.size().unwrap_or(0) as u64,
+                reclaimable: e.reclaimable().unwrap_or(0) as u64,
+            },
+        }
+    }
+
+    fn get_container_disk_usage_from_summary(
+        usage: Option<&[AgentContainerDiskUsageSummary]>,
+    ) -> Option<Arc<[ContainerDiskUsageSummary]>> {
+        let usage = usage?;
+        if usage.is_empty() {
+            return None;
+        }
+
+        let summaries: Vec<ContainerDiskUsageSummary> = usage
+            .iter()
+            .map(|u| ContainerDiskUsageSummary {
+                runtime: Arc::from(u.runtime().unwrap_or("")),
+                images: Machine::get_disk_usage_entry(u.images()),
+                containers: Machine::get_disk_usage_entry(u.containers()),
+                volumes: Machine::get_disk_usage_entry(u.volumes()),
+                build_cache: Machine::get_disk_usage_entry(u.build_cache()),
+            })
+            .collect();
+
+        Some(summaries.into())
+    }
+
+    fn get_stacks_from_summary(
+        stacks: Option<&[AgentStackSummary]>,
+    ) -> Option<Arc<[StackSummary]>> {
+        let stacks = stacks?;
+        if stacks.is_empty() {
+            return None;
+        }
+
+        let summaries: Vec<StackSummary> = stacks
+            .iter()
+            .map(|stack| StackSummary {
+                project: Arc::from(stack.project().unwrap_or("")),
+                runtime: Arc::from(stack.runtime().unwrap_or("")),
+                health: Arc::from(stack.health().map(|h| h.as_str()).unwrap_or("Stopped")),
+                services: stack
+                    .services()
+                    .unwrap_or_default()
+                    .iter()
+                    .map(|s| Arc::from(s.as_str()))
+                    .collect(),
+                running: stack.running().unwrap_or(0) as u64,
+                total: stack.members().map(|m| m.len()).unwrap_or(0) as u64,
+            })
+            .collect();
+
+        Some(summaries.into())
+    }
+
+    /// Returns a copy of this machine with its inventory replaced by what the
+    /// agent currently reports, including its machine type. Identity, group,
+    /// tags and status are kept.
+    pub fn refresh_from_agent_overview(&self, overview: &OverviewSummary) -> Machine {
+        let fresh = Machine::new_from_agent_overview(overview, &self.endpoint, &self.group);
+
+        Machine {
+            id: self.id.clone(),
+            status: self.status.clone(),
+            added: self.added,
+            updated: Some(Utc::now()),
+            tags: self.tags.clone(),
+            ..fresh
+        }
+    }
+
     pub fn new_from_agent_overview(
         overview: &OverviewSummary,
-        address: &str,
+        endpoint: &AgentEndpoint,
         group: &str,
     ) -> Machine {
         let now = Utc::now();
 
         Machine {
             id: Machine::make_id(),
-            address: Arc::from(address),
+            address: endpoint.address.clone(),
+            endpoint: endpoint.clone(),
             group: Arc::from(group),
             status: MachineStatusSummary {
                 state: MachineState::Running,
@@ -341,7 +731,7 @@ impl Machine {
             },
             added: now,
             updated: None,
-            machine_type: MachineType::BareMetal,
+            machine_type: Machine::get_machine_type_from_summary(overview),
             tags: Arc::from([]),
             system: overview.system.as_ref().map(|system| SystemSummary {
                 machine_id: Arc::from(system.machine_id.to_owned().unwrap_or("".into())),
@@ -366,7 +756,78 @@ impl Machine {
             volumes: Machine::get_volumes_from_summary(overview.volumes()),
             network_interfaces: Machine::get_networks_from_summary(overview.network()),
             addresses: Machine::get_addresses_from_summary(overview.network()),
-            containers: None,
+            containers: Machine::get_containers_from_summary(overview.containers()),
+            images: Machine::get_images_from_summary(overview.images()),
+            container_volumes: Machine::get_container_volumes_from_summary(
+                overview.container_volumes(),
+            ),
+            container_networks: Machine::get_container_networks_from_summary(
+                overview.container_networks(),
+            ),
+            container_disk_usage: Machine::get_container_disk_usage_from_summary(
+                overview.container_disk_usage(),
+            ),
+            stacks: Machine::get_stacks_from_summary(overview.stacks()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use chrono::Utc;
+
+    use super::{
+        ContainerProblem::{CrashLooping, OomKilled, Unhealthy},
+        ContainerSummary,
+    };
+
+    fn container(
+        state: &str,
+        restart_count: u64,
+        exit_code: Option<i64>,
+        unhealthy: bool,
+        oom_killed: bool,
+    ) -> ContainerSummary {
+        ContainerSummary {
+            container_id: Arc::from("abc"),
+            name: Arc::from("web"),
+            image: Arc::from("nginx"),
+            created: Utc::now(),
+            state: Arc::from(state),
+            health: unhealthy.then(|| Arc::from("Unhealthy")),
+            restart_count,
+            exit_code,
+            oom_killed,
+        }
+    }
+
+    #[test]
+    fn container_problems() {
+        let cases = [
+            ("Running", 0, Some(0), false, false, None),
+            ("Restarting", 3, Some(1), false, false, Some(CrashLooping)),
+            ("Restarting", 2, Some(1), false, false, None),
+            ("Running", 5, Some(1), false, false, Some(CrashLooping)),
+            ("Running", 5, Some(0), false, false, None),
+            ("Stopped", 5, Some(137), false, false, None),
+            ("Stopped", 5, Some(143), false, false, None),
+            ("Stopped", 5, Some(1), false, false, None),
+            ("Running", 0, None, true, false, Some(Unhealthy)),
+            ("Restarting", 4, Some(1), true, false, Some(CrashLooping)),
+            ("Stopped", 0, Some(137), false, true, Some(OomKilled)),
+        ];
+
+        for (state, restarts, exit_code, unhealthy, oom_killed, expected) in cases {
+            assert_eq!(
+                container(state, restarts, exit_code, unhealthy, oom_killed).problem(),
+                expected,
+                "{} with {} restarts, exit code {:?}",
+                state,
+                restarts,
+                exit_code
+            );
         }
     }
 }
@@ -1 +1,4 @@
-pub mod machine;
\ No newline at end of file
+pub mod container;
+pub mod group;
+pub mod machine;
+pub mod utilization;
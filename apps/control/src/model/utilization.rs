@@ -0,0 +1,161 @@
+use std::sync::Arc;
+
+use chrono::{DateTime, Utc};
+
+use geth_agent_client::types::{
+    CpuSummary as AgentCpuSummary, MemoryTypeSummary as AgentMemoryTypeSummary,
+    NetworkInterfaceSummary as AgentNetworkInterfaceSummary,
+    NetworkInterfaceTrafficSummary as AgentNetworkInterfaceTrafficSummary, OverviewSummary,
+    VolumeSummary as AgentVolumeSummary,
+};
+
+/// A point of a metric's recorded history.
+#[derive(Clone, Debug)]
+pub struct MetricPoint {
+    pub(crate) timestamp: DateTime<Utc>,
+    pub(crate) value: f64,
+}
+
+#[derive(Clone, Debug)]
+pub struct MemoryTypeUtilization {
+    pub(crate) total: u64,
+    pub(crate) available: u64,
+    pub(crate) used: u64,
+}
+
+#[derive(Clone, Debug)]
+pub struct MemoryUtilization {
+    pub(crate) memory: Option<MemoryTypeUtilization>,
+    pub(crate) swap: Option<MemoryTypeUtilization>,
+}
+
+#[derive(Clone, Debug)]
+pub struct CoreUtilization {
+    pub(crate) name: Arc<str>,
+    pub(crate) usage: f32,
+    pub(crate) frequency: f32,
+}
+
+#[derive(Clone, Debug)]
+pub struct VolumeUtilization {
+    pub(crate) name: Arc<str>,
+    pub(crate) mount_point: Arc<str>,
+    pub(crate) total_space: u64,
+    pub(crate) available_space: u64,
+    pub(crate) used_space: u64,
+}
+
+#[derive(Clone, Debug)]
+pub struct NetworkTraffic {
+    pub(crate) transmitted: u64,
+    pub(crate) recieved: u64,
+}
+
+#[derive(Clone, Debug)]
+pub struct NetworkInterfaceUtilization {
+    pub(crate) name: Arc<str>,
+    pub(crate) bytes: Option<NetworkTraffic>,
+    pub(crate) packets: Option<NetworkTraffic>,
+}
+
+#[derive(Clone, Debug)]
+pub struct MachineUtilization {
+    pub(crate) machine_id: Arc<str>,
+    pub(crate) boot_time: Option<u64>,
+    pub(crate) up_time: Option<u64>,
+    pub(crate) memory: Option<MemoryUtilization>,
+    pub(crate) cpu: Arc<[CoreUtilization]>,
+    pub(crate) volumes: Arc<[VolumeUtilization]>,
+    pub(crate) network_interfaces: Arc<[NetworkInterfaceUtilization]>,
+}
+
+impl MachineUtilization {
+    fn get_memory_type(memory: Option<&AgentMemoryTypeSummary>) -> Option<MemoryTypeUtilization> {
+        memory.map(|m| MemoryTypeUtilization {
+            total: m.total().unwrap_or(0) as u64,
+            available: m.available().unwrap_or(0) as u64,
+            used: m.used().unwrap_or(0) as u64,
+        })
+    }
+
+    fn get_cores(cpu: Option<&AgentCpuSummary>) -> Arc<[CoreUtilization]> {
+        let mut cores = Vec::new();
+
+        if let Some(utilization) = cpu.and_then(|c| c.utilization()) {
+            for core in utilization {
+                cores.push(CoreUtilization {
+                    name: Arc::from(core.name().unwrap_or("")),
+                    usage: core.usage().unwrap_or(0.0),
+                    frequency: core.frequency().unwrap_or(0.0),
+                });
+            }
+        }
+
+        cores.into()
+    }
+
+    fn get_volumes(volumes: Option<&[AgentVolumeSummary]>) -> Arc<[VolumeUtilization]> {
+        let mut summaries = Vec::new();
+
+        for volume in volumes.unwrap_or_default() {
+            summaries.push(VolumeUtilization {
+                name: Arc::from(volume.name().unwrap_or("")),
+                mount_point: Arc::from(volume.mount_point().unwrap_or("")),
+                total_space: volume.total_space().unwrap_or(0) as u64,
+                available_space: volume.available_space().unwrap_or(0) as u64,
+                used_space: volume.used_space().unwrap_or(0) as u64,
+            });
+        }
+
+        summaries.into()
+    }
+
+    fn get_traffic(
+        traffic: Option<&AgentNetworkInterfaceTrafficSummary>,
+    ) -> Option<NetworkTraffic> {
+        traffic.map(|t| NetworkTraffic {
+            transmitted: t.transmitted().unwrap_or(0) as u64,
+            recieved: t.recieved().unwrap_or(0) as u64,
+        })
+    }
+
+    fn get_network_interfaces(
+        networks: Option<&[AgentNetworkInterfaceSummary]>,
+    ) -> Arc<[NetworkInterfaceUtilization]> {
+        let mut summaries = Vec::new();
+
+        for network in networks.unwrap_or_default() {
+            summaries.push(NetworkInterfaceUtilization {
+                name: Arc::from(network.name().unwrap_or("")),
+                bytes: MachineUtilization::get_traffic(network.bytes_traffic()),
+                packets: MachineUtilization::get_traffic(network.packet_traffic()),
+            });
+        }
+
+        summaries.into()
+    }
+
+    pub fn new_from_agent_overview(
+        machine_id: &str,
+        overview: &OverviewSummary,
+    ) -> MachineUtilization {
+        MachineUtilization {
+            machine_id: Arc::from(machine_id),
+            boot_time: overview
+                .system()
+                .and_then(|s| s.boot_time())
+                .map(|t| t as u64),
+            up_time: overview
+                .system()
+                .and_then(|s| s.up_time())
+                .map(|t| t as u64),
+            memory: overview.memory().map(|m| MemoryUtilization {
+                memory: MachineUtilization::get_memory_type(m.memory()),
+                swap: MachineUtilization::get_memory_type(m.swap()),
+            }),
+            cpu: MachineUtilization::get_cores(overview.cpu()),
+            volumes: MachineUtilization::get_volumes(overview.volumes()),
+            network_interfaces: MachineUtilization::get_network_interfaces(overview.network()),
+        }
+    }
+}
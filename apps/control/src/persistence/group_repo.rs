@@ -0,0 +1,127 @@
+use std::sync::Arc;
+
+use super::repository::Repository;
+use async_trait::async_trait;
+use prisma::{group_summary, PrismaClient};
+
+use crate::model::group::Group;
+
+pub struct GroupPrismaRepository {
+    conn: Arc<PrismaClient>,
+}
+
+impl GroupPrismaRepository {
+    pub fn new(conn: Arc<PrismaClient>) -> Self {
+        GroupPrismaRepository { conn }
+    }
+
+    fn db_to_model(group: group_summary::Data) -> Group {
+        Group {
+            id: group.id.into(),
+            name: group.name.into(),
+            platform: group.platform.map(Arc::from),
+            location: group.location.map(Arc::from),
+            added: group.added.into(),
+        }
+    }
+
+    pub async fn find_by_name(&self, name: &str) -> Option<Group> {
+        let group = self
+            .conn
+            .group_summary()
+            .find_unique(group_summary::name::equals(name.to_string()))
+            .exec()
+            .await
+            .unwrap();
+
+        group.map(GroupPrismaRepository::db_to_model)
+    }
+}
+
+#[async_trait]
+impl Repository<Group, String> for GroupPrismaRepository {
+    async fn find_by_id(&self, id: String) -> Option<Group> {
+        let group = self
+            .conn
+            .group_summary()
+            .find_unique(group_summary::id::equals(id))
+            .exec()
+            .await
+            .unwrap();
+
+        group.map(GroupPrismaRepository::db_to_model)
+    }
+
+    async fn find_all(&self) -> Arc<[Group]> {
+        let results = self
+            .conn
+            .group_summary()
+            .find_many(vec![])
+            .exec()
+            .await
+            .unwrap();
+
+        let mut groups: Vec<Group> = Vec::new();
+        for group in results {
+            groups.push(GroupPrismaRepository::db_to_model(group));
+        }
+        groups.into()
+    }
+
+    async fn modify(&mut self, item: Group) -> Result<(), String> {
+        let result = self
+            .conn
+            .group_summary()
+            .update(
+                group_summary::id::equals(item.id.to_string()),
+                vec![
+                    group_summary::name::set(item.name.to_string()),
+                    group_summary::platform::set(item.platform.map(|p| p.to_string())),
+                    group_summary::location::set(item.location.map(|l| l.to_string())),
+                ],
+            )
+            .exec()
+            .await;
+
+        match result {
+            Ok(_) => Ok(()),
+            Err(e) => Err(e.to_string()),
+        }
+    }
+
+    async fn insert(&mut self, item: Group) -> Result<(), String> {
+        let result = self
+            .conn
+            .group_summary()
+            .create(
+                item.id.to_string(),
+                item.name.to_string(),
+                item.added.into(),
+                vec![
+                    group_summary::platform::set(item.platform.map(|p| p.to_string())),
+                    group_summary::location::set(item.location.map(|l| l.to_string())),
+                ],
+            )
+            .exec()
+            .await;
+
+        match result {
+            Ok(_) => Ok(()),
+            Err(e) => Err(e.to_string()),
+        }
+    }
+
+    async fn delete(&mut self, id: String) -> Result<(), String> {
+        let result = self
+            .conn
+            .group_summary()
+            .delete(group_summary::id::equals(id))
+            .exec()
+            .await;
+
+        match result {
+            Ok(_) => Ok(()),
+            Err(e) => Err(e.to_string()),
+        }
+    }
+}
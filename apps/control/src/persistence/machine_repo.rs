@@ -1,34 +1,50 @@
-use std::sync::Arc;
+use std::{collections::HashSet, str::FromStr, sync::Arc};
 
 use super::repository::Repository;
 use async_trait::async_trait;
+use http::uri::Authority;
+use log::{error, info, warn};
 use prisma::{
-    machine_summary,
+    address_summary, agent_endpoint, container_disk_usage_summary, container_network_summary,
+    container_summary, container_volume_summary, cpu_summary, disk_summary, group_summary,
+    image_summary, machine_status_summary, machine_summary, memory_summary,
+    network_interface_summary, stack_summary, system_summary, tag,
     types::{machine_full_summary, MachineSummaryFull},
-    PrismaClient,
+    volume_summary, PrismaClient,
 };
+use prisma_client_rust::QueryError;
 
 use chrono::{DateTime, FixedOffset, Local, Utc};
 
+use super::secret::SecretCipher;
 use crate::model::machine::{
-    AddressSummary, AddressVersion, ContainerSummary, CpuSummary, DiskInterface, DiskSummary,
-    DiskType, Machine, MachineState, MachineStatusSummary, MachineType, MemorySummary,
-    NetworkInterfaceSummary, SystemSummary, Tag, VolumeSummary,
+    AddressSummary, AddressVersion, AgentEndpoint, AgentScheme, ContainerDiskUsageSummary,
+    ContainerNetworkSummary, ContainerSummary, ContainerVolumeSummary, CpuSummary, DiskInterface,
+    DiskSummary, DiskType, DiskUsageEntry, ImageSummary, Machine, MachineState,
+    MachineStatusSummary, MachineType, MemorySummary, NetworkInterfaceSummary, StackSummary,
+    SystemSummary, Tag, VolumeSummary, DEFAULT_AGENT_PORT,
 };
 
 pub struct MachinePrismaRepository {
-    conn: PrismaClient,
+    conn: Arc<PrismaClient>,
+    secrets: SecretCipher,
 }
 
 impl MachinePrismaRepository {
-    pub fn new(conn: PrismaClient) -> Self {
-        MachinePrismaRepository { conn }
+    pub fn new(conn: Arc<PrismaClient>, secrets: SecretCipher) -> Self {
+        MachinePrismaRepository { conn, secrets }
     }
 
-    fn db_to_model(machine: MachineSummaryFull) -> Machine {
-        Machine {
+    fn db_to_model(&self, machine: MachineSummaryFull) -> Result<Machine, String> {
+        Ok(Machine {
             id: machine.id.clone().into(),
             address: machine.address.clone().into(),
+            endpoint: self.convert_endpoint(
+                &machine.id,
+                &machine.address,
+                machine.port,
+                machine.endpoint,
+            )?,
             group: machine.group.clone().into(),
             status: convert_status_summary(machine.status),
             added: machine.added.into(),
@@ -43,6 +59,191 @@ impl MachinePrismaRepository {
             network_interfaces: convert_network_interface_summaries(machine.network_interfaces),
             addresses: convert_address_summaries(machine.addresses),
             containers: convert_container_summaries(machine.containers),
+            images: convert_image_summaries(machine.images),
+            container_volumes: convert_container_volume_summaries(machine.container_volumes),
+            container_networks: convert_container_network_summaries(machine.container_networks),
+            container_disk_usage: convert_container_disk_usage_summaries(
+                machine.container_disk_usage,
+            ),
+        })
+    }
+
+    fn convert_endpoint(
+        &self,
+        machine_id: &str,
+        address: &str,
+        port: i32,
+        e: Option<agent_endpoint::Data>,
+    ) -> Result<AgentEndpoint, String> {
+        let e = e.ok_or_else(|| format!("Machine {} has no agent endpoint", machine_id))?;
+
+        let api_key = self
+            .secrets
+            .decrypt(&e.api_key)
+            .map_err(|err| format!("Failed to decrypt api key for {}: {}", machine_id, err))?;
+
+        Ok(AgentEndpoint::new(
+            match e.scheme {
+                prisma::AgentScheme::Http => AgentScheme::Http,
+                prisma::AgentScheme::Https => AgentScheme::Https,
+            },
+            address,
+            port as u16,
+            &api_key,
+            e.ca_bundle.as_deref(),
+        ))
+    }
+
+    /// Gives machines registered before agent endpoints were stored an
+    /// endpoint row. Their address may still carry the agent's port, which is
+    /// split out, and they were reached over HTTP with `legacy_api_key`, the
+    /// key control sent to every agent back then.
+    pub async fn migrate_legacy_endpoints(
+        &self,
+        legacy_api_key: Option<&str>,
+    ) -> Result<usize, String> {
+        let migrated: HashSet<String> = self
+            .conn
+            .agent_endpoint()
+            .find_many(vec![])
+            .exec()
+            .await
+            .map_err(|e| e.to_string())?
+            .into_iter()
+            .map(|e| e.machine_id)
+            .collect();
+
+        let legacy: Vec<machine_summary::Data> = self
+            .conn
+            .machine_summary()
+            .find_many(vec![])
+            .exec()
+            .await
+            .map_err(|e| e.to_string())?
+            .into_iter()
+            .filter(|m| !migrated.contains(&m.id))
+            .collect();
+
+        if legacy.is_empty() {
+            return Ok(0);
+        }
+
+        let legacy_api_key = legacy_api_key.ok_or_else(|| {
+            format!(
+                "{} machines were registered before agent api keys were stored, set \
+                secrets.legacy_agent_key to the key their agents expect",
+                legacy.len()
+            )
+        })?;
+
+        for machine in legacy.iter() {
+            let (host, port) = split_legacy_address(&machine.address)
+                .map_err(|e| format!("Cannot migrate machine {}: {}", machine.id, e))?;
+            let endpoint = AgentEndpoint::new(AgentScheme::Http, &host, port, legacy_api_key, None);
+            let api_key = self.secrets.encrypt(legacy_api_key)?;
+            let id = machine.id.clone();
+
+            self.conn
+                ._transaction()
+                .run(|client| async move {
+                    client
+                        .machine_summary()
+                        .update(
+                            machine_summary::id::equals(id.clone()),
+                            vec![
+                                machine_summary::address::set(host),
+                                machine_summary::port::set(port as i32),
+                            ],
+                        )
+                        .exec()
+                        .await?;
+
+                    sync_endpoint(&client, &id, &endpoint, api_key).await
+                })
+                .await
+                .map_err(|e| format!("Cannot migrate machine {}: {}", machine.id, e))?;
+
+            info!(
+                "Migrated machine {} to an agent endpoint at {}",
+                machine.id, machine.address
+            );
+        }
+
+        Ok(legacy.len())
+    }
+
+    /// Checks that every stored agent api key can be decrypted with the
+    /// configured secret key. Machines whose keys cannot be read would
+    /// otherwise be left out of every listing.
+    pub async fn check_endpoints(&self) -> Result<(), String> {
+        let endpoints = self
+            .conn
+            .agent_endpoint()
+            .find_many(vec![])
+            .exec()
+            .await
+            .map_err(|e| e.to_string())?;
+
+        let unreadable: Vec<String> = endpoints
+            .into_iter()
+            .filter(|e| self.secrets.decrypt(&e.api_key).is_err())
+            .map(|e| e.machine_id)
+            .collect();
+
+        if unreadable.is_empty() {
+            return Ok(());
+        }
+
+        Err(format!(
+            "Agent api keys of machines {} cannot be decrypted, check the configured secret key",
+            unreadable.join(", ")
+        ))
+    }
+
+    pub async fn count_in_group(&self, group: &str) -> Result<i64, String> {
+        let result = self
+            .conn
+            .machine_summary()
+            .count(vec![machine_summary::group::equals(group.to_string())])
+            .exec()
+            .await;
+
+        match result {
+            Ok(c) => Ok(c),
+            Err(e) => Err(e.to_string()),
+        }
+    }
+
+    pub async fn update_status(
+        &mut self,
+        id: &str,
+        status: &MachineStatusSummary,
+    ) -> Result<(), String> {
+        let state = convert_machine_state_to_prisma(&status.state);
+
+        let result = self
+            .conn
+            .machine_status_summary()
+            .upsert(
+                machine_status_summary::machine_id::equals(id.to_string()),
+                machine_status_summary::create(
+                    state,
+                    machine_summary::id::equals(id.to_string()),
+                    vec![machine_status_summary::last_checked::set(
+                        status.last_checked.into(),
+                    )],
+                ),
+                vec![
+                    machine_status_summary::status::set(state),
+                    machine_status_summary::last_checked::set(status.last_checked.into()),
+                ],
+            )
+            .exec()
+            .await;
+
+        match result {
+            Ok(_) => Ok(()),
+            Err(e) => Err(e.to_string()),
         }
     }
 }
@@ -61,7 +262,13 @@ impl Repository<Machine, String> for MachinePrismaRepository {
 
         match machine {
             None => return None,
-            Some(machine) => return Some(MachinePrismaRepository::db_to_model(machine)),
+            Some(machine) => match self.db_to_model(machine) {
+                Ok(m) => Some(m),
+                Err(e) => {
+                    error!("{}", e);
+                    None
+                }
+            },
         }
     }
 
@@ -77,384 +284,1416 @@ impl Repository<Machine, String> for MachinePrismaRepository {
 
         let mut machines: Vec<Machine> = Vec::new();
         for machine in results {
-            machines.push(MachinePrismaRepository::db_to_model(machine));
+            match self.db_to_model(machine) {
+                Ok(m) => machines.push(m),
+                Err(e) => error!("{}", e),
+            }
         }
         machines.into()
     }
 
     async fn modify(&mut self, item: Machine) -> Result<(), String> {
-        todo!()
+        let api_key = self.secrets.encrypt(&item.endpoint.api_key)?;
+
+        let result = self
+            .conn
+            ._transaction()
+            .run(|client| async move {
+                let id = item.id.to_string();
+
+                client
+                    .machine_summary()
+                    .update(
+                        machine_summary::id::equals(id.clone()),
+                        vec![
+                            machine_summary::address::set(item.address.to_string()),
+                            machine_summary::port::set(item.endpoint.port as i32),
+                            machine_summary::group_summary::connect(group_summary::id::equals(
+                                item.group.to_string(),
+                            )),
+                            machine_summary::r#type::set(convert_machine_type_to_prisma(
+                                item.machine_type.clone(),
+                            )),
+                            machine_summary::updated::set(item.updated.map(|u| u.into())),
+                        ],
+                    )
+                    .exec()
+                    .await?;
+
+                sync_endpoint(&client, &id, &item.endpoint, api_key).await?;
+                sync_tags(&client, &id, &item.tags).await?;
+                sync_system(&client, &id, item.system.as_ref()).await?;
+                sync_memory(&client, &id, item.memory.as_ref()).await?;
+                sync_cpu(&client, &id, item.cpu.as_ref()).await?;
+                sync_disks(&client, &id, item.disks.as_deref().unwrap_or(&[])).await?;
+                sync_volumes(&client, &id, item.volumes.as_deref().unwrap_or(&[])).await?;
+                sync_network_interfaces(
+                    &client,
+                    &id,
+                    item.network_interfaces.as_deref().unwrap_or(&[]),
+                )
+                .await?;
+                sync_addresses(&client, &id, item.addresses.as_deref().unwrap_or(&[])).await?;
+                sync_containers(&client, &id, item.containers.as_deref().unwrap_or(&[])).await?;
+                sync_images(&client, &id, item.images.as_deref().unwrap_or(&[])).await?;
+                sync_container_volumes(
+                    &client,
+                    &id,
+                    item.container_volumes.as_deref().unwrap_or(&[]),
+                )
+                .await?;
+                sync_container_networks(
+                    &client,
+                    &id,
+                    item.container_networks.as_deref().unwrap_or(&[]),
+                )
+                .await?;
+                sync_container_disk_usage(
+                    &client,
+                    &id,
+                    item.container_disk_usage.as_deref().unwrap_or(&[]),
+                )
+                .await?;
+                sync_stacks(&client, &id, item.stacks.as_deref().unwrap_or(&[])).await?;
+
+                Ok::<(), QueryError>(())
+            })
+            .await;
+
+        match result {
+            Ok(_) => Ok(()),
+            Err(e) => Err(e.to_string()),
+        }
     }
 
     async fn insert(&mut self, item: Machine) -> Result<(), String> {
-        let t = match item.machine_type {
-            MachineType::BareMetal => prisma::MachineType::BareMetal,
-            MachineType::Hypervisor => prisma::MachineType::Hypervisor,
-            MachineType::VirtualMachine => prisma::MachineType::VirtualMachine,
-        };
+        let api_key = self.secrets.encrypt(&item.endpoint.api_key)?;
 
         let result = self
             .conn
-            .machine_summary()
-            .create(
-                item.id.clone().to_string(),
-                item.address.clone().to_string(),
-                item.group.clone().to_string(),
-                item.added.into(),
-                t,
-                vec![],
-            )
-            .include(machine_full_summary::include())
-            .exec()
+            ._transaction()
+            .run(|client| async move {
+                let id = item.id.to_string();
+
+                client
+                    .machine_summary()
+                    .create(
+                        id.clone(),
+                        item.address.to_string(),
+                        group_summary::id::equals(item.group.to_string()),
+                        item.added.into(),
+                        convert_machine_type_to_prisma(item.machine_type.clone()),
+                        vec![
+                            machine_summary::port::set(item.endpoint.port as i32),
+                            machine_summary::updated::set(item.updated.map(|u| u.into())),
+                        ],
+                    )
+                    .exec()
+                    .await?;
+
+                client
+                    .machine_status_summary()
+                    .create(
+                        convert_machine_state_to_prisma(&item.status.state),
+                        machine_summary::id::equals(id.clone()),
+                        vec![machine_status_summary::last_checked::set(
+                            item.status.last_checked.into(),
+                        )],
+                    )
+                    .exec()
+                    .await?;
+
+                sync_endpoint(&client, &id, &item.endpoint, api_key).await?;
+
+                let tags: Vec<&Tag> = item.tags.iter().collect();
+                create_tags(&client, &id, &tags).await?;
+
+                sync_system(&client, &id, item.system.as_ref()).await?;
+                sync_memory(&client, &id, item.memory.as_ref()).await?;
+                sync_cpu(&client, &id, item.cpu.as_ref()).await?;
+
+                let disks: Vec<&DiskSummary> = item.disks.iter().flat_map(|d| d.iter()).collect();
+                create_disks(&client, &id, &disks).await?;
+
+                let volumes: Vec<&VolumeSummary> =
+                    item.volumes.iter().flat_map(|v| v.iter()).collect();
+                create_volumes(&client, &id, &volumes).await?;
+
+                let interfaces: Vec<&NetworkInterfaceSummary> = item
+                    .network_interfaces
+                    .iter()
+                    .flat_map(|n| n.iter())
+                    .collect();
+                create_network_interfaces(&client, &id, &interfaces).await?;
+
+                let addresses: Vec<&AddressSummary> =
+                    item.addresses.iter().flat_map(|a| a.iter()).collect();
+                create_addresses(&client, &id, &addresses).await?;
+
+                let containers: Vec<&ContainerSummary> =
+                    item.containers.iter().flat_map(|c| c.iter()).collect();
+                create_containers(&client, &id, &containers).await?;
+
+                let images: Vec<&ImageSummary> =
+                    item.images.iter().flat_map(|i| i.iter()).collect();
+                create_images(&client, &id, &images).await?;
+
+                let container_volumes: Vec<&ContainerVolumeSummary> = item
+                    .container_volumes
+                    .iter()
+                    .flat_map(|v| v.iter())
+                    .collect();
+                create_container_volumes(&client, &id, &container_volumes).await?;
+
+                let container_networks: Vec<&ContainerNetworkSummary> = item
+                    .container_networks
+                    .iter()
+                    .flat_map(|n| n.iter())
+                    .collect();
+                create_container_networks(&client, &id, &container_networks).await?;
+
+                let container_disk_usage: Vec<&ContainerDiskUsageSummary> = item
+                    .container_disk_usage
+                    .iter()
+                    .flat_map(|u| u.iter())
+                    .collect();
+                create_container_disk_usage(&client, &id, &container_disk_usage).await?;
+
+                let stacks: Vec<&StackSummary> =
+                    item.stacks.iter().flat_map(|s| s.iter()).collect();
+                create_stacks(&client, &id, &stacks).await?;
+
+                Ok::<(), QueryError>(())
+            })
             .await;
 
-        // figure out how to make these with one write
-        let machine_status = match item.status.state {
-            MachineState::Running => prisma::MachineStatus::Running,
-            MachineState::Stopped => prisma::MachineStatus::Stopped,
-            MachineState::Unknown => prisma::MachineStatus::Unknown,
-        };
+        match result {
+            Ok(_) => Ok(()),
+            Err(e) => Err(e.to_string()),
+        }
+    }
 
-        let status_result = self
+    async fn delete(&mut self, id: String) -> Result<(), String> {
+        let result = self
             .conn
-            .machine_status_summary()
-            .create(
-                machine_status,
-                machine_summary::id::equals(item.id.clone().to_string()),
-                vec![prisma::machine_status_summary::last_checked::set(
-                    item.status.last_checked.into(),
-                )],
-            )
+            .machine_summary()
+            .delete(machine_summary::id::equals(id))
             .exec()
             .await;
 
-        let tags_result = self
-            .conn
+        match result {
+            Ok(_) => return Ok(()),
+            Err(e) => return Err(e.to_string()),
+        }
+    }
+}
+
+/// Splits a pre-endpoint address such as `host:7032` into its host and port.
+/// Addresses stored without a port used the default agent port.
+fn split_legacy_address(address: &str) -> Result<(String, u16), String> {
+    let authority = Authority::from_str(address).map_err(|e| format!("{}: {}", address, e))?;
+
+    let port = match authority.port_u16() {
+        Some(p) => p,
+        None if authority.as_str() == authority.host() => DEFAULT_AGENT_PORT,
+        None => return Err(format!("{} has an invalid port", address)),
+    };
+
+    Ok((authority.host().to_string(), port))
+}
+
+// Child rows are matched to the incoming model by a natural key (tag key,
+// disk device, volume mount point, interface name, address, container id,
+// image id, runtime volume name, network id, runtime, stack project).
+// Matches are updated in place, new entries are created and anything left
+// over is removed.
+
+async fn sync_endpoint(
+    client: &PrismaClient,
+    machine_id: &str,
+    endpoint: &AgentEndpoint,
+    sealed_api_key: String,
+) -> Result<(), QueryError> {
+    let scheme = match endpoint.scheme {
+        AgentScheme::Http => prisma::AgentScheme::Http,
+        AgentScheme::Https => prisma::AgentScheme::Https,
+    };
+    let ca_bundle = endpoint.ca_bundle.as_ref().map(|c| c.to_string());
+
+    client
+        .agent_endpoint()
+        .upsert(
+            agent_endpoint::machine_id::equals(machine_id.to_string()),
+            agent_endpoint::create(
+                machine_summary::id::equals(machine_id.to_string()),
+                scheme,
+                sealed_api_key.clone(),
+                vec![agent_endpoint::ca_bundle::set(ca_bundle.clone())],
+            ),
+            vec![
+                agent_endpoint::scheme::set(scheme),
+                agent_endpoint::api_key::set(sealed_api_key),
+                agent_endpoint::ca_bundle::set(ca_bundle),
+            ],
+        )
+        .exec()
+        .await?;
+
+    Ok(())
+}
+
+async fn sync_tags(
+    client: &PrismaClient,
+    machine_id: &str,
+    tags: &[Tag],
+) -> Result<(), QueryError> {
+    let existing = client
+        .tag()
+        .find_many(vec![tag::machine_id::equals(machine_id.to_string())])
+        .exec()
+        .await?;
+
+    let mut stale = Vec::new();
+    for row in existing.iter() {
+        match tags.iter().find(|t| *t.key == row.key) {
+            Some(t) => {
+                client
+                    .tag()
+                    .update(
+                        tag::id::equals(row.id),
+                        vec![tag::value::set(t.value.to_string())],
+                    )
+                    .exec()
+                    .await?;
+            }
+            None => stale.push(row.id),
+        }
+    }
+
+    let added: Vec<&Tag> = tags
+        .iter()
+        .filter(|t| !existing.iter().any(|r| *t.key == r.key))
+        .collect();
+    create_tags(client, machine_id, &added).await?;
+
+    if !stale.is_empty() {
+        client
             .tag()
-            .create_many(
-                item.tags
-                    .iter()
-                    .map(|t| {
-                        prisma::tag::create_unchecked(
-                            item.id.clone().to_string(),
-                            t.key.to_string(),
-                            t.value.to_string(),
-                            vec![],
-                        )
-                    })
-                    .collect(),
-            )
+            .delete_many(vec![tag::id::in_vec(stale)])
             .exec()
-            .await;
+            .await?;
+    }
 
-        if item.disks.is_some() {
-            let disks_result = self
-                .conn
-                .disk_summary()
-                .create_many(
-                    item.disks
-                        .unwrap()
-                        .iter()
-                        .map(|d| {
-                            let mut optionals: Vec<prisma::disk_summary::SetParam> = Vec::new();
-
-                            optionals.push(prisma::disk_summary::interface::set(Some(
-                                convert_disk_interface(&d.interface),
-                            )));
-
-                            if d.model.is_some() {
-                                optionals.push(prisma::disk_summary::model::set(Some(
-                                    d.model.clone().unwrap().to_string(),
-                                )));
-                            }
-                            if d.vendor.is_some() {
-                                optionals.push(prisma::disk_summary::vendor::set(Some(
-                                    d.vendor.clone().unwrap().to_string(),
-                                )));
-                            }
-                            if d.serial.is_some() {
-                                optionals.push(prisma::disk_summary::serial::set(Some(
-                                    d.serial.clone().unwrap().to_string(),
-                                )));
-                            }
-                            if d.sector_size.is_some() {
-                                optionals.push(prisma::disk_summary::sector_size::set(Some(
-                                    d.sector_size.unwrap() as i32,
-                                )));
-                            }
-                            if d.size_raw.is_some() {
-                                optionals.push(prisma::disk_summary::size_raw::set(Some(
-                                    d.size_raw.unwrap() as i64,
-                                )));
-                            }
-
-                            prisma::disk_summary::create_unchecked(
-                                item.id.clone().to_string(),
-                                d.device.to_string(),
-                                convert_disk_type(&d.r#type),
-                                d.size_actual as i64,
-                                optionals,
-                            )
-                        })
-                        .collect(),
-                )
+    Ok(())
+}
+
+async fn create_tags(
+    client: &PrismaClient,
+    machine_id: &str,
+    tags: &[&Tag],
+) -> Result<(), QueryError> {
+    if tags.is_empty() {
+        return Ok(());
+    }
+
+    client
+        .tag()
+        .create_many(
+            tags.iter()
+                .map(|t| {
+                    tag::create_unchecked(
+                        machine_id.to_string(),
+                        t.key.to_string(),
+                        t.value.to_string(),
+                        vec![],
+                    )
+                })
+                .collect(),
+        )
+        .exec()
+        .await?;
+
+    Ok(())
+}
+
+async fn sync_system(
+    client: &PrismaClient,
+    machine_id: &str,
+    system: Option<&SystemSummary>,
+) -> Result<(), QueryError> {
+    let system = match system {
+        Some(s) => s,
+        None => {
+            client
+                .system_summary()
+                .delete_many(vec![system_summary::machine_id::equals(
+                    machine_id.to_string(),
+                )])
                 .exec()
-                .await;
+                .await?;
+            return Ok(());
         }
+    };
+
+    client
+        .system_summary()
+        .upsert(
+            system_summary::machine_id::equals(machine_id.to_string()),
+            system_summary::create(
+                machine_summary::id::equals(machine_id.to_string()),
+                system.machine_id.to_string(),
+                system.family.to_string(),
+                system.kernel_version.to_string(),
+                system.os.to_string(),
+                system.os_version.to_string(),
+                system.os_pretty.to_string(),
+                system.hostname.to_string(),
+                vec![],
+            ),
+            vec![
+                system_summary::local_machine_id::set(system.machine_id.to_string()),
+                system_summary::family::set(system.family.to_string()),
+                system_summary::kernel_version::set(system.kernel_version.to_string()),
+                system_summary::os::set(system.os.to_string()),
+                system_summary::os_version::set(system.os_version.to_string()),
+                system_summary::os_pretty::set(system.os_pretty.to_string()),
+                system_summary::hostname::set(system.hostname.to_string()),
+            ],
+        )
+        .exec()
+        .await?;
+
+    Ok(())
+}
 
-        if item.volumes.is_some() {
-            let volumes_result = self
-                .conn
-                .volume_summary()
-                .create_many(
-                    item.volumes
-                        .unwrap()
-                        .iter()
-                        .map(|v| {
-                            let mut optionals: Vec<prisma::volume_summary::SetParam> = Vec::new();
-
-                            if v.file_system.is_some() {
-                                optionals.push(prisma::volume_summary::file_system::set(Some(
-                                    v.file_system.clone().unwrap().to_string(),
-                                )));
-                            }
-
-                            prisma::volume_summary::create_unchecked(
-                                item.id.clone().to_string(),
-                                v.name.to_string(),
-                                v.mount_point.to_string(),
-                                v.total_space as i64,
-                                optionals,
-                            )
-                        })
-                        .collect(),
-                )
+async fn sync_memory(
+    client: &PrismaClient,
+    machine_id: &str,
+    memory: Option<&MemorySummary>,
+) -> Result<(), QueryError> {
+    let memory = match memory {
+        Some(m) => m,
+        None => {
+            client
+                .memory_summary()
+                .delete_many(vec![memory_summary::machine_id::equals(
+                    machine_id.to_string(),
+                )])
                 .exec()
-                .await;
+                .await?;
+            return Ok(());
         }
+    };
+
+    client
+        .memory_summary()
+        .upsert(
+            memory_summary::machine_id::equals(machine_id.to_string()),
+            memory_summary::create(
+                machine_summary::id::equals(machine_id.to_string()),
+                memory.swap as i64,
+                memory.memory as i64,
+                vec![],
+            ),
+            vec![
+                memory_summary::swap_total::set(memory.swap as i64),
+                memory_summary::memory_total::set(memory.memory as i64),
+            ],
+        )
+        .exec()
+        .await?;
+
+    Ok(())
+}
 
-        if item.network_interfaces.is_some() {
-            let nic_result = self
-                .conn
-                .network_interface_summary()
-                .create_many(
-                    item.network_interfaces
-                        .unwrap()
-                        .iter()
-                        .map(|n| {
-                            let mut optionals: Vec<prisma::network_interface_summary::SetParam> =
-                                Vec::new();
-
-                            if n.mac.is_some() {
-                                optionals.push(
-                                    prisma::network_interface_summary::mac_address::set(Some(
-                                        n.mac.clone().unwrap().to_string(),
-                                    )),
-                                );
-                            }
-
-                            if n.r#virtual {
-                                optionals
-                                    .push(prisma::network_interface_summary::r#virtual::set(true));
-                            }
-
-                            if n.vendor.is_some() {
-                                optionals.push(prisma::network_interface_summary::vendor::set(
-                                    Some(n.vendor.clone().unwrap().to_string()),
-                                ));
-                            }
-
-                            if n.mtu.is_some() {
-                                optionals.push(prisma::network_interface_summary::mtu::set(Some(
-                                    n.mtu.clone().unwrap() as i32,
-                                )));
-                            }
-
-                            if n.speed.is_some() {
-                                optionals.push(prisma::network_interface_summary::speed::set(
-                                    Some(n.speed.clone().unwrap() as i32),
-                                ));
-                            }
-
-                            if n.duplex.is_some() {
-                                optionals.push(prisma::network_interface_summary::duplex::set(
-                                    Some(n.duplex.clone().unwrap().to_string()),
-                                ));
-                            }
-
-                            if n.addresses.len() > 0 {
-                                let mut adrs = Vec::new();
-                                for a in n.addresses.iter() {
-                                    adrs.push(a.to_string());
-                                }
-
-                                optionals
-                                    .push(prisma::network_interface_summary::addresses::set(adrs));
-                            }
-
-                            prisma::network_interface_summary::create_unchecked(
-                                item.id.clone().to_string(),
-                                n.name.to_string(),
-                                optionals,
-                            )
-                        })
-                        .collect(),
-                )
+async fn sync_cpu(
+    client: &PrismaClient,
+    machine_id: &str,
+    cpu: Option<&CpuSummary>,
+) -> Result<(), QueryError> {
+    let cpu = match cpu {
+        Some(c) => c,
+        None => {
+            client
+                .cpu_summary()
+                .delete_many(vec![cpu_summary::machine_id::equals(
+                    machine_id.to_string(),
+                )])
                 .exec()
-                .await;
+                .await?;
+            return Ok(());
         }
+    };
+
+    let optionals = vec![
+        cpu_summary::model::set(cpu.model.as_ref().map(|m| m.to_string())),
+        cpu_summary::vendor::set(cpu.vendor.as_ref().map(|v| v.to_string())),
+    ];
+
+    let mut updates = vec![
+        cpu_summary::cores::set(cpu.cores as i32),
+        cpu_summary::architecture::set(cpu.architecture.to_string()),
+    ];
+    updates.extend(optionals.clone());
+
+    client
+        .cpu_summary()
+        .upsert(
+            cpu_summary::machine_id::equals(machine_id.to_string()),
+            cpu_summary::create(
+                machine_summary::id::equals(machine_id.to_string()),
+                cpu.cores as i32,
+                cpu.architecture.to_string(),
+                optionals,
+            ),
+            updates,
+        )
+        .exec()
+        .await?;
+
+    Ok(())
+}
 
-        if item.addresses.is_some() {
-            let addresses_result = self
-                .conn
-                .address_summary()
-                .create_many(
-                    item.addresses
-                        .unwrap()
-                        .iter()
-                        .map(|a| {
-                            let mut optionals: Vec<prisma::address_summary::SetParam> = Vec::new();
-
-                            let version = match a.version {
-                                AddressVersion::V4 => prisma::AddressVersion::V4,
-                                AddressVersion::V6 => prisma::AddressVersion::V6,
-                                AddressVersion::V6Local => prisma::AddressVersion::V6Local,
-                            };
-
-                            if a.netmask.is_some() {
-                                optionals.push(prisma::address_summary::netmask::set(Some(
-                                    a.netmask.clone().unwrap().to_string(),
-                                )));
-                            }
-
-                            if a.broadcast.is_some() {
-                                optionals.push(prisma::address_summary::broadcast::set(Some(
-                                    a.broadcast.clone().unwrap().to_string(),
-                                )));
-                            }
-
-                            prisma::address_summary::create_unchecked(
-                                item.id.clone().to_string(),
-                                version,
-                                a.address.clone().to_string(),
-                                optionals,
-                            )
-                        })
-                        .collect(),
-                )
-                .exec()
-                .await;
+fn disk_optionals(d: &DiskSummary) -> Vec<disk_summary::SetParam> {
+    vec![
+        disk_summary::interface::set(Some(convert_disk_interface(&d.interface))),
+        disk_summary::model::set(d.model.as_ref().map(|m| m.to_string())),
+        disk_summary::vendor::set(d.vendor.as_ref().map(|v| v.to_string())),
+        disk_summary::serial::set(d.serial.as_ref().map(|s| s.to_string())),
+        disk_summary::sector_size::set(d.sector_size.map(|s| s as i32)),
+        disk_summary::size_raw::set(d.size_raw.map(|s| s as i64)),
+    ]
+}
+
+async fn sync_disks(
+    client: &PrismaClient,
+    machine_id: &str,
+    disks: &[DiskSummary],
+) -> Result<(), QueryError> {
+    let existing = client
+        .disk_summary()
+        .find_many(vec![disk_summary::machine_id::equals(
+            machine_id.to_string(),
+        )])
+        .exec()
+        .await?;
+
+    let mut stale = Vec::new();
+    for row in existing.iter() {
+        match disks.iter().find(|d| *d.device == row.device) {
+            Some(d) => {
+                let mut updates = vec![
+                    disk_summary::r#type::set(convert_disk_type(&d.r#type)),
+                    disk_summary::size_actual::set(d.size_actual as i64),
+                ];
+                updates.extend(disk_optionals(d));
+
+                client
+                    .disk_summary()
+                    .update(disk_summary::id::equals(row.id), updates)
+                    .exec()
+                    .await?;
+            }
+            None => stale.push(row.id),
         }
+    }
 
-        if item.containers.is_some() {
-            let containers_result = self
-                .conn
-                .container_summary()
-                .create_many(
-                    item.containers
-                        .unwrap()
-                        .iter()
-                        .map(|c| {
-                            prisma::container_summary::create_unchecked(
-                                item.id.clone().to_string(),
-                                c.container_id.clone().to_string(),
-                                c.name.clone().to_string(),
-                                c.image.clone().to_string(),
-                                c.created.into(),
-                                c.state.clone().to_string(),
-                                vec![],
-                            )
-                        })
-                        .collect(),
-                )
-                .exec()
-                .await;
+    let added: Vec<&DiskSummary> = disks
+        .iter()
+        .filter(|d| !existing.iter().any(|r| *d.device == r.device))
+        .collect();
+    create_disks(client, machine_id, &added).await?;
+
+    if !stale.is_empty() {
+        client
+            .disk_summary()
+            .delete_many(vec![disk_summary::id::in_vec(stale)])
+            .exec()
+            .await?;
+    }
+
+    Ok(())
+}
+
+async fn create_disks(
+    client: &PrismaClient,
+    machine_id: &str,
+    disks: &[&DiskSummary],
+) -> Result<(), QueryError> {
+    if disks.is_empty() {
+        return Ok(());
+    }
+
+    client
+        .disk_summary()
+        .create_many(
+            disks
+                .iter()
+                .map(|d| {
+                    disk_summary::create_unchecked(
+                        machine_id.to_string(),
+                        d.device.to_string(),
+                        convert_disk_type(&d.r#type),
+                        d.size_actual as i64,
+                        disk_optionals(d),
+                    )
+                })
+                .collect(),
+        )
+        .exec()
+        .await?;
+
+    Ok(())
+}
+
+async fn sync_volumes(
+    client: &PrismaClient,
+    machine_id: &str,
+    volumes: &[VolumeSummary],
+) -> Result<(), QueryError> {
+    let existing = client
+        .volume_summary()
+        .find_many(vec![volume_summary::machine_id::equals(
+            machine_id.to_string(),
+        )])
+        .exec()
+        .await?;
+
+    let mut stale = Vec::new();
+    for row in existing.iter() {
+        match volumes.iter().find(|v| *v.mount_point == row.mount_point) {
+            Some(v) => {
+                client
+                    .volume_summary()
+                    .update(
+                        volume_summary::id::equals(row.id),
+                        vec![
+                            volume_summary::name::set(v.name.to_string()),
+                            volume_summary::total_space::set(v.total_space as i64),
+                            volume_summary::file_system::set(
+                                v.file_system.as_ref().map(|f| f.to_string()),
+                            ),
+                        ],
+                    )
+                    .exec()
+                    .await?;
+            }
+            None => stale.push(row.id),
         }
+    }
 
-        if item.system.is_some() {
-            let system = item.system.unwrap();
-            let system_result = self
-                .conn
-                .system_summary()
-                .create(
-                    machine_summary::id::equals(item.id.clone().to_string()),
-                    system.machine_id.to_string(),
-                    system.family.to_string(),
-                    system.kernel_version.to_string(),
-                    system.os.to_string(),
-                    system.os_version.to_string(),
-                    system.os_pretty.to_string(),
-                    system.hostname.to_string(),
-                    vec![],
-                )
-                .exec()
-                .await;
+    let added: Vec<&VolumeSummary> = volumes
+        .iter()
+        .filter(|v| !existing.iter().any(|r| *v.mount_point == r.mount_point))
+        .collect();
+    create_volumes(client, machine_id, &added).await?;
+
+    if !stale.is_empty() {
+        client
+            .volume_summary()
+            .delete_many(vec![volume_summary::id::in_vec(stale)])
+            .exec()
+            .await?;
+    }
+
+    Ok(())
+}
+
+async fn create_volumes(
+    client: &PrismaClient,
+    machine_id: &str,
+    volumes: &[&VolumeSummary],
+) -> Result<(), QueryError> {
+    if volumes.is_empty() {
+        return Ok(());
+    }
+
+    client
+        .volume_summary()
+        .create_many(
+            volumes
+                .iter()
+                .map(|v| {
+                    volume_summary::create_unchecked(
+                        machine_id.to_string(),
+                        v.name.to_string(),
+                        v.mount_point.to_string(),
+                        v.total_space as i64,
+                        vec![volume_summary::file_system::set(
+                            v.file_system.as_ref().map(|f| f.to_string()),
+                        )],
+                    )
+                })
+                .collect(),
+        )
+        .exec()
+        .await?;
+
+    Ok(())
+}
+
+fn network_interface_optionals(
+    n: &NetworkInterfaceSummary,
+) -> Vec<network_interface_summary::SetParam> {
+    vec![
+        network_interface_summary::addresses::set(
+            n.addresses.iter().map(|a| a.to_string()).collect(),
+        ),
+        network_interface_summary::r#virtual::set(n.r#virtual),
+        network_interface_summary::mac_address::set(n.mac.as_ref().map(|m| m.to_string())),
+        network_interface_summary::vendor::set(n.vendor.as_ref().map(|v| v.to_string())),
+        network_interface_summary::mtu::set(n.mtu.map(|m| m as i32)),
+        network_interface_summary::speed::set(n.speed.map(|s| s as i32)),
+        network_interface_summary::duplex::set(n.duplex.as_ref().map(|d| d.to_string())),
+    ]
+}
+
+async fn sync_network_interfaces(
+    client: &PrismaClient,
+    machine_id: &str,
+    interfaces: &[NetworkInterfaceSummary],
+) -> Result<(), QueryError> {
+    let existing = client
+        .network_interface_summary()
+        .find_many(vec![network_interface_summary::machine_id::equals(
+            machine_id.to_string(),
+        )])
+        .exec()
+        .await?;
+
+    let mut stale = Vec::new();
+    for row in existing.iter() {
+        match interfaces.iter().find(|n| *n.name == row.name) {
+            Some(n) => {
+                client
+                    .network_interface_summary()
+                    .update(
+                        network_interface_summary::id::equals(row.id),
+                        network_interface_optionals(n),
+                    )
+                    .exec()
+                    .await?;
+            }
+            None => stale.push(row.id),
         }
+    }
 
-        if item.memory.is_some() {
-            let memory = item.memory.unwrap();
-            let memory_result = self
-                .conn
-                .memory_summary()
-                .create(
-                    machine_summary::id::equals(item.id.clone().to_string()),
-                    memory.memory as i64,
-                    memory.swap as i64,
-                    vec![],
-                )
-                .exec()
-                .await;
+    let added: Vec<&NetworkInterfaceSummary> = interfaces
+        .iter()
+        .filter(|n| !existing.iter().any(|r| *n.name == r.name))
+        .collect();
+    create_network_interfaces(client, machine_id, &added).await?;
+
+    if !stale.is_empty() {
+        client
+            .network_interface_summary()
+            .delete_many(vec![network_interface_summary::id::in_vec(stale)])
+            .exec()
+            .await?;
+    }
+
+    Ok(())
+}
+
+async fn create_network_interfaces(
+    client: &PrismaClient,
+    machine_id: &str,
+    interfaces: &[&NetworkInterfaceSummary],
+) -> Result<(), QueryError> {
+    if interfaces.is_empty() {
+        return Ok(());
+    }
+
+    client
+        .network_interface_summary()
+        .create_many(
+            interfaces
+                .iter()
+                .map(|n| {
+                    network_interface_summary::create_unchecked(
+                        machine_id.to_string(),
+                        n.name.to_string(),
+                        network_interface_optionals(n),
+                    )
+                })
+                .collect(),
+        )
+        .exec()
+        .await?;
+
+    Ok(())
+}
+
+fn convert_address_version(v: &AddressVersion) -> prisma::AddressVersion {
+    match v {
+        AddressVersion::V4 => prisma::AddressVersion::V4,
+        AddressVersion::V6 => prisma::AddressVersion::V6,
+        AddressVersion::V6Local => prisma::AddressVersion::V6Local,
+    }
+}
+
+fn address_optionals(a: &AddressSummary) -> Vec<address_summary::SetParam> {
+    vec![
+        address_summary::netmask::set(a.netmask.as_ref().map(|n| n.to_string())),
+        address_summary::broadcast::set(a.broadcast.as_ref().map(|b| b.to_string())),
+    ]
+}
+
+async fn sync_addresses(
+    client: &PrismaClient,
+    machine_id: &str,
+    addresses: &[AddressSummary],
+) -> Result<(), QueryError> {
+    let existing = client
+        .address_summary()
+        .find_many(vec![address_summary::machine_id::equals(
+            machine_id.to_string(),
+        )])
+        .exec()
+        .await?;
+
+    let mut stale = Vec::new();
+    for row in existing.iter() {
+        match addresses.iter().find(|a| *a.address == row.address) {
+            Some(a) => {
+                let mut updates = vec![address_summary::version::set(convert_address_version(
+                    &a.version,
+                ))];
+                updates.extend(address_optionals(a));
+
+                client
+                    .address_summary()
+                    .update(address_summary::id::equals(row.id), updates)
+                    .exec()
+                    .await?;
+            }
+            None => stale.push(row.id),
         }
+    }
+
+    let added: Vec<&AddressSummary> = addresses
+        .iter()
+        .filter(|a| !existing.iter().any(|r| *a.address == r.address))
+        .collect();
+    create_addresses(client, machine_id, &added).await?;
 
-        if item.cpu.is_some() {
-            let cpu = item.cpu.unwrap();
+    if !stale.is_empty() {
+        client
+            .address_summary()
+            .delete_many(vec![address_summary::id::in_vec(stale)])
+            .exec()
+            .await?;
+    }
 
-            let mut optionals: Vec<prisma::cpu_summary::SetParam> = Vec::new();
+    Ok(())
+}
 
-            if cpu.vendor.is_some() {
-                optionals.push(prisma::cpu_summary::vendor::set(Some(
-                    cpu.vendor.unwrap().to_string(),
-                )));
+async fn create_addresses(
+    client: &PrismaClient,
+    machine_id: &str,
+    addresses: &[&AddressSummary],
+) -> Result<(), QueryError> {
+    if addresses.is_empty() {
+        return Ok(());
+    }
+
+    client
+        .address_summary()
+        .create_many(
+            addresses
+                .iter()
+                .map(|a| {
+                    address_summary::create_unchecked(
+                        machine_id.to_string(),
+                        convert_address_version(&a.version),
+                        a.address.to_string(),
+                        address_optionals(a),
+                    )
+                })
+                .collect(),
+        )
+        .exec()
+        .await?;
+
+    Ok(())
+}
+
+async fn sync_containers(
+    client: &PrismaClient,
+    machine_id: &str,
+    containers: &[ContainerSummary],
+) -> Result<(), QueryError> {
+    let existing = client
+        .container_summary()
+        .find_many(vec![container_summary::machine_id::equals(
+            machine_id.to_string(),
+        )])
+        .exec()
+        .await?;
+
+    let mut stale = Vec::new();
+    for row in existing.iter() {
+        match containers
+            .iter()
+            .find(|c| *c.container_id == row.container_id)
+        {
+            Some(c) => {
+                client
+                    .container_summary()
+                    .update(
+                        container_summary::id::equals(row.id),
+                        vec![
+                            container_summary::name::set(c.name.to_string()),
+                            container_summary::image::set(c.image.to_string()),
+                            container_summary::created::set(c.created.into()),
+                            container_summary::state::set(c.state.to_string()),
+                            container_summary::health::set(
+                                c.health.as_ref().map(|h| h.to_string()),
+                            ),
+                            container_summary::restart_count::set(c.restart_count as i32),
+                            container_summary::exit_code::set(c.exit_code.map(|e| e as i32)),
+                            container_summary::oom_killed::set(c.oom_killed),
+                        ],
+                    )
+                    .exec()
+                    .await?;
             }
+            None => stale.push(row.id),
+        }
+    }
+
+    let added: Vec<&ContainerSummary> = containers
+        .iter()
+        .filter(|c| !existing.iter().any(|r| *c.container_id == r.container_id))
+        .collect();
+    create_containers(client, machine_id, &added).await?;
+
+    if !stale.is_empty() {
+        client
+            .container_summary()
+            .delete_many(vec![container_summary::id::in_vec(stale)])
+            .exec()
+            .await?;
+    }
+
+    Ok(())
+}
+
+async fn create_containers(
+    client: &PrismaClient,
+    machine_id: &str,
+    containers: &[&ContainerSummary],
+) -> Result<(), QueryError> {
+    if containers.is_empty() {
+        return Ok(());
+    }
+
+    client
+        .container_summary()
+        .create_many(
+            containers
+                .iter()
+                .map(|c| {
+                    container_summary::create_unchecked(
+                        machine_id.to_string(),
+                        c.container_id.to_string(),
+                        c.name.to_string(),
+                        c.image.to_string(),
+                        c.created.into(),
+                        c.state.to_string(),
+                        vec![
+                            container_summary::health::set(
+                                c.health.as_ref().map(|h| h.to_string()),
+                            ),
+                            container_summary::restart_count::set(c.restart_count as i32),
+                            container_summary::exit_code::set(c.exit_code.map(|e| e as i32)),
+                            container_summary::oom_killed::set(c.oom_killed),
+                        ],
+                    )
+                })
+                .collect(),
+        )
+        .exec()
+        .await?;
+
+    Ok(())
+}
+
+fn image_optionals(i: &ImageSummary) -> Vec<image_summary::SetParam> {
+    vec![
+        image_summary::tags::set(i.tags.iter().map(|t| t.to_string()).collect()),
+        image_summary::dangling::set(i.dangling),
+        image_summary::containers::set(i.containers.map(|c| c as i64)),
+    ]
+}
 
-            if cpu.model.is_some() {
-                optionals.push(prisma::cpu_summary::model::set(Some(
-                    cpu.model.unwrap().to_string(),
-                )));
+async fn sync_images(
+    client: &PrismaClient,
+    machine_id: &str,
+    images: &[ImageSummary],
+) -> Result<(), QueryError> {
+    let existing = client
+        .image_summary()
+        .find_many(vec![image_summary::machine_id::equals(
+            machine_id.to_string(),
+        )])
+        .exec()
+        .await?;
+
+    let mut stale = Vec::new();
+    for row in existing.iter() {
+        match images
+            .iter()
+            .find(|i| *i.image_id == row.image_id && *i.runtime == row.runtime)
+        {
+            Some(i) => {
+                let mut params = image_optionals(i);
+                params.push(image_summary::size::set(i.size as i64));
+                params.push(image_summary::created::set(i.created.into()));
+                client
+                    .image_summary()
+                    .update(image_summary::id::equals(row.id), params)
+                    .exec()
+                    .await?;
             }
+            None => stale.push(row.id),
+        }
+    }
 
-            let cpu_result = self
-                .conn
-                .cpu_summary()
-                .create(
-                    machine_summary::id::equals(item.id.clone().to_string()),
-                    cpu.cores as i32,
-                    cpu.architecture.to_string(),
-                    optionals,
-                )
-                .exec()
-                .await;
+    let added: Vec<&ImageSummary> = images
+        .iter()
+        .filter(|i| {
+            !existing
+                .iter()
+                .any(|r| *i.image_id == r.image_id && *i.runtime == r.runtime)
+        })
+        .collect();
+    create_images(client, machine_id, &added).await?;
+
+    if !stale.is_empty() {
+        client
+            .image_summary()
+            .delete_many(vec![image_summary::id::in_vec(stale)])
+            .exec()
+            .await?;
+    }
+
+    Ok(())
+}
+
+async fn create_images(
+    client: &PrismaClient,
+    machine_id: &str,
+    images: &[&ImageSummary],
+) -> Result<(), QueryError> {
+    if images.is_empty() {
+        return Ok(());
+    }
+
+    client
+        .image_summary()
+        .create_many(
+            images
+                .iter()
+                .map(|i| {
+                    image_summary::create_unchecked(
+                        machine_id.to_string(),
+                        i.image_id.to_string(),
+                        i.runtime.to_string(),
+                        i.size as i64,
+                        i.created.into(),
+                        image_optionals(i),
+                    )
+                })
+                .collect(),
+        )
+        .exec()
+        .await?;
+
+    Ok(())
+}
+
+async fn sync_container_volumes(
+    client: &PrismaClient,
+    machine_id: &str,
+    volumes: &[ContainerVolumeSummary],
+) -> Result<(), QueryError> {
+    let existing = client
+        .container_volume_summary()
+        .find_many(vec![container_volume_summary::machine_id::equals(
+            machine_id.to_string(),
+        )])
+        .exec()
+        .await?;
+
+    let mut stale = Vec::new();
+    for row in existing.iter() {
+        match volumes
+            .iter()
+            .find(|v| *v.name == row.name && *v.runtime == row.runtime)
+        {
+            Some(v) => {
+                client
+                    .container_volume_summary()
+                    .update(
+                        container_volume_summary::id::equals(row.id),
+                        vec![
+                            container_volume_summary::driver::set(v.driver.to_string()),
+                            container_volume_summary::mount_point::set(v.mount_point.to_string()),
+                            container_volume_summary::size::set(v.size.map(|s| s as i64)),
+                        ],
+                    )
+                    .exec()
+                    .await?;
+            }
+            None => stale.push(row.id),
         }
+    }
 
-        match result {
-            Ok(_) => return Ok(()),
-            Err(_) => return Err("item".to_string()),
+    let added: Vec<&ContainerVolumeSummary> = volumes
+        .iter()
+        .filter(|v| {
+            !existing
+                .iter()
+                .any(|r| *v.name == r.name && *v.runtime == r.runtime)
+        })
+        .collect();
+    create_container_volumes(client, machine_id, &added).await?;
+
+    if !stale.is_empty() {
+        client
+            .container_volume_summary()
+            .delete_many(vec![container_volume_summary::id::in_vec(stale)])
+            .exec()
+            .await?;
+    }
+
+    Ok(())
+}
+
+async fn create_container_volumes(
+    client: &PrismaClient,
+    machine_id: &str,
+    volumes: &[&ContainerVolumeSummary],
+) -> Result<(), QueryError> {
+    if volumes.is_empty() {
+        return Ok(());
+    }
+
+    client
+        .container_volume_summary()
+        .create_many(
+            volumes
+                .iter()
+                .map(|v| {
+                    container_volume_summary::create_unchecked(
+                        machine_id.to_string(),
+                        v.name.to_string(),
+                        v.runtime.to_string(),
+                        v.driver.to_string(),
+                        v.mount_point.to_string(),
+                        vec![container_volume_summary::size::set(
+                            v.size.map(|s| s as i64),
+                        )],
+                    )
+                })
+                .collect(),
+        )
+        .exec()
+        .await?;
+
+    Ok(())
+}
+
+fn container_network_optionals(
+    n: &ContainerNetworkSummary,
+) -> Vec<container_network_summary::SetParam> {
+    vec![
+        container_network_summary::internal::set(n.internal),
+        container_network_summary::subnets::set(n.subnets.iter().map(|s| s.to_string()).collect()),
+        container_network_summary::driver::set(n.driver.as_ref().map(|d| d.to_string())),
+        container_network_summary::scope::set(n.scope.as_ref().map(|s| s.to_string())),
+    ]
+}
+
+async fn sync_container_networks(
+    client: &PrismaClient,
+    machine_id: &str,
+    networks: &[ContainerNetworkSummary],
+) -> Result<(), QueryError> {
+    let existing = client
+        .container_network_summary()
+        .find_many(vec![container_network_summary::machine_id::equals(
+            machine_id.to_string(),
+        )])
+        .exec()
+        .await?;
+
+    let mut stale = Vec::new();
+    for row in existing.iter() {
+        match networks
+            .iter()
+            .find(|n| *n.network_id == row.network_id && *n.runtime == row.runtime)
+        {
+            Some(n) => {
+                let mut params = container_network_optionals(n);
+                params.push(container_network_summary::name::set(n.name.to_string()));
+                client
+                    .container_network_summary()
+                    .update(container_network_summary::id::equals(row.id), params)
+                    .exec()
+                    .await?;
+            }
+            None => stale.push(row.id),
         }
     }
 
-    async fn delete(&mut self, id: String) -> Result<(), String> {
-        let result = self
-            .conn
-            .machine_summary()
-            .delete(machine_summary::id::equals(id))
+    let added: Vec<&ContainerNetworkSummary> = networks
+        .iter()
+        .filter(|n| {
+            !existing
+                .iter()
+                .any(|r| *n.network_id == r.network_id && *n.runtime == r.runtime)
+        })
+        .collect();
+    create_container_networks(client, machine_id, &added).await?;
+
+    if !stale.is_empty() {
+        client
+            .container_network_summary()
+            .delete_many(vec![container_network_summary::id::in_vec(stale)])
             .exec()
-            .await;
+            .await?;
+    }
 
-        match result {
-            Ok(_) => return Ok(()),
-            Err(e) => return Err(e.to_string()),
+    Ok(())
+}
+
+async fn create_container_networks(
+    client: &PrismaClient,
+    machine_id: &str,
+    networks: &[&ContainerNetworkSummary],
+) -> Result<(), QueryError> {
+    if networks.is_empty() {
+        return Ok(());
+    }
+
+    client
+        .container_network_summary()
+        .create_many(
+            networks
+                .iter()
+                .map(|n| {
+                    container_network_summary::create_unchecked(
+                        machine_id.to_string(),
+                        n.network_id.to_string(),
+                        n.name.to_string(),
+                        n.runtime.to_string(),
+                        container_network_optionals(n),
+                    )
+                })
+                .collect(),
+        )
+        .exec()
+        .await?;
+
+    Ok(())
+}
+
+fn container_disk_usage_params(
+    u: &ContainerDiskUsageSummary,
+) -> Vec<container_disk_usage_summary::SetParam> {
+    vec![
+        container_disk_usage_summary::images_size::set(u.images.size as i64),
+        container_disk_usage_summary::images_reclaimable::set(u.images.reclaimable as i64),
+        container_disk_usage_summary::containers_size::set(u.containers.size as i64),
+        container_disk_usage_summary::containers_reclaimable::set(u.containers.reclaimable as i64),
+        container_disk_usage_summary::volumes_size::set(u.volumes.size as i64),
+        container_disk_usage_summary::volumes_reclaimable::set(u.volumes.reclaimable as i64),
+        container_disk_usage_summary::build_cache_size::set(u.build_cache.size as i64),
+        container_disk_usage_summary::build_cache_reclaimable::set(
+            u.build_cache.reclaimable as i64,
+        ),
+    ]
+}
+
+async fn sync_container_disk_usage(
+    client: &PrismaClient,
+    machine_id: &str,
+    usage: &[ContainerDiskUsageSummary],
+) -> Result<(), QueryError> {
+    let existing = client
+        .container_disk_usage_summary()
+        .find_many(vec![container_disk_usage_summary::machine_id::equals(
+            machine_id.to_string(),
+        )])
+        .exec()
+        .await?;
+
+    let mut stale = Vec::new();
+    for row in existing.iter() {
+        match usage.iter().find(|u| *u.runtime == row.runtime) {
+            Some(u) => {
+                client
+                    .container_disk_usage_summary()
+                    .update(
+                        container_disk_usage_summary::id::equals(row.id),
+                        container_disk_usage_params(u),
+                    )
+                    .exec()
+                    .await?;
+            }
+            None => stale.push(row.id),
+        }
+    }
+
+    let added: Vec<&ContainerDiskUsageSummary> = usage
+        .iter()
+        .filter(|u| !existing.iter().any(|r| *u.runtime == r.runtime))
+        .collect();
+    create_container_disk_usage(client, machine_id, &added).await?;
+
+    if !stale.is_empty() {
+        client
+            .container_disk_usage_summary()
+            .delete_many(vec![container_disk_usage_summary::id::in_vec(stale)])
+            .exec()
+            .await?;
+    }
+
+    Ok(())
+}
+
+async fn create_container_disk_usage(
+    client: &PrismaClient,
+    machine_id: &str,
+    usage: &[&ContainerDiskUsageSummary],
+) -> Result<(), QueryError> {
+    if usage.is_empty() {
+        return Ok(());
+    }
+
+    client
+        .container_disk_usage_summary()
+        .create_many(
+            usage
+                .iter()
+                .map(|u| {
+                    container_disk_usage_summary::create_unchecked(
+                        machine_id.to_string(),
+                        u.runtime.to_string(),
+                        u.images.size as i64,
+                        u.images.reclaimable as i64,
+                        u.containers.size as i64,
+                        u.containers.reclaimable as i64,
+                        u.volumes.size as i64,
+                        u.volumes.reclaimable as i64,
+                        u.build_cache.size as i64,
+                        u.build_cache.reclaimable as i64,
+                        vec![],
+                    )
+                })
+                .collect(),
+        )
+        .exec()
+        .await?;
+
+    Ok(())
+}
+
+fn stack_params(s: &StackSummary) -> Vec<stack_summary::SetParam> {
+    vec![
+        stack_summary::health::set(s.health.to_string()),
+        stack_summary::services::set(s.services.iter().map(|v| v.to_string()).collect()),
+        stack_summary::running::set(s.running as i32),
+        stack_summary::total::set(s.total as i32),
+    ]
+}
+
+async fn sync_stacks(
+    client: &PrismaClient,
+    machine_id: &str,
+    stacks: &[StackSummary],
+) -> Result<(), QueryError> {
+    let existing = client
+        .stack_summary()
+        .find_many(vec![stack_summary::machine_id::equals(
+            machine_id.to_string(),
+        )])
+        .exec()
+        .await?;
+
+    let mut stale = Vec::new();
+    for row in existing.iter() {
+        match stacks
+            .iter()
+            .find(|s| *s.project == row.project && *s.runtime == row.runtime)
+        {
+            Some(s) => {
+                client
+                    .stack_summary()
+                    .update(stack_summary::id::equals(row.id), stack_params(s))
+                    .exec()
+                    .await?;
+            }
+            None => stale.push(row.id),
         }
     }
+
+    let added: Vec<&StackSummary> = stacks
+        .iter()
+        .filter(|s| {
+            !existing
+                .iter()
+                .any(|r| *s.project == r.project && *s.runtime == r.runtime)
+        })
+        .collect();
+    create_stacks(client, machine_id, &added).await?;
+
+    if !stale.is_empty() {
+        client
+            .stack_summary()
+            .delete_many(vec![stack_summary::id::in_vec(stale)])
+            .exec()
+            .await?;
+    }
+
+    Ok(())
+}
+
+async fn create_stacks(
+    client: &PrismaClient,
+    machine_id: &str,
+    stacks: &[&StackSummary],
+) -> Result<(), QueryError> {
+    if stacks.is_empty() {
+        return Ok(());
+    }
+
+    client
+        .stack_summary()
+        .create_many(
+            stacks
+                .iter()
+                .map(|s| {
+                    stack_summary::create_unchecked(
+                        machine_id.to_string(),
+                        s.project.to_string(),
+                        s.runtime.to_string(),
+                        s.health.to_string(),
+                        s.running as i32,
+                        s.total as i32,
+                        vec![stack_summary::services::set(
+                            s.services.iter().map(|v| v.to_string()).collect(),
+                        )],
+                    )
+                })
+                .collect(),
+        )
+        .exec()
+        .await?;
+
+    Ok(())
 }
 
 fn convert_disk_type(t: &DiskType) -> prisma::DiskType {
@@ -630,6 +1869,112 @@ fn convert_container_summaries(
             image: container.image.clone().into(),
             created: container.created.into(),
             state: container.state.clone().into(),
+            health: handle_optional_string(container.health.clone()),
+            restart_count: container.restart_count as u64,
+            exit_code: container.exit_code.map(|e| e as i64),
+            oom_killed: container.oom_killed,
+        });
+    }
+    let it = result.into();
+    Some(it)
+}
+
+fn convert_image_summaries(i: Vec<prisma::image_summary::Data>) -> Option<Arc<[ImageSummary]>> {
+    let mut result = Vec::new();
+    for image in i {
+        result.push(ImageSummary {
+            image_id: image.image_id.clone().into(),
+            runtime: image.runtime.clone().into(),
+            tags: image.tags.iter().map(|t| t.to_owned().into()).collect(),
+            size: image.size as u64,
+            created: image.created.into(),
+            dangling: image.dangling,
+            containers: handle_optional_big_int(image.containers),
+        });
+    }
+    let it = result.into();
+    Some(it)
+}
+
+fn convert_container_volume_summaries(
+    v: Vec<prisma::container_volume_summary::Data>,
+) -> Option<Arc<[ContainerVolumeSummary]>> {
+    let mut result = Vec::new();
+    for volume in v {
+        result.push(ContainerVolumeSummary {
+            name: volume.name.clone().into(),
+            runtime: volume.runtime.clone().into(),
+            driver: volume.driver.clone().into(),
+            mount_point: volume.mount_point.clone().into(),
+            size: handle_optional_big_int(volume.size),
+        });
+    }
+    let it = result.into();
+    Some(it)
+}
+
+fn convert_container_network_summaries(
+    n: Vec<prisma::container_network_summary::Data>,
+) -> Option<Arc<[ContainerNetworkSummary]>> {
+    let mut result = Vec::new();
+    for network in n {
+        result.push(ContainerNetworkSummary {
+            network_id: network.network_id.clone().into(),
+            name: network.name.clone().into(),
+            runtime: network.runtime.clone().into(),
+            internal: network.internal,
+            subnets: network
+                .subnets
+                .iter()
+                .map(|s| s.to_owned().into())
+                .collect(),
+            driver: handle_optional_string(network.driver.clone()),
+            scope: handle_optional_string(network.scope.clone()),
+        });
+    }
+    let it = result.into();
+    Some(it)
+}
+
+fn convert_container_disk_usage_summaries(
+    u: Vec<prisma::container_disk_usage_summary::Data>,
+) -> Option<Arc<[ContainerDiskUsageSummary]>> {
+    let mut result = Vec::new();
+    for usage in u {
+        result.push(ContainerDiskUsageSummary {
+            runtime: usage.runtime.clone().into(),
+            images: DiskUsageEntry {
+                size: usage.images_size as u64,
+                reclaimable: usage.images_reclaimable as u64,
+            },
+            containers: DiskUsageEntry {
+                size: usage.containers_size as u64,
+                reclaimable: usage.containers_reclaimable as u64,
+            },
+            volumes: DiskUsageEntry {
+                size: usage.volumes_size as u64,
+                reclaimable: usage.volumes_reclaimable as u64,
+            },
+            build_cache: DiskUsageEntry {
+                size: usage.build_cache_size as u64,
+                reclaimable: usage.build_cache_reclaimable as u64,
+            },
+        });
+    }
+    let it = result.into();
+    Some(it)
+}
+
+fn convert_stack_summaries(s: Vec<prisma::stack_summary::Data>) -> Option<Arc<[StackSummary]>> {
+    let mut result = Vec::new();
+    for stack in s {
+        result.push(StackSummary {
+            project: stack.project.clone().into(),
+            runtime: stack.runtime.clone().into(),
+            health: stack.health.clone().into(),
+            services: stack.services.iter().map(|v| v.to_owned().into()).collect(),
+            running: stack.running as u64,
+            total: stack.total as u64,
         });
     }
     let it = result.into();
@@ -682,6 +2027,14 @@ fn convert_status_summary(s: Option<prisma::machine_status_summary::Data>) -> Ma
     }
 }
 
+fn convert_machine_state_to_prisma(s: &MachineState) -> prisma::MachineStatus {
+    match s {
+        MachineState::Running => prisma::MachineStatus::Running,
+        MachineState::Stopped => prisma::MachineStatus::Stopped,
+        MachineState::Unknown => prisma::MachineStatus::Unknown,
+    }
+}
+
 fn convert_machine_type(t: prisma::MachineType) -> MachineType {
     match t {
         prisma::MachineType::BareMetal => MachineType::BareMetal,
@@ -697,3 +2050,33 @@ fn convert_machine_type_to_prisma(t: MachineType) -> prisma::MachineType {
         MachineType::Hypervisor => prisma::MachineType::Hypervisor,
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn splits_port_from_legacy_address() {
+        assert_eq!(
+            split_legacy_address("10.0.0.4:7040").unwrap(),
+            ("10.0.0.4".to_string(), 7040)
+        );
+        assert_eq!(
+            split_legacy_address("[fd00::4]:7032").unwrap(),
+            ("[fd00::4]".to_string(), 7032)
+        );
+    }
+
+    #[test]
+    fn legacy_address_without_port_uses_default() {
+        assert_eq!(
+            split_legacy_address("nas.local").unwrap(),
+            ("nas.local".to_string(), DEFAULT_AGENT_PORT)
+        );
+    }
+
+    #[test]
+    fn rejects_unparseable_legacy_address() {
+        assert!(split_legacy_address("nas.local:port").is_err());
+    }
+}
@@ -1,2 +1,4 @@
+pub mod group_repo;
+pub mod machine_repo;
 pub mod repository;
-pub mod machine_repo;
\ No newline at end of file
+pub mod secret;
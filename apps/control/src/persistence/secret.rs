@@ -0,0 +1,83 @@
+use aes_gcm::{
+    aead::{Aead, KeyInit},
+    Aes256Gcm, Key, Nonce,
+};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use rand::{rngs::OsRng, RngCore};
+
+const NONCE_LENGTH: usize = 12;
+
+/// Encrypts secrets such as agent api keys before they are written to the
+/// database. Values are stored as base64 of the nonce followed by the
+/// ciphertext.
+#[derive(Clone)]
+pub struct SecretCipher {
+    cipher: Aes256Gcm,
+}
+
+impl SecretCipher {
+    /// Creates a cipher from a base64 encoded 32 byte key.
+    pub fn new(key: &str) -> Result<SecretCipher, String> {
+        let key = STANDARD.decode(key).map_err(|e| e.to_string())?;
+
+        if key.len() != 32 {
+            return Err(format!("secret key must be 32 bytes, got {}", key.len()));
+        }
+
+        let cipher = Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(&key));
+
+        Ok(SecretCipher { cipher })
+    }
+
+    pub fn encrypt(&self, plaintext: &str) -> Result<String, String> {
+        let mut nonce = [0u8; NONCE_LENGTH];
+        OsRng.fill_bytes(&mut nonce);
+
+        let ciphertext = self
+            .cipher
+            .encrypt(Nonce::from_slice(&nonce), plaintext.as_bytes())
+            .map_err(|e| e.to_string())?;
+
+        let mut sealed = nonce.to_vec();
+        sealed.extend(ciphertext);
+
+        Ok(STANDARD.encode(sealed))
+    }
+
+    pub fn decrypt(&self, sealed: &str) -> Result<String, String> {
+        let sealed = STANDARD.decode(sealed).map_err(|e| e.to_string())?;
+
+        if sealed.len() < NONCE_LENGTH {
+            return Err("sealed secret is too short".to_string());
+        }
+
+        let (nonce, ciphertext) = sealed.split_at(NONCE_LENGTH);
+        let plaintext = self
+            .cipher
+            .decrypt(Nonce::from_slice(nonce), ciphertext)
+            .map_err(|e| e.to_string())?;
+
+        String::from_utf8(plaintext).map_err(|e| e.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const KEY: &str = "AAECAwQFBgcICQoLDA0ODxAREhMUFRYXGBkaGxwdHh8=";
+
+    #[test]
+    fn round_trips() {
+        let cipher = SecretCipher::new(KEY).unwrap();
+        let sealed = cipher.encrypt("toes").unwrap();
+
+        assert_ne!(sealed, "toes");
+        assert_eq!(cipher.decrypt(&sealed).unwrap(), "toes");
+    }
+
+    #[test]
+    fn rejects_short_keys() {
+        assert!(SecretCipher::new("AAEC").is_err());
+    }
+}
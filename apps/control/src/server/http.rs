@@ -19,14 +19,23 @@ use tokio::sync::Mutex;
 
 use crate::{
     config::ServerConfig,
-    controller::agent::AgentController,
+    controller::{agent::AgentController, group::GroupController},
     server::operation::{
         group::{
             create::create_group, delete::delete_group, describe::describe_group, list::list_groups,
         },
         machine::{
-            describe::describe_machine, list::list_machines, register::register_machine,
-            remove::remove_machine, utilization::describe_machine_utilization,
+            container::{
+                pause_machine_container, remove_machine_container, restart_machine_container,
+                start_machine_container, stop_machine_container, unpause_machine_container,
+            },
+            describe::describe_machine,
+            list::list_machines,
+            metrics::get_machine_metric_history,
+            register::register_machine,
+            remove::remove_machine,
+            sync::sync_machine,
+            utilization::describe_machine_utilization,
         },
     },
 };
@@ -38,11 +47,18 @@ struct Config;
 
 pub struct State {
     pub controller: Arc<Mutex<AgentController>>,
+    pub group_controller: Arc<Mutex<GroupController>>,
 }
 
 impl State {
-    pub fn new(controller: Arc<Mutex<AgentController>>) -> State {
-        State { controller }
+    pub fn new(
+        controller: Arc<Mutex<AgentController>>,
+        group_controller: Arc<Mutex<GroupController>>,
+    ) -> State {
+        State {
+            controller,
+            group_controller,
+        }
     }
 }
 
@@ -52,8 +68,12 @@ pub async fn check_health(
     Ok(output::HealthOutput { success: true })
 }
 
-pub async fn start_server(controller: Arc<Mutex<AgentController>>, config: ServerConfig) {
-    let auth_controller = AuthController::new(config.no_auth_operations(), config.allowed_keys());
+pub async fn start_server(
+    controller: Arc<Mutex<AgentController>>,
+    group_controller: Arc<Mutex<GroupController>>,
+    config: ServerConfig,
+) {
+    let auth_controller = AuthController::new(config.no_auth_operations(), config.keys());
 
     let plugins = PluginPipeline::new()
         .print()
@@ -65,9 +85,17 @@ pub async fn start_server(controller: Arc<Mutex<AgentController>>, config: Serve
         .health(check_health)
         .describe_machine(describe_machine)
         .describe_machine_utilization(describe_machine_utilization)
+        .get_machine_metric_history(get_machine_metric_history)
         .list_machines(list_machines)
         .register_machine(register_machine)
         .remove_machine(remove_machine)
+        .sync_machine(sync_machine)
+        .start_machine_container(start_machine_container)
+        .stop_machine_container(stop_machine_container)
+        .restart_machine_container(restart_machine_container)
+        .pause_machine_container(pause_machine_container)
+        .unpause_machine_container(unpause_machine_container)
+        .remove_machine_container(remove_machine_container)
         .create_group(create_group)
         .delete_group(delete_group)
         .describe_group(describe_group)
@@ -76,7 +104,7 @@ pub async fn start_server(controller: Arc<Mutex<AgentController>>, config: Serve
         .expect("failed to build an instance of Geth Control Server");
 
     // create state to add to request
-    let state = State::new(controller);
+    let state = State::new(controller, group_controller);
     let app = app
         .layer(&AddExtensionLayer::new(Arc::new(state)))
         .layer(&ServerRequestIdProviderLayer::new());
@@ -0,0 +1,12 @@
+use geth_control_server::model::GroupSummary;
+
+use crate::model::group::Group;
+
+pub fn group_to_summary(group: Group) -> GroupSummary {
+    GroupSummary {
+        id: group.id.to_string(),
+        name: group.name.to_string(),
+        platform: group.platform.map(|p| p.to_string()),
+        location: group.location.map(|l| l.to_string()),
+    }
+}
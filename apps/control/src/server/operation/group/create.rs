@@ -1,10 +1,40 @@
-use std::{sync::Arc};
+use std::sync::Arc;
 
 use aws_smithy_http_server::Extension;
-use geth_control_server::{output::CreateGroupOutput, input::CreateGroupInput, error};
+use geth_control_server::{error, input::CreateGroupInput, output::CreateGroupOutput};
+use log::{error, info};
 
-use crate::server::http::State;
+use crate::{controller::error::ControllerError, server::http::State};
 
-pub async fn create_group(input: CreateGroupInput, state: Extension<Arc<State>>) -> Result<CreateGroupOutput, error::CreateGroupError> {
-    Err(error::CreateGroupError::ResourceNotFoundException(error::ResourceNotFoundException { message: format!("Group {} not found", input.name()) }))
-}
\ No newline at end of file
+use super::conversion::group_to_summary;
+
+pub async fn create_group(
+    input: CreateGroupInput,
+    state: Extension<Arc<State>>,
+) -> Result<CreateGroupOutput, error::CreateGroupError> {
+    info!("Creating group {}", input.name());
+    let mut controller = state.group_controller.lock().await;
+
+    let group_result = controller
+        .create_group(input.name(), input.platform(), input.location())
+        .await;
+
+    match group_result {
+        Ok(g) => Ok(CreateGroupOutput {
+            summary: group_to_summary(g),
+        }),
+        Err(ControllerError::Persistence(e)) => {
+            error!("Error creating group {}: {}", input.name(), e);
+            Err(error::CreateGroupError::InternalServerException(
+                error::InternalServerException {
+                    message: format!("Error creating group {}", input.name()),
+                },
+            ))
+        }
+        Err(e) => Err(error::CreateGroupError::InvalidInputException(
+            error::InvalidInputException {
+                message: format!("Error creating group {}: {}", input.name(), e),
+            },
+        )),
+    }
+}
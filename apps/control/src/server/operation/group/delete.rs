@@ -1,10 +1,38 @@
-use std::{sync::Arc};
+use std::sync::Arc;
 
 use aws_smithy_http_server::Extension;
-use geth_control_server::{output::DeleteGroupOutput, input::DeleteGroupInput, error};
+use geth_control_server::{error, input::DeleteGroupInput, output::DeleteGroupOutput};
+use log::error;
 
-use crate::server::http::State;
+use crate::{controller::error::ControllerError, server::http::State};
 
-pub async fn delete_group(input: DeleteGroupInput, state: Extension<Arc<State>>) -> Result<DeleteGroupOutput, error::DeleteGroupError> {
-    Err(error::DeleteGroupError::ResourceNotFoundException(error::ResourceNotFoundException { message: format!("Group {} not found", input.id()) }))
-}
\ No newline at end of file
+pub async fn delete_group(
+    input: DeleteGroupInput,
+    state: Extension<Arc<State>>,
+) -> Result<DeleteGroupOutput, error::DeleteGroupError> {
+    let mut controller = state.group_controller.lock().await;
+
+    let delete_result = controller.delete_group(input.id()).await;
+
+    match delete_result {
+        Ok(_) => Ok(DeleteGroupOutput { success: true }),
+        Err(ControllerError::NotFound(message)) => {
+            Err(error::DeleteGroupError::ResourceNotFoundException(
+                error::ResourceNotFoundException { message },
+            ))
+        }
+        Err(ControllerError::Persistence(e)) => {
+            error!("Error deleting group {}: {}", input.id(), e);
+            Err(error::DeleteGroupError::InternalServerException(
+                error::InternalServerException {
+                    message: format!("Error deleting group {}", input.id()),
+                },
+            ))
+        }
+        Err(e) => Err(error::DeleteGroupError::InvalidInputException(
+            error::InvalidInputException {
+                message: e.to_string(),
+            },
+        )),
+    }
+}
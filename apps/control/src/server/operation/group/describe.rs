@@ -1,10 +1,28 @@
-use std::{sync::Arc};
+use std::sync::Arc;
 
 use aws_smithy_http_server::Extension;
-use geth_control_server::{output::DescribeGroupOutput, input::DescribeGroupInput, error};
+use geth_control_server::{error, input::DescribeGroupInput, output::DescribeGroupOutput};
 
 use crate::server::http::State;
 
-pub async fn describe_group(input: DescribeGroupInput, state: Extension<Arc<State>>) -> Result<DescribeGroupOutput, error::DescribeGroupError> {
-    Err(error::DescribeGroupError::ResourceNotFoundException(error::ResourceNotFoundException { message: format!("Group {} not found", input.id()) }))
-}
\ No newline at end of file
+use super::conversion::group_to_summary;
+
+pub async fn describe_group(
+    input: DescribeGroupInput,
+    state: Extension<Arc<State>>,
+) -> Result<DescribeGroupOutput, error::DescribeGroupError> {
+    let mut controller = state.group_controller.lock().await;
+
+    let group_result = controller.get_group(input.id()).await;
+
+    match group_result {
+        Ok(g) => Ok(DescribeGroupOutput {
+            summary: group_to_summary(g),
+        }),
+        Err(e) => Err(error::DescribeGroupError::ResourceNotFoundException(
+            error::ResourceNotFoundException {
+                message: e.to_string(),
+            },
+        )),
+    }
+}
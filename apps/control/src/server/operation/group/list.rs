@@ -1,10 +1,24 @@
-use std::{sync::Arc};
+use std::sync::Arc;
 
 use aws_smithy_http_server::Extension;
-use geth_control_server::{output::ListGroupsOutput, input::ListGroupsInput, error};
+use geth_control_server::{error, input::ListGroupsInput, output::ListGroupsOutput};
 
 use crate::server::http::State;
 
-pub async fn list_groups(input: ListGroupsInput, state: Extension<Arc<State>>) -> Result<ListGroupsOutput, error::ListGroupsError> {
-    Err(error::ListGroupsError::ValidationException(error::ValidationException { message: format!("Machine not found"), field_list: None }))
-}
\ No newline at end of file
+use super::conversion::group_to_summary;
+
+pub async fn list_groups(
+    _: ListGroupsInput,
+    state: Extension<Arc<State>>,
+) -> Result<ListGroupsOutput, error::ListGroupsError> {
+    let mut controller = state.group_controller.lock().await;
+
+    let groups = controller.list_groups().await;
+
+    let mut summary = Vec::new();
+    for group in groups.iter() {
+        summary.push(group_to_summary(group.clone()))
+    }
+
+    Ok(ListGroupsOutput { summary })
+}
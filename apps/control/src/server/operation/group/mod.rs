@@ -1,4 +1,5 @@
+pub mod conversion;
 pub mod create;
 pub mod delete;
 pub mod describe;
-pub mod list;
\ No newline at end of file
+pub mod list;
@@ -0,0 +1,209 @@
+use std::sync::Arc;
+
+use aws_smithy_http_server::Extension;
+use geth_control_server::{
+    error::{
+        self, AgentUnreachableException, ConflictException, InternalServerException,
+        ResourceNotFoundException,
+    },
+    input::{
+        PauseMachineContainerInput, RemoveMachineContainerInput, RestartMachineContainerInput,
+        StartMachineContainerInput, StopMachineContainerInput, UnpauseMachineContainerInput,
+    },
+    output::{
+        PauseMachineContainerOutput, RemoveMachineContainerOutput, RestartMachineContainerOutput,
+        StartMachineContainerOutput, StopMachineContainerOutput, UnpauseMachineContainerOutput,
+    },
+};
+
+use crate::{
+    controller::error::ControllerError, model::container::ContainerAction, server::http::State,
+};
+
+/// Maps a controller failure onto whichever modeled error the operation uses.
+fn map_error<E>(
+    err: ControllerError,
+    not_found: fn(ResourceNotFoundException) -> E,
+    conflict: fn(ConflictException) -> E,
+    unreachable: fn(AgentUnreachableException) -> E,
+    internal: fn(InternalServerException) -> E,
+) -> E {
+    match err {
+        ControllerError::NotFound(message) => not_found(ResourceNotFoundException { message }),
+        ControllerError::Conflict(message) => conflict(ConflictException { message }),
+        ControllerError::AgentUnreachable(message) => {
+            unreachable(AgentUnreachableException { message })
+        }
+        e => internal(InternalServerException {
+            message: e.to_string(),
+        }),
+    }
+}
+
+async fn run(
+    state: &State,
+    machine_id: &str,
+    container_id: &str,
+    action: ContainerAction,
+) -> Result<(), ControllerError> {
+    let target = state
+        .controller
+        .lock()
+        .await
+        .agent_target(machine_id)
+        .await?;
+    target.container_action(container_id, &action).await
+}
+
+pub async fn start_machine_container(
+    input: StartMachineContainerInput,
+    state: Extension<Arc<State>>,
+) -> Result<StartMachineContainerOutput, error::StartMachineContainerError> {
+    let action = ContainerAction::Start;
+
+    match run(&state, input.identifier(), input.container_id(), action).await {
+        Ok(_) => Ok(StartMachineContainerOutput {}),
+        Err(e) => Err(map_error(
+            e,
+            error::StartMachineContainerError::ResourceNotFoundException,
+            error::StartMachineContainerError::ConflictException,
+            error::StartMachineContainerError::AgentUnreachableException,
+            error::StartMachineContainerError::InternalServerException,
+        )),
+    }
+}
+
+pub async fn stop_machine_container(
+    input: StopMachineContainerInput,
+    state: Extension<Arc<State>>,
+) -> Result<StopMachineContainerOutput, error::StopMachineContainerError> {
+    let action = ContainerAction::Stop {
+        timeout: input.timeout,
+    };
+
+    match run(&state, input.identifier(), input.container_id(), action).await {
+        Ok(_) => Ok(StopMachineContainerOutput {}),
+        Err(e) => Err(map_error(
+            e,
+            error::StopMachineContainerError::ResourceNotFoundException,
+            error::StopMachineContainerError::ConflictException,
+            error::StopMachineContainerError::AgentUnreachableException,
+            error::StopMachineContainerError::InternalServerException,
+        )),
+    }
+}
+
+pub async fn restart_machine_container(
+    input: RestartMachineContainerInput,
+    state: Extension<Arc<State>>,
+) -> Result<RestartMachineContainerOutput, error::RestartMachineContainerError> {
+    let action = ContainerAction::Restart {
+        timeout: input.timeout,
+    };
+
+    match run(&state, input.identifier(), input.container_id(), action).await {
+        Ok(_) => Ok(RestartMachineContainerOutput {}),
+        Err(e) => Err(map_error(
+            e,
+            error::RestartMachineContainerError::ResourceNotFoundException,
+            error::RestartMachineContainerError::ConflictException,
+            error::RestartMachineContainerError::AgentUnreachableException,
+            error::RestartMachineContainerError::InternalServerException,
+        )),
+    }
+}
+
+pub async fn pause_machine_container(
+    input: PauseMachineContainerInput,
+    state: Extension<Arc<State>>,
+) -> Result<PauseMachineContainerOutput, error::PauseMachineContainerError> {
+    let action = ContainerAction::Pause;
+
+    match run(&state, input.identifier(), input.container_id(), action).await {
+        Ok(_) => Ok(PauseMachineContainerOutput {}),
+        Err(e) => Err(map_error(
+            e,
+            error::PauseMachineContainerError::ResourceNotFoundException,
+            error::PauseMachineContainerError::ConflictException,
+            error::PauseMachineContainerError::AgentUnreachableException,
+            error::PauseMachineContainerError::InternalServerException,
+        )),
+    }
+}
+
+pub async fn unpause_machine_container(
+    input: UnpauseMachineContainerInput,
+    state: Extension<Arc<State>>,
+) -> Result<UnpauseMachineContainerOutput, error::UnpauseMachineContainerError> {
+    let action = ContainerAction::Unpause;
+
+    match run(&state, input.identifier(), input.container_id(), action).await {
+        Ok(_) => Ok(UnpauseMachineContainerOutput {}),
+        Err(e) => Err(map_error(
+            e,
+            error::UnpauseMachineContainerError::ResourceNotFoundException,
+            error::UnpauseMachineContainerError::ConflictException,
+            error::UnpauseMachineContainerError::AgentUnreachableException,
+            error::UnpauseMachineContainerError::InternalServerException,
+        )),
+    }
+}
+
+pub async fn remove_machine_container(
+    input: RemoveMachineContainerInput,
+    state: Extension<Arc<State>>,
+) -> Result<RemoveMachineContainerOutput, error::RemoveMachineContainerError> {
+    let action = ContainerAction::Remove {
+        force: input.force.unwrap_or(false),
+        volumes: input.volumes.unwrap_or(false),
+    };
+
+    match run(&state, input.identifier(), input.container_id(), action).await {
+        Ok(_) => Ok(RemoveMachineContainerOutput {}),
+        Err(e) => Err(map_error(
+            e,
+            error::RemoveMachineContainerError::ResourceNotFoundException,
+            error::RemoveMachineContainerError::ConflictException,
+            error::RemoveMachineContainerError::AgentUnreachableException,
+            error::RemoveMachineContainerError::InternalServerException,
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use geth_control_server::error::StopMachineContainerError;
+
+    use super::map_error;
+    use crate::controller::error::ControllerError;
+
+    fn map(err: ControllerError) -> StopMachineContainerError {
+        map_error(
+            err,
+            StopMachineContainerError::ResourceNotFoundException,
+            StopMachineContainerError::ConflictException,
+            StopMachineContainerError::AgentUnreachableException,
+            StopMachineContainerError::InternalServerException,
+        )
+    }
+
+    #[test]
+    fn maps_controller_errors_to_modeled_errors() {
+        assert!(matches!(
+            map(ControllerError::NotFound("gone".to_string())),
+            StopMachineContainerError::ResourceNotFoundException(e) if e.message == "gone"
+        ));
+        assert!(matches!(
+            map(ControllerError::Conflict("busy".to_string())),
+            StopMachineContainerError::ConflictException(e) if e.message == "busy"
+        ));
+        assert!(matches!(
+            map(ControllerError::AgentUnreachable("down".to_string())),
+            StopMachineContainerError::AgentUnreachableException(e) if e.message == "down"
+        ));
+        assert!(matches!(
+            map(ControllerError::Agent("boom".to_string())),
+            StopMachineContainerError::InternalServerException(e) if e.message == "boom"
+        ));
+    }
+}
@@ -1,10 +1,17 @@
 use geth_control_server::model::{
-    CpuSummary, DiskSummary, MachineStatus, MachineSummary, MemorySummary, MemoryTypeSummary,
-    NetworkInterfaceSummary, SystemSummary, Tag, TagString,
+    CoreUtilizationSummary, CpuSummary, DiskSummary, MachineContainerAlert, MachineStackSummary,
+    MachineStatus, MachineSummary, MachineUtilizationSummary, MemorySummary, MemoryTypeSummary,
+    MemoryTypeUtilizationSummary, MemoryUtilizationSummary, NetworkInterfaceSummary,
+    NetworkInterfaceTrafficSummary, NetworkInterfaceUtilizationSummary, StackHealth, SystemSummary,
+    Tag, TagString, VolumeUtilizationSummary,
 };
 
-use crate::model::machine::{
-    AddressVersion, DiskInterface, DiskType, Machine, MachineState, MachineType,
+use crate::model::{
+    machine::{
+        AddressVersion, ContainerProblem, ContainerSummary, DiskInterface, DiskType, Machine,
+        MachineState, MachineType,
+    },
+    utilization::{MachineUtilization, MemoryTypeUtilization, NetworkTraffic},
 };
 
 pub fn machine_to_summary(machine: Machine) -> MachineSummary {
@@ -154,5 +161,112 @@ pub fn machine_to_summary(machine: Machine) -> MachineSummary {
             None => None,
         },
         containers: None,
+        container_alerts: container_alerts(machine.containers.as_deref()),
+        stacks: machine.stacks.map(|stacks| {
+            stacks
+                .iter()
+                .map(|s| MachineStackSummary {
+                    project: s.project.to_string(),
+                    runtime: s.runtime.to_string(),
+                    health: StackHealth::from(s.health.as_ref()),
+                    services: s.services.iter().map(|v| v.to_string()).collect(),
+                    running: s.running as i32,
+                    total: s.total as i32,
+                })
+                .collect()
+        }),
+    }
+}
+
+fn container_alerts(containers: Option<&[ContainerSummary]>) -> Option<Vec<MachineContainerAlert>> {
+    let alerts: Vec<MachineContainerAlert> = containers?
+        .iter()
+        .filter_map(|c| {
+            let problem = match c.problem()? {
+                ContainerProblem::Unhealthy => {
+                    geth_control_server::model::ContainerProblem::Unhealthy
+                }
+                ContainerProblem::CrashLooping => {
+                    geth_control_server::model::ContainerProblem::CrashLooping
+                }
+                ContainerProblem::OomKilled => {
+                    geth_control_server::model::ContainerProblem::OomKilled
+                }
+            };
+            Some(MachineContainerAlert {
+                container_id: c.container_id.to_string(),
+                name: c.name.to_string(),
+                problem,
+                restart_count: c.restart_count as i64,
+                exit_code: c.exit_code,
+            })
+        })
+        .collect();
+
+    match alerts.len() {
+        0 => None,
+        _ => Some(alerts),
+    }
+}
+
+fn memory_type_to_summary(memory: MemoryTypeUtilization) -> MemoryTypeUtilizationSummary {
+    MemoryTypeUtilizationSummary {
+        total: memory.total as i64,
+        available: Some(memory.available as i64),
+        used: Some(memory.used as i64),
+    }
+}
+
+fn traffic_to_summary(traffic: NetworkTraffic) -> NetworkInterfaceTrafficSummary {
+    NetworkInterfaceTrafficSummary {
+        transmitted: Some(traffic.transmitted as i64),
+        recieved: Some(traffic.recieved as i64),
+    }
+}
+
+pub fn utilization_to_summary(utilization: MachineUtilization) -> MachineUtilizationSummary {
+    MachineUtilizationSummary {
+        id: utilization.machine_id.to_string(),
+        boot_time: utilization.boot_time.map(|t| t as i64),
+        up_time: utilization.up_time.map(|t| t as i64),
+        memory: utilization.memory.map(|m| MemoryUtilizationSummary {
+            memory: m.memory.map(memory_type_to_summary),
+            swap: m.swap.map(memory_type_to_summary),
+        }),
+        cpu: Some(
+            utilization
+                .cpu
+                .iter()
+                .map(|c| CoreUtilizationSummary {
+                    name: c.name.to_string(),
+                    usage: Some(c.usage),
+                    frequency: Some(c.frequency),
+                })
+                .collect(),
+        ),
+        volumes: Some(
+            utilization
+                .volumes
+                .iter()
+                .map(|v| VolumeUtilizationSummary {
+                    name: v.name.to_string(),
+                    mount_point: Some(v.mount_point.to_string()),
+                    total_space: v.total_space as i64,
+                    available_space: Some(v.available_space as i64),
+                    used_space: Some(v.used_space as i64),
+                })
+                .collect(),
+        ),
+        network_interfaces: Some(
+            utilization
+                .network_interfaces
+                .iter()
+                .map(|n| NetworkInterfaceUtilizationSummary {
+                    name: n.name.to_string(),
+                    bytes_traffic: n.bytes.clone().map(traffic_to_summary),
+                    packet_traffic: n.packets.clone().map(traffic_to_summary),
+                })
+                .collect(),
+        ),
     }
 }
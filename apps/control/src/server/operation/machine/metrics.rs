@@ -0,0 +1,64 @@
+use std::sync::Arc;
+
+use aws_smithy_http_server::Extension;
+use geth_control_server::{
+    error, input::GetMachineMetricHistoryInput, model::MetricPoint,
+    output::GetMachineMetricHistoryOutput,
+};
+
+use crate::{controller::error::ControllerError, server::http::State};
+
+pub async fn get_machine_metric_history(
+    input: GetMachineMetricHistoryInput,
+    state: Extension<Arc<State>>,
+) -> Result<GetMachineMetricHistoryOutput, error::GetMachineMetricHistoryError> {
+    let target = state
+        .controller
+        .lock()
+        .await
+        .agent_target(input.identifier())
+        .await;
+
+    let result = match target {
+        Ok(t) => {
+            t.metric_history(input.metric.as_str(), input.start, input.end, input.step)
+                .await
+        }
+        Err(e) => Err(e),
+    };
+
+    match result {
+        Ok(points) => Ok(GetMachineMetricHistoryOutput {
+            metric: input.metric,
+            points: points
+                .iter()
+                .map(|p| MetricPoint {
+                    timestamp: p.timestamp.timestamp(),
+                    value: p.value,
+                })
+                .collect(),
+        }),
+        Err(ControllerError::NotFound(message)) => Err(
+            error::GetMachineMetricHistoryError::ResourceNotFoundException(
+                error::ResourceNotFoundException { message },
+            ),
+        ),
+        Err(ControllerError::InvalidInput(message)) => {
+            Err(error::GetMachineMetricHistoryError::InvalidInputException(
+                error::InvalidInputException { message },
+            ))
+        }
+        Err(ControllerError::AgentUnreachable(message)) => Err(
+            error::GetMachineMetricHistoryError::AgentUnreachableException(
+                error::AgentUnreachableException { message },
+            ),
+        ),
+        Err(e) => Err(
+            error::GetMachineMetricHistoryError::InternalServerException(
+                error::InternalServerException {
+                    message: e.to_string(),
+                },
+            ),
+        ),
+    }
+}
@@ -1,6 +1,9 @@
+pub mod container;
 pub mod describe;
 pub mod utilization;
+pub mod metrics;
 pub mod list;
 pub mod register;
 pub mod remove;
+pub mod sync;
 pub mod conversion;
\ No newline at end of file
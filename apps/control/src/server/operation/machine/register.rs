@@ -1,13 +1,43 @@
 use std::sync::Arc;
 
 use aws_smithy_http_server::Extension;
-use geth_control_server::{error, input::RegisterMachineInput, output::RegisterMachineOutput};
-use log::{debug, info};
+use geth_control_server::{
+    error, input::RegisterMachineInput, model::AgentScheme as InputAgentScheme,
+    output::RegisterMachineOutput,
+};
+use log::{debug, error, info};
 
-use crate::server::http::State;
+use crate::{
+    controller::error::ControllerError,
+    model::machine::{AgentEndpoint, AgentScheme, DEFAULT_AGENT_PORT},
+    server::http::State,
+};
 
 use super::conversion::machine_to_summary;
 
+fn endpoint_from_input(input: &RegisterMachineInput) -> Result<AgentEndpoint, String> {
+    let port = match input.port() {
+        Some(p) => u16::try_from(p)
+            .ok()
+            .filter(|p| *p > 0)
+            .ok_or_else(|| format!("Port {} is out of range", p))?,
+        None => DEFAULT_AGENT_PORT,
+    };
+
+    let scheme = match input.scheme() {
+        Some(InputAgentScheme::Https) => AgentScheme::Https,
+        _ => AgentScheme::Http,
+    };
+
+    Ok(AgentEndpoint::new(
+        scheme,
+        input.address(),
+        port,
+        input.api_key(),
+        input.ca_bundle(),
+    ))
+}
+
 pub async fn register_machine(
     input: RegisterMachineInput,
     state: Extension<Arc<State>>,
@@ -16,12 +46,22 @@ pub async fn register_machine(
         "Got register machine register. Registering at address {}",
         input.address()
     );
+
+    let endpoint = match endpoint_from_input(&input) {
+        Ok(e) => e,
+        Err(message) => {
+            return Err(error::RegisterMachineError::InvalidInputException(
+                error::InvalidInputException { message },
+            ))
+        }
+    };
+
     debug!("Locking controller mutex");
     let mut controller = state.controller.lock().await;
 
     debug!("Registering machine via controller");
     let machine_result = controller
-        .register_machine(input.address(), input.group_id())
+        .register_machine(endpoint, input.group_id())
         .await;
 
     match machine_result {
@@ -32,10 +72,18 @@ pub async fn register_machine(
             debug!("Summary: {:?}", summary);
             Ok(RegisterMachineOutput { summary })
         }
-        Err(e) => Err(error::RegisterMachineError::InvalidInputException(
-            error::InvalidInputException {
-                message: format!("Error making machine at address {}: {}", input.address(), e),
-            },
-        )),
+        Err(ControllerError::AgentUnreachable(message)) => {
+            Err(error::RegisterMachineError::AgentUnreachableException(
+                error::AgentUnreachableException { message },
+            ))
+        }
+        Err(e) => {
+            error!("Error registering machine at {}: {}", input.address(), e);
+            Err(error::RegisterMachineError::InvalidInputException(
+                error::InvalidInputException {
+                    message: format!("Error making machine at address {}: {}", input.address(), e),
+                },
+            ))
+        }
     }
 }
@@ -0,0 +1,58 @@
+use std::sync::Arc;
+
+use aws_smithy_http_server::Extension;
+use geth_control_server::{error, input::SyncMachineInput, output::SyncMachineOutput};
+use log::info;
+
+use crate::{controller::error::ControllerError, server::http::State};
+
+use super::conversion::machine_to_summary;
+
+pub async fn sync_machine(
+    input: SyncMachineInput,
+    state: Extension<Arc<State>>,
+) -> Result<SyncMachineOutput, error::SyncMachineError> {
+    info!("Syncing machine {}", input.identifier());
+    let target = state
+        .controller
+        .lock()
+        .await
+        .agent_target(input.identifier())
+        .await;
+
+    let result = match target {
+        Ok(t) => match t.fetch().await {
+            Ok(overview) => {
+                state
+                    .controller
+                    .lock()
+                    .await
+                    .persist_overview(&t.machine_id, &overview)
+                    .await
+            }
+            Err(e) => Err(e),
+        },
+        Err(e) => Err(e),
+    };
+
+    match result {
+        Ok(m) => Ok(SyncMachineOutput {
+            summary: machine_to_summary(m),
+        }),
+        Err(ControllerError::NotFound(message)) => {
+            Err(error::SyncMachineError::ResourceNotFoundException(
+                error::ResourceNotFoundException { message },
+            ))
+        }
+        Err(ControllerError::AgentUnreachable(message)) => {
+            Err(error::SyncMachineError::AgentUnreachableException(
+                error::AgentUnreachableException { message },
+            ))
+        }
+        Err(e) => Err(error::SyncMachineError::InternalServerException(
+            error::InternalServerException {
+                message: e.to_string(),
+            },
+        )),
+    }
+}
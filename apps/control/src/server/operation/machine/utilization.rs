@@ -1,10 +1,50 @@
-use std::{sync::Arc};
+use std::sync::Arc;
 
 use aws_smithy_http_server::Extension;
-use geth_control_server::{output::DescribeMachineUtilizationOutput, input::DescribeMachineUtilizationInput, error, model::{MachineSummary}};
+use geth_control_server::{
+    error, input::DescribeMachineUtilizationInput, output::DescribeMachineUtilizationOutput,
+};
 
-use crate::server::http::State;
+use crate::{controller::error::ControllerError, server::http::State};
 
-pub async fn describe_machine_utilization(input: DescribeMachineUtilizationInput, state: Extension<Arc<State>>) -> Result<DescribeMachineUtilizationOutput, error::DescribeMachineUtilizationError> {
-    Err(error::DescribeMachineUtilizationError::ResourceNotFoundException(error::ResourceNotFoundException { message: format!("Machine {} not found", input.id()) }))
-}
\ No newline at end of file
+use super::conversion::utilization_to_summary;
+
+pub async fn describe_machine_utilization(
+    input: DescribeMachineUtilizationInput,
+    state: Extension<Arc<State>>,
+) -> Result<DescribeMachineUtilizationOutput, error::DescribeMachineUtilizationError> {
+    let target = state
+        .controller
+        .lock()
+        .await
+        .agent_target(input.identifier())
+        .await;
+
+    let result = match target {
+        Ok(t) => t.utilization().await,
+        Err(e) => Err(e),
+    };
+
+    match result {
+        Ok(u) => Ok(DescribeMachineUtilizationOutput {
+            summary: utilization_to_summary(u),
+        }),
+        Err(ControllerError::NotFound(message)) => Err(
+            error::DescribeMachineUtilizationError::ResourceNotFoundException(
+                error::ResourceNotFoundException { message },
+            ),
+        ),
+        Err(ControllerError::AgentUnreachable(message)) => Err(
+            error::DescribeMachineUtilizationError::AgentUnreachableException(
+                error::AgentUnreachableException { message },
+            ),
+        ),
+        Err(e) => Err(
+            error::DescribeMachineUtilizationError::InternalServerException(
+                error::InternalServerException {
+                    message: e.to_string(),
+                },
+            ),
+        ),
+    }
+}
@@ -0,0 +1,78 @@
+use std::{error::Error, fmt};
+
+use aws_smithy_client::SdkError;
+
+#[derive(Debug)]
+pub enum AgentServiceError {
+    InvalidAddress(String),
+    InvalidCaBundle(String),
+    InvalidClientIdentity(String),
+    Unreachable(String),
+    NotFound(String),
+    Conflict(String),
+    Request(String),
+}
+
+impl fmt::Display for AgentServiceError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AgentServiceError::InvalidAddress(m) => write!(f, "invalid agent address: {}", m),
+            AgentServiceError::InvalidCaBundle(m) => write!(f, "invalid CA bundle: {}", m),
+            AgentServiceError::InvalidClientIdentity(m) => {
+                write!(f, "invalid client certificate: {}", m)
+            }
+            AgentServiceError::Unreachable(m) => write!(f, "agent unreachable: {}", m),
+            AgentServiceError::NotFound(m) => write!(f, "not found on agent: {}", m),
+            AgentServiceError::Conflict(m) => write!(f, "conflict on agent: {}", m),
+            AgentServiceError::Request(m) => write!(f, "agent request failed: {}", m),
+        }
+    }
+}
+
+impl Error for AgentServiceError {}
+
+impl<E> From<SdkError<E>> for AgentServiceError
+where
+    E: Error + 'static,
+{
+    fn from(err: SdkError<E>) -> Self {
+        match err {
+            SdkError::DispatchFailure(e) => AgentServiceError::Unreachable(format!("{:?}", e)),
+            SdkError::TimeoutError(_) => AgentServiceError::Unreachable("timed out".to_string()),
+            SdkError::ServiceError(e) => {
+                from_status(e.raw().http().status().as_u16(), e.err().to_string())
+            }
+            e => AgentServiceError::Request(e.to_string()),
+        }
+    }
+}
+
+/// Maps the HTTP status of an error the agent answered with.
+fn from_status(status: u16, message: String) -> AgentServiceError {
+    match status {
+        404 => AgentServiceError::NotFound(message),
+        409 => AgentServiceError::Conflict(message),
+        _ => AgentServiceError::Request(message),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{from_status, AgentServiceError};
+
+    #[test]
+    fn maps_agent_statuses() {
+        assert!(matches!(
+            from_status(404, "gone".to_string()),
+            AgentServiceError::NotFound(m) if m == "gone"
+        ));
+        assert!(matches!(
+            from_status(409, "busy".to_string()),
+            AgentServiceError::Conflict(m) if m == "busy"
+        ));
+        assert!(matches!(
+            from_status(500, "boom".to_string()),
+            AgentServiceError::Request(_)
+        ));
+    }
+}
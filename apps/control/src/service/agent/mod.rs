@@ -1,81 +1,310 @@
-use std::{collections::HashMap, str::FromStr};
+use std::{collections::HashMap, fs, str::FromStr};
 
 use aws_smithy_client::{
     erase::{DynConnector, DynMiddleware},
-    SdkError,
+    hyper_ext::Adapter,
 };
 use aws_smithy_http::operation::Request;
 use geth_agent_client::{
     config::AuthApiKey,
     operation::{
-        get_overview::{GetOverviewError, GetOverviewOutput},
-        stream_container_logs::{StreamContainerLogsError, StreamContainerLogsOutput},
+        get_metric_history::GetMetricHistoryOutput, get_overview::GetOverviewOutput,
+        health::HealthOutput, stream_container_logs::StreamContainerLogsOutput,
     },
+    types::MetricName,
     Builder, Client, Config,
 };
 use http::{
     uri::{Authority, Scheme},
     Uri,
 };
+use hyper::client::HttpConnector;
+use hyper_rustls::{HttpsConnector, HttpsConnectorBuilder};
+use rustls::{Certificate, ClientConfig, PrivateKey, RootCertStore};
+use rustls_pemfile::Item;
+
+use crate::model::{
+    container::ContainerAction,
+    machine::{AgentEndpoint, AgentScheme},
+};
+
+use self::error::AgentServiceError;
+
+pub mod error;
+
+pub type AgentClient = Client<DynConnector, DynMiddleware<DynConnector>>;
+
+/// A certificate chain and private key presented to agents that require
+/// client certificates.
+#[derive(Clone)]
+pub struct ClientIdentity {
+    certs: Vec<Certificate>,
+    key: PrivateKey,
+}
+
+impl ClientIdentity {
+    /// Reads a PEM encoded certificate chain and private key from disk.
+    pub fn load(cert_path: &str, key_path: &str) -> Result<ClientIdentity, AgentServiceError> {
+        let read = |path: &str| {
+            fs::read(path).map_err(|e| {
+                AgentServiceError::InvalidClientIdentity(format!("failed to read {}: {}", path, e))
+            })
+        };
+
+        let certs = rustls_pemfile::certs(&mut read(cert_path)?.as_slice())
+            .map_err(|e| AgentServiceError::InvalidClientIdentity(e.to_string()))?;
+        if certs.is_empty() {
+            return Err(AgentServiceError::InvalidClientIdentity(format!(
+                "no certificates found in {}",
+                cert_path
+            )));
+        }
+
+        let key = rustls_pemfile::read_all(&mut read(key_path)?.as_slice())
+            .map_err(|e| AgentServiceError::InvalidClientIdentity(e.to_string()))?
+            .into_iter()
+            .find_map(|item| match item {
+                Item::PKCS8Key(k) | Item::RSAKey(k) | Item::ECKey(k) => Some(k),
+                _ => None,
+            })
+            .ok_or_else(|| {
+                AgentServiceError::InvalidClientIdentity(format!(
+                    "no private key found in {}",
+                    key_path
+                ))
+            })?;
+
+        Ok(ClientIdentity {
+            certs: certs.into_iter().map(Certificate).collect(),
+            key: PrivateKey(key),
+        })
+    }
+}
 
 pub struct AgentService {
-    pub agents: HashMap<String, Client<DynConnector, DynMiddleware<DynConnector>>>,
+    pub agents: HashMap<String, AgentClient>,
+    identity: Option<ClientIdentity>,
 }
 
 impl AgentService {
-    pub fn new() -> AgentService {
+    pub fn new(identity: Option<ClientIdentity>) -> AgentService {
         let agents = HashMap::new();
 
-        AgentService { agents }
+        AgentService { agents, identity }
     }
 
     pub async fn get_server_overview(
         &mut self,
-        endpoint: &str,
-    ) -> Result<GetOverviewOutput, SdkError<GetOverviewError>> {
-        let c = self.get_agent_client(endpoint);
-        c.get_overview().send().await
+        endpoint: &AgentEndpoint,
+    ) -> Result<GetOverviewOutput, AgentServiceError> {
+        let c = self.get_agent_client(endpoint)?;
+        AgentService::overview(c).await
+    }
+
+    /// Fetches an agent's overview.
+    pub async fn overview(client: &AgentClient) -> Result<GetOverviewOutput, AgentServiceError> {
+        Ok(client.get_overview().send().await?)
+    }
+
+    /// Checks an agent's health.
+    pub async fn check_health(client: &AgentClient) -> Result<HealthOutput, AgentServiceError> {
+        Ok(client.health().send().await?)
+    }
+
+    pub async fn metric_history(
+        c: &AgentClient,
+        metric: &str,
+        start: Option<i64>,
+        end: Option<i64>,
+        step: Option<i64>,
+    ) -> Result<GetMetricHistoryOutput, AgentServiceError> {
+        Ok(c.get_metric_history()
+            .metric(MetricName::from(metric))
+            .set_start(start)
+            .set_end(end)
+            .set_step(step)
+            .send()
+            .await?)
     }
 
     pub async fn get_container_logs(
         &mut self,
-        endpoint: &str,
+        endpoint: &AgentEndpoint,
         container_id: &str,
-    ) -> Result<StreamContainerLogsOutput, SdkError<StreamContainerLogsError>> {
-        let c = self.get_agent_client(endpoint);
-        c.stream_container_logs()
+    ) -> Result<StreamContainerLogsOutput, AgentServiceError> {
+        let c = self.get_agent_client(endpoint)?;
+        Ok(c.stream_container_logs()
             .id(container_id)
             .follow(true)
             .send()
-            .await
+            .await?)
     }
 
-    fn cache_agent_client(
-        &mut self,
-        id: String,
-        client: Client<DynConnector, DynMiddleware<DynConnector>>,
-    ) {
+    pub async fn container_action(
+        c: &AgentClient,
+        container_id: &str,
+        action: &ContainerAction,
+    ) -> Result<(), AgentServiceError> {
+        match action {
+            ContainerAction::Start => {
+                c.start_container().id(container_id).send().await?;
+            }
+            ContainerAction::Stop { timeout } => {
+                c.stop_container()
+                    .id(container_id)
+                    .set_timeout(*timeout)
+                    .send()
+                    .await?;
+            }
+            ContainerAction::Restart { timeout } => {
+                c.restart_container()
+                    .id(container_id)
+                    .set_timeout(*timeout)
+                    .send()
+                    .await?;
+            }
+            ContainerAction::Pause => {
+                c.pause_container().id(container_id).send().await?;
+            }
+            ContainerAction::Unpause => {
+                c.unpause_container().id(container_id).send().await?;
+            }
+            ContainerAction::Remove { force, volumes } => {
+                c.remove_container()
+                    .id(container_id)
+                    .force(*force)
+                    .volumes(*volumes)
+                    .send()
+                    .await?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Drops any cached client for the endpoint so the next call picks up
+    /// changed credentials or certificates.
+    pub fn evict(&mut self, endpoint: &AgentEndpoint) {
+        self.agents.remove(&endpoint.to_string());
+    }
+
+    /// Returns a handle to the cached client for the endpoint. Handles are
+    /// cheap to clone and share the underlying connection pool, so one can be
+    /// taken under the controller lock and used with the associated request
+    /// functions after the lock is released.
+    pub fn client(&mut self, endpoint: &AgentEndpoint) -> Result<AgentClient, AgentServiceError> {
+        Ok(self.get_agent_client(endpoint)?.clone())
+    }
+
+    fn cache_agent_client(&mut self, id: String, client: AgentClient) {
         self.agents.insert(id, client);
     }
 
     fn get_agent_client(
         &mut self,
-        endpoint: &str,
-    ) -> &Client<DynConnector, DynMiddleware<DynConnector>> {
-        self.agents
-            .entry(endpoint.to_string())
-            .or_insert(make_agent_client(endpoint))
+        endpoint: &AgentEndpoint,
+    ) -> Result<&AgentClient, AgentServiceError> {
+        let key = endpoint.to_string();
+
+        if !self.agents.contains_key(&key) {
+            let client = make_agent_client(endpoint, self.identity.as_ref())?;
+            self.cache_agent_client(key.clone(), client);
+        }
+
+        Ok(&self.agents[&key])
     }
 }
 
-fn make_agent_client(endpoint: &str) -> Client<DynConnector, DynMiddleware<DynConnector>> {
-    let authority = Authority::from_str(endpoint).expect("failed to parse authority");
-    let raw_client = Builder::new()
-        .rustls_connector(Default::default())
-        .middleware_fn(rewrite_base_url(Scheme::HTTP, authority))
-        .build_dyn();
-    let config = Config::builder().api_key(AuthApiKey::from("toes")).build();
-    Client::with_config(raw_client, config)
+fn make_agent_client(
+    endpoint: &AgentEndpoint,
+    identity: Option<&ClientIdentity>,
+) -> Result<AgentClient, AgentServiceError> {
+    let authority = Authority::from_str(&endpoint.authority()).map_err(|e| {
+        AgentServiceError::InvalidAddress(format!("{}: {}", endpoint.authority(), e))
+    })?;
+
+    let scheme = match endpoint.scheme {
+        AgentScheme::Http => Scheme::HTTP,
+        AgentScheme::Https => Scheme::HTTPS,
+    };
+
+    let raw_client = match (&endpoint.ca_bundle, identity) {
+        (None, None) => Builder::new()
+            .rustls_connector(Default::default())
+            .middleware_fn(rewrite_base_url(scheme, authority))
+            .build_dyn(),
+        (ca_bundle, identity) => Builder::new()
+            .connector(
+                Adapter::builder().build(make_tls_connector(ca_bundle.as_deref(), identity)?),
+            )
+            .middleware_fn(rewrite_base_url(scheme, authority))
+            .build_dyn(),
+    };
+
+    let config = Config::builder()
+        .api_key(AuthApiKey::from(endpoint.api_key.as_ref()))
+        .build();
+    Ok(Client::with_config(raw_client, config))
+}
+
+/// Builds an HTTPS connector that trusts the certificates in the given PEM
+/// bundle, or the system roots without one, and presents the client identity
+/// to agents that ask for it.
+fn make_tls_connector(
+    ca_bundle: Option<&str>,
+    identity: Option<&ClientIdentity>,
+) -> Result<HttpsConnector<HttpConnector>, AgentServiceError> {
+    let roots = match ca_bundle {
+        Some(ca_bundle) => bundle_roots(ca_bundle)?,
+        None => system_roots()?,
+    };
+
+    let builder = ClientConfig::builder()
+        .with_safe_defaults()
+        .with_root_certificates(roots);
+
+    let tls = match identity {
+        Some(identity) => builder
+            .with_single_cert(identity.certs.clone(), identity.key.clone())
+            .map_err(|e| AgentServiceError::InvalidClientIdentity(e.to_string()))?,
+        None => builder.with_no_client_auth(),
+    };
+
+    Ok(HttpsConnectorBuilder::new()
+        .with_tls_config(tls)
+        .https_or_http()
+        .enable_http1()
+        .enable_http2()
+        .build())
+}
+
+fn bundle_roots(ca_bundle: &str) -> Result<RootCertStore, AgentServiceError> {
+    let certs = rustls_pemfile::certs(&mut ca_bundle.as_bytes())
+        .map_err(|e| AgentServiceError::InvalidCaBundle(e.to_string()))?;
+
+    if certs.is_empty() {
+        return Err(AgentServiceError::InvalidCaBundle(
+            "no certificates found".to_string(),
+        ));
+    }
+
+    let mut roots = RootCertStore::empty();
+    for cert in certs {
+        roots
+            .add(&Certificate(cert))
+            .map_err(|e| AgentServiceError::InvalidCaBundle(e.to_string()))?;
+    }
+    Ok(roots)
+}
+
+fn system_roots() -> Result<RootCertStore, AgentServiceError> {
+    let certs = rustls_native_certs::load_native_certs().map_err(|e| {
+        AgentServiceError::InvalidCaBundle(format!("failed to load system roots: {}", e))
+    })?;
+
+    let mut roots = RootCertStore::empty();
+    let certs: Vec<Vec<u8>> = certs.into_iter().map(|c| c.0).collect();
+    roots.add_parsable_certificates(&certs);
+    Ok(roots)
 }
 
 pub fn rewrite_base_url(
@@ -96,10 +325,14 @@ pub fn rewrite_base_url(
 mod tests {
     use super::*;
 
+    fn test_endpoint() -> AgentEndpoint {
+        AgentEndpoint::new(AgentScheme::Http, "a", 7032, "toes", None)
+    }
+
     #[tokio::test]
     async fn connectivity_test() {
-        let mut controller = AgentService::new();
-        let r = controller.get_server_overview("a").await;
+        let mut controller = AgentService::new(None);
+        let r = controller.get_server_overview(&test_endpoint()).await;
 
         match r {
             Ok(s) => {
@@ -113,10 +346,10 @@ mod tests {
 
     #[tokio::test]
     async fn follow_server_logs() {
-        let mut controller = AgentService::new();
+        let mut controller = AgentService::new(None);
         let response = controller
             .get_container_logs(
-                "a",
+                &test_endpoint(),
                 "da7f4533c5c38b95ed80c3923536b77667d17bb332a49d971424aa6888754986",
             )
             .await;
@@ -1,6 +1,8 @@
 use std::collections::HashMap;
 use chrono::{DateTime, Utc};
 
+use crate::stack::{COMPOSE_PROJECT_LABEL, COMPOSE_SERVICE_LABEL};
+
 #[derive(Clone, Debug)]
 pub enum ContainerState {
     Running,
@@ -22,10 +24,53 @@ pub enum ContainerProtocol {
     Unknown(String),
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq)]
 pub enum ContainerType {
     Docker,
     Podman,
+    Containerd,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum HealthStatus {
+    Starting,
+    Healthy,
+    Unhealthy,
+    /// The container has no health check.
+    None,
+}
+
+/// The result of a container's health check.
+#[derive(Clone, Debug)]
+pub struct ContainerHealth {
+    pub(crate) status: HealthStatus,
+    pub(crate) failing_streak: u64,
+    pub(crate) last_output: Option<String>,
+}
+
+impl ContainerHealth {
+    pub fn status(&self) -> &HealthStatus {
+        &self.status
+    }
+
+    /// Consecutive failed checks.
+    pub fn failing_streak(&self) -> u64 {
+        self.failing_streak
+    }
+
+    /// Output of the most recent check.
+    pub fn last_output(&self) -> Option<&str> {
+        self.last_output.as_deref()
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum RestartPolicy {
+    No,
+    Always,
+    UnlessStopped,
+    /// Restart on a non-zero exit, up to a maximum number of retries.
+    OnFailure(Option<u64>),
 }
 
 #[derive(Clone, Debug)]
@@ -148,6 +193,12 @@ pub struct Container {
     pub(crate) labels: Option<HashMap<String, String>>,
     pub(crate) statistics: Option<ContainerStatistics>,
     pub(crate) type_: ContainerType,
+    pub(crate) runtime: String,
+    pub(crate) health: Option<ContainerHealth>,
+    pub(crate) restart_count: u64,
+    pub(crate) exit_code: Option<i64>,
+    pub(crate) oom_killed: bool,
+    pub(crate) restart_policy: Option<RestartPolicy>,
 }
 
 impl Container {
@@ -225,10 +276,56 @@ impl Container {
         }
     }
 
+    /// Replaces the statistics with a fresher sample.
+    pub fn set_statistics (&mut self, statistics: Option<ContainerStatistics>) {
+        self.statistics = statistics;
+    }
+
     pub fn type_ (&self) -> &ContainerType {
         &self.type_
     }
 
+    /// The name of the configured runtime this container was read from.
+    pub fn runtime (&self) -> &str {
+        &self.runtime
+    }
+
+    pub fn health (&self) -> Option<&ContainerHealth> {
+        self.health.as_ref()
+    }
+
+    /// Times the runtime has restarted the container.
+    pub fn restart_count (&self) -> u64 {
+        self.restart_count
+    }
+
+    /// Exit code of the last run, once the container has exited.
+    pub fn exit_code (&self) -> Option<i64> {
+        self.exit_code
+    }
+
+    /// Whether the last run was killed for running out of memory.
+    pub fn oom_killed (&self) -> bool {
+        self.oom_killed
+    }
+
+    pub fn restart_policy (&self) -> Option<&RestartPolicy> {
+        self.restart_policy.as_ref()
+    }
+
+    pub fn unhealthy (&self) -> bool {
+        matches!(self.health.as_ref().map(|h| &h.status), Some(HealthStatus::Unhealthy))
+    }
+
+    /// The compose project the container was started from, if any.
+    pub fn compose_project (&self) -> Option<&str> {
+        self.labels.as_ref()?.get(COMPOSE_PROJECT_LABEL).map(String::as_str)
+    }
+
+    pub fn compose_service (&self) -> Option<&str> {
+        self.labels.as_ref()?.get(COMPOSE_SERVICE_LABEL).map(String::as_str)
+    }
+
 }
 
 #[derive(Clone, Debug)]
@@ -277,10 +374,17 @@ impl ContainerStatistics {
     }
 }
 
+#[derive(Clone, Debug, PartialEq)]
+pub enum ContainerLogSource {
+    StdOut,
+    StdErr,
+}
+
 #[derive(Clone, Debug)]
 pub struct ContainerLogLine {
     pub(crate) timestamp: DateTime<Utc>,
     pub(crate) line: String,
+    pub(crate) source: ContainerLogSource,
 }
 
 impl ContainerLogLine {
@@ -288,7 +392,334 @@ impl ContainerLogLine {
         &self.timestamp
     }
 
+    pub fn source (&self) -> &ContainerLogSource {
+        &self.source
+    }
+
     pub fn line (&self) -> &str {
         &self.line
     }
-}
\ No newline at end of file
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum ContainerEventAction {
+    Create,
+    Start,
+    Stop,
+    Die,
+    Destroy,
+    Pause,
+    Unpause,
+    Rename,
+    /// A health check changed state, carrying the new status.
+    HealthStatus(String),
+    Other(String),
+}
+
+impl ContainerEventAction {
+    /// Parses a runtime's action name, such as `start` or
+    /// `health_status: healthy`.
+    pub fn from_action(action: &str) -> ContainerEventAction {
+        if let Some(status) = action.strip_prefix("health_status") {
+            return ContainerEventAction::HealthStatus(status.trim_start_matches(':').trim().to_string());
+        }
+        match action {
+            "create" => ContainerEventAction::Create,
+            "start" => ContainerEventAction::Start,
+            "stop" => ContainerEventAction::Stop,
+            "die" => ContainerEventAction::Die,
+            "destroy" => ContainerEventAction::Destroy,
+            "pause" => ContainerEventAction::Pause,
+            "unpause" => ContainerEventAction::Unpause,
+            "rename" => ContainerEventAction::Rename,
+            a => ContainerEventAction::Other(a.to_string()),
+        }
+    }
+
+    /// Whether the event changes anything a container summary reports.
+    pub fn changes_state(&self) -> bool {
+        !matches!(self, ContainerEventAction::Other(_))
+    }
+
+    /// Whether the event belongs to an exec session in the container, such as
+    /// Docker's `exec_start` or containerd's `tasks/exec-started`.
+    pub fn is_exec(&self) -> bool {
+        match self {
+            ContainerEventAction::Other(a) => a.starts_with("exec_") || a.starts_with("tasks/exec-"),
+            _ => false,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct ContainerEvent {
+    pub(crate) id: String,
+    pub(crate) action: ContainerEventAction,
+    pub(crate) time: DateTime<Utc>,
+    pub(crate) runtime: String,
+    pub(crate) attributes: HashMap<String, String>,
+}
+
+impl ContainerEvent {
+    pub fn new(id: &str, action: ContainerEventAction, time: DateTime<Utc>, runtime: &str, attributes: HashMap<String, String>) -> ContainerEvent {
+        ContainerEvent {
+            id: id.to_string(),
+            action,
+            time,
+            runtime: runtime.to_string(),
+            attributes,
+        }
+    }
+
+    pub fn id (&self) -> &str {
+        &self.id
+    }
+
+    pub fn action (&self) -> &ContainerEventAction {
+        &self.action
+    }
+
+    pub fn time (&self) -> &DateTime<Utc> {
+        &self.time
+    }
+
+    /// The name of the configured runtime that emitted the event.
+    pub fn runtime (&self) -> &str {
+        &self.runtime
+    }
+
+    pub fn attributes (&self) -> &HashMap<String, String> {
+        &self.attributes
+    }
+}
+
+/// A chunk of output from an exec session. With a TTY attached both streams
+/// arrive as stdout.
+#[derive(Clone, Debug)]
+pub enum ExecOutput {
+    StdOut(Vec<u8>),
+    StdErr(Vec<u8>),
+}
+
+#[derive(Clone, Debug)]
+pub struct Image {
+    pub(crate) id: String,
+    pub(crate) tags: Vec<String>,
+    pub(crate) size: u64,
+    pub(crate) created: DateTime<Utc>,
+    /// Containers using the image, when the runtime reports it.
+    pub(crate) containers: Option<u64>,
+    pub(crate) runtime: String,
+}
+
+impl Image {
+    pub fn id (&self) -> &str {
+        &self.id
+    }
+
+    pub fn tags (&self) -> &Vec<String> {
+        &self.tags
+    }
+
+    pub fn size (&self) -> u64 {
+        self.size
+    }
+
+    pub fn created (&self) -> &DateTime<Utc> {
+        &self.created
+    }
+
+    pub fn containers (&self) -> Option<u64> {
+        self.containers
+    }
+
+    /// An image is dangling when nothing tags it.
+    pub fn dangling (&self) -> bool {
+        self.tags.is_empty()
+    }
+
+    pub fn runtime (&self) -> &str {
+        &self.runtime
+    }
+}
+
+/// A named volume managed by a runtime, as opposed to a container's
+/// [`Volume`] mount.
+#[derive(Clone, Debug)]
+pub struct RuntimeVolume {
+    pub(crate) name: String,
+    pub(crate) driver: String,
+    pub(crate) mount_point: String,
+    pub(crate) created: Option<DateTime<Utc>>,
+    pub(crate) size: Option<u64>,
+    pub(crate) labels: HashMap<String, String>,
+    pub(crate) runtime: String,
+}
+
+impl RuntimeVolume {
+    pub fn name (&self) -> &str {
+        &self.name
+    }
+
+    pub fn driver (&self) -> &str {
+        &self.driver
+    }
+
+    pub fn mount_point (&self) -> &str {
+        &self.mount_point
+    }
+
+    pub fn created (&self) -> Option<&DateTime<Utc>> {
+        self.created.as_ref()
+    }
+
+    pub fn size (&self) -> Option<u64> {
+        self.size
+    }
+
+    pub fn labels (&self) -> &HashMap<String, String> {
+        &self.labels
+    }
+
+    pub fn runtime (&self) -> &str {
+        &self.runtime
+    }
+}
+
+/// A network managed by a runtime, as opposed to a container's [`Network`]
+/// endpoint.
+#[derive(Clone, Debug)]
+pub struct RuntimeNetwork {
+    pub(crate) id: String,
+    pub(crate) name: String,
+    pub(crate) driver: String,
+    pub(crate) scope: String,
+    pub(crate) internal: bool,
+    pub(crate) subnets: Vec<String>,
+    pub(crate) runtime: String,
+}
+
+impl RuntimeNetwork {
+    pub fn id (&self) -> &str {
+        &self.id
+    }
+
+    pub fn name (&self) -> &str {
+        &self.name
+    }
+
+    pub fn driver (&self) -> &str {
+        &self.driver
+    }
+
+    pub fn scope (&self) -> &str {
+        &self.scope
+    }
+
+    pub fn internal (&self) -> bool {
+        self.internal
+    }
+
+    pub fn subnets (&self) -> &Vec<String> {
+        &self.subnets
+    }
+
+    pub fn runtime (&self) -> &str {
+        &self.runtime
+    }
+}
+
+/// One row of `docker system df`.
+#[derive(Clone, Debug, Default)]
+pub struct DiskUsageEntry {
+    pub(crate) total: u64,
+    pub(crate) active: u64,
+    pub(crate) size: u64,
+    pub(crate) reclaimable: u64,
+}
+
+impl DiskUsageEntry {
+    pub fn total (&self) -> u64 {
+        self.total
+    }
+
+    pub fn active (&self) -> u64 {
+        self.active
+    }
+
+    pub fn size (&self) -> u64 {
+        self.size
+    }
+
+    pub fn reclaimable (&self) -> u64 {
+        self.reclaimable
+    }
+
+    pub(crate) fn add(&mut self, size: u64, active: bool) {
+        self.total += 1;
+        self.size += size;
+        match active {
+            true => self.active += 1,
+            false => self.reclaimable += size,
+        }
+    }
+}
+
+/// Space used by a runtime's images, containers, volumes and build cache.
+#[derive(Clone, Debug)]
+pub struct DiskUsage {
+    pub(crate) runtime: String,
+    pub(crate) images: DiskUsageEntry,
+    pub(crate) containers: DiskUsageEntry,
+    pub(crate) volumes: DiskUsageEntry,
+    pub(crate) build_cache: DiskUsageEntry,
+}
+
+impl DiskUsage {
+    /// Estimates usage from listings, for runtimes with no usage API. An
+    /// image counts as active when a container was created from one of its
+    /// tags or its id.
+    pub(crate) fn summarize(runtime: &str, images: &[Image], containers: &[Container], volumes: &[RuntimeVolume]) -> DiskUsage {
+        let mut usage = DiskUsage {
+            runtime: runtime.to_string(),
+            images: DiskUsageEntry::default(),
+            containers: DiskUsageEntry::default(),
+            volumes: DiskUsageEntry::default(),
+            build_cache: DiskUsageEntry::default(),
+        };
+
+        for image in images {
+            let active = containers.iter().any(|c| c.image == image.id || image.tags.contains(&c.image));
+            usage.images.add(image.size, active);
+        }
+        for container in containers {
+            let active = matches!(container.state, ContainerState::Running);
+            usage.containers.add(0, active);
+        }
+        for volume in volumes {
+            let active = containers.iter().any(|c| c.volumes.iter().any(|v| v.source.as_deref() == Some(volume.mount_point.as_str())));
+            usage.volumes.add(volume.size.unwrap_or_default(), active);
+        }
+        usage
+    }
+
+    pub fn runtime (&self) -> &str {
+        &self.runtime
+    }
+
+    pub fn images (&self) -> &DiskUsageEntry {
+        &self.images
+    }
+
+    pub fn containers (&self) -> &DiskUsageEntry {
+        &self.containers
+    }
+
+    pub fn volumes (&self) -> &DiskUsageEntry {
+        &self.volumes
+    }
+
+    pub fn build_cache (&self) -> &DiskUsageEntry {
+        &self.build_cache
+    }
+}
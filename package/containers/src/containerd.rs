@@ -0,0 +1,628 @@
+use std::{collections::HashMap, process::Stdio, time::Duration};
+
+use async_stream::stream;
+use async_trait::async_trait;
+use bollard::service::ContainerInspectResponse;
+use chrono::{DateTime, NaiveDateTime, TimeZone, Utc};
+use log::{debug, warn};
+use serde::Deserialize;
+use tokio::{io::{AsyncBufReadExt, BufReader, AsyncRead}, process::Command, sync::mpsc};
+
+use crate::common::{Container, ContainerEvent, ContainerEventAction, ContainerLogLine, ContainerLogSource, ContainerStatistics, ContainerType, Image, RuntimeNetwork, RuntimeVolume};
+use crate::error::ContainerError;
+use crate::runtime::{ContainerRuntime, EventStream, LogStream, StatisticsStream};
+
+const NERDCTL: &str = "nerdctl";
+const DEFAULT_SOCKET: &str = "/run/containerd/containerd.sock";
+const DEFAULT_NAMESPACE: &str = "default";
+const STATS_INTERVAL: Duration = Duration::from_secs(1);
+
+/// A containerd runtime. containerd has no Docker-compatible API, so this
+/// drives `nerdctl`, whose inspect output uses the Docker schema.
+pub struct ContainerdRuntime {
+    name: String,
+    socket: String,
+    namespace: String,
+}
+
+impl ContainerdRuntime {
+    /// Creates the runtime once `nerdctl version` shows nerdctl is installed
+    /// and can reach containerd on the socket.
+    pub fn new(name: &str, socket: Option<&str>, namespace: Option<&str>) -> Result<ContainerdRuntime, ContainerError> {
+        let runtime = ContainerdRuntime {
+            name: name.to_string(),
+            socket: socket.unwrap_or(DEFAULT_SOCKET).trim_start_matches("unix://").to_string(),
+            namespace: namespace.unwrap_or(DEFAULT_NAMESPACE).to_string(),
+        };
+        runtime.probe()?;
+        Ok(runtime)
+    }
+
+    fn probe(&self) -> Result<(), ContainerError> {
+        let output = std::process::Command::new(NERDCTL)
+            .arg("--address").arg(&self.socket)
+            .arg("--namespace").arg(&self.namespace)
+            .arg("version")
+            .stdin(Stdio::null())
+            .output()
+            .map_err(|e| ContainerError::Runtime(format!("failed to run {}: {}", NERDCTL, e)))?;
+
+        if output.status.success() {
+            return Ok(());
+        }
+        Err(ContainerError::Runtime(String::from_utf8_lossy(&output.stderr).trim().to_string()))
+    }
+
+    fn command(&self) -> Command {
+        let mut cmd = Command::new(NERDCTL);
+        cmd.arg("--address").arg(&self.socket)
+            .arg("--namespace").arg(&self.namespace)
+            .stdin(Stdio::null())
+            .kill_on_drop(true);
+        cmd
+    }
+
+    async fn run(&self, args: &[&str]) -> Result<String, ContainerError> {
+        let output = self.command().args(args).output().await
+            .map_err(|e| ContainerError::Runtime(format!("failed to run {}: {}", NERDCTL, e)))?;
+
+        if output.status.success() {
+            return Ok(String::from_utf8_lossy(&output.stdout).into_owned());
+        }
+        Err(command_error(&String::from_utf8_lossy(&output.stderr)))
+    }
+
+    async fn inspect(&self, id: &str) -> Result<ContainerInspectResponse, ContainerError> {
+        let out = self.run(&["container", "inspect", "--mode", "dockercompat", id]).await?;
+        let mut parsed: Vec<ContainerInspectResponse> = serde_json::from_str(&out)
+            .map_err(|e| ContainerError::Runtime(format!("invalid inspect output: {}", e)))?;
+        match parsed.pop() {
+            Some(c) => Ok(c),
+            None => Err(ContainerError::NotFound(id.to_string())),
+        }
+    }
+
+    /// Runs a listing command with `--format '{{json .}}'`, parsing one row
+    /// per line.
+    async fn list_rows<T: for<'de> Deserialize<'de>>(&self, args: &[&str]) -> Result<Vec<T>, ContainerError> {
+        let mut args = args.to_vec();
+        args.extend(["--format", "{{json .}}"]);
+        let out = self.run(&args).await?;
+
+        let mut rows = Vec::new();
+        for line in out.lines().filter(|l| !l.trim().is_empty()) {
+            let row = serde_json::from_str(line)
+                .map_err(|e| ContainerError::Runtime(format!("invalid {} output: {}", args[0], e)))?;
+            rows.push(row);
+        }
+        Ok(rows)
+    }
+}
+
+fn command_error(stderr: &str) -> ContainerError {
+    let message = stderr.trim().to_string();
+    let lower = message.to_lowercase();
+    if lower.contains("no such") || lower.contains("not found") {
+        ContainerError::NotFound(message)
+    } else {
+        ContainerError::Runtime(message)
+    }
+}
+
+/// A row of `nerdctl stats --format '{{json .}}'`, which reports human
+/// readable values in the same shape as `docker stats`.
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct StatsRow {
+    #[serde(rename = "CPUPerc")]
+    cpu_perc: String,
+    mem_perc: String,
+    mem_usage: String,
+    #[serde(rename = "NetIO")]
+    net_io: String,
+    #[serde(rename = "BlockIO")]
+    block_io: String,
+}
+
+fn parse_percent(value: &str) -> f64 {
+    value.trim().trim_end_matches('%').parse().unwrap_or_default()
+}
+
+/// Parses sizes such as `1.5MiB`, `12kB` or `0B` into bytes.
+fn parse_size(value: &str) -> u64 {
+    let value = value.trim();
+    let split = value.find(|c: char| !(c.is_ascii_digit() || c == '.')).unwrap_or(value.len());
+    let (number, unit) = value.split_at(split);
+    let number: f64 = number.parse().unwrap_or_default();
+    let multiplier: f64 = match unit.trim() {
+        "kB" | "KB" => 1e3,
+        "MB" => 1e6,
+        "GB" => 1e9,
+        "TB" => 1e12,
+        "KiB" => 1024.0,
+        "MiB" => 1024.0 * 1024.0,
+        "GiB" => 1024.0 * 1024.0 * 1024.0,
+        "TiB" => 1024.0 * 1024.0 * 1024.0 * 1024.0,
+        _ => 1.0,
+    };
+    (number * multiplier) as u64
+}
+
+/// Splits a `used / total` pair into its two sizes.
+fn parse_pair(value: &str) -> (u64, u64) {
+    let mut parts = value.splitn(2, '/');
+    let first = parts.next().map(parse_size).unwrap_or_default();
+    let second = parts.next().map(parse_size).unwrap_or_default();
+    (first, second)
+}
+
+impl ContainerStatistics {
+    fn new_from_nerdctl(row: StatsRow) -> ContainerStatistics {
+        let (memory_usage, memory_limit) = parse_pair(&row.mem_usage);
+        let (network_rx_bytes, network_tx_bytes) = parse_pair(&row.net_io);
+        let (block_read_bytes, block_write_bytes) = parse_pair(&row.block_io);
+
+        ContainerStatistics {
+            cpu_utilization: parse_percent(&row.cpu_perc),
+            memory_utilization: parse_percent(&row.mem_perc),
+            memory_usage,
+            memory_limit,
+            network_rx_bytes,
+            network_tx_bytes,
+            block_read_bytes,
+            block_write_bytes,
+        }
+    }
+}
+
+async fn sample_stats(mut cmd: Command, id: &str) -> Result<ContainerStatistics, ContainerError> {
+    let output = cmd.args(["stats", "--no-stream", "--format", "{{json .}}", id]).output().await
+        .map_err(|e| ContainerError::Runtime(format!("failed to run {}: {}", NERDCTL, e)))?;
+    if !output.status.success() {
+        return Err(command_error(&String::from_utf8_lossy(&output.stderr)));
+    }
+
+    let out = String::from_utf8_lossy(&output.stdout);
+    let line = out.lines().next().unwrap_or_default();
+    let row: StatsRow = serde_json::from_str(line)
+        .map_err(|e| ContainerError::Runtime(format!("invalid stats output: {}", e)))?;
+    Ok(ContainerStatistics::new_from_nerdctl(row))
+}
+
+/// A row of `nerdctl events --format '{{json .}}'`. `event` holds the
+/// containerd event payload as a JSON string.
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct EventRow {
+    timestamp: String,
+    namespace: String,
+    topic: String,
+    event: String,
+}
+
+#[derive(Deserialize, Default)]
+struct EventPayload {
+    id: Option<String>,
+    container_id: Option<String>,
+}
+
+fn topic_action(topic: &str) -> ContainerEventAction {
+    match topic {
+        "/containers/create" => ContainerEventAction::Create,
+        "/tasks/start" => ContainerEventAction::Start,
+        "/tasks/exit" => ContainerEventAction::Die,
+        "/containers/delete" => ContainerEventAction::Destroy,
+        "/tasks/paused" => ContainerEventAction::Pause,
+        "/tasks/resumed" => ContainerEventAction::Unpause,
+        t => ContainerEventAction::Other(t.trim_start_matches('/').to_string()),
+    }
+}
+
+impl ContainerEvent {
+    fn new_from_nerdctl(line: &str, runtime: &str, namespace: &str) -> Option<ContainerEvent> {
+        let row: EventRow = serde_json::from_str(line).ok()?;
+        if row.namespace != namespace {
+            return None;
+        }
+        let payload: EventPayload = serde_json::from_str(&row.event).unwrap_or_default();
+        // Task events name the container in `container_id`, container events in `id`.
+        let id = payload.container_id.or(payload.id)?;
+
+        let mut attributes = HashMap::new();
+        attributes.insert(String::from("topic"), row.topic.clone());
+
+        Some(ContainerEvent {
+            id,
+            action: topic_action(&row.topic),
+            time: DateTime::parse_from_rfc3339(&row.timestamp).map(DateTime::<Utc>::from).unwrap_or_else(|_| Utc::now()),
+            runtime: runtime.to_string(),
+            attributes,
+        })
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct ImageRow {
+    #[serde(rename = "ID")]
+    id: String,
+    repository: String,
+    tag: String,
+    created_at: String,
+    size: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct VolumeRow {
+    name: String,
+    driver: String,
+    mountpoint: String,
+    #[serde(default)]
+    labels: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct NetworkRow {
+    #[serde(rename = "ID")]
+    id: String,
+    name: String,
+}
+
+const NONE: &str = "<none>";
+
+/// Parses nerdctl's `2023-06-13 18:30:42 +0000 UTC` timestamps.
+fn parse_created(value: &str) -> DateTime<Utc> {
+    let parts: Vec<&str> = value.split_whitespace().take(3).collect();
+    DateTime::parse_from_str(&parts.join(" "), "%Y-%m-%d %H:%M:%S %z")
+        .map(DateTime::<Utc>::from)
+        .unwrap_or_else(|_| Utc.from_utc_datetime(&NaiveDateTime::default()))
+}
+
+/// Parses `key=value,key=value` label lists.
+fn parse_labels(value: &str) -> HashMap<String, String> {
+    value.split(',')
+        .filter_map(|l| l.split_once('='))
+        .map(|(k, v)| (k.to_string(), v.to_string()))
+        .collect()
+}
+
+/// Groups image rows, which nerdctl prints once per tag, into images.
+fn images_from_rows(rows: Vec<ImageRow>, runtime: &str) -> Vec<Image> {
+    let mut images: Vec<Image> = Vec::new();
+    for row in rows {
+        let tag = match (row.repository.as_str(), row.tag.as_str()) {
+            (NONE, _) => None,
+            (repo, NONE) => Some(repo.to_string()),
+            (repo, tag) => Some(format!("{}:{}", repo, tag)),
+        };
+
+        match images.iter_mut().find(|i| i.id == row.id) {
+            Some(image) => image.tags.extend(tag),
+            None => images.push(Image {
+                id: row.id,
+                tags: tag.into_iter().collect(),
+                size: parse_size(&row.size),
+                created: parse_created(&row.created_at),
+                containers: None,
+                runtime: runtime.to_string(),
+            }),
+        }
+    }
+    images
+}
+
+fn forward_lines<R>(reader: R, source: ContainerLogSource, tx: mpsc::UnboundedSender<ContainerLogLine>)
+where
+    R: AsyncRead + Unpin + Send + 'static,
+{
+    tokio::spawn(async move {
+        let mut lines = BufReader::new(reader).lines();
+        while let Ok(Some(line)) = lines.next_line().await {
+            let (timestamp, line) = ContainerLogLine::parse_docker_line(line);
+            let log = ContainerLogLine { timestamp, line, source: source.clone() };
+            if tx.send(log).is_err() {
+                break;
+            }
+        }
+    });
+}
+
+fn to_rfc3339(secs: i64) -> String {
+    Utc.timestamp_opt(secs, 0).single().unwrap_or_default().to_rfc3339()
+}
+
+#[async_trait]
+impl ContainerRuntime for ContainerdRuntime {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn kind(&self) -> ContainerType {
+        ContainerType::Containerd
+    }
+
+    async fn list_containers(&self) -> Vec<Container> {
+        let mut result: Vec<Container> = Vec::new();
+        let ids = match self.run(&["ps", "--all", "--quiet", "--no-trunc"]).await {
+            Ok(out) => out,
+            Err(e) => {
+                warn!("Error listing containers in {}: {}", self.name, e);
+                return result;
+            }
+        };
+
+        for id in ids.lines().map(str::trim).filter(|l| !l.is_empty()) {
+            match self.get_container(id).await {
+                Some(c) => result.push(c),
+                None => debug!("Found container with no data"),
+            }
+        }
+        debug!("Found {} containers in {}", result.len(), self.name);
+        result
+    }
+
+    async fn get_container(&self, id: &str) -> Option<Container> {
+        match self.inspect(id).await {
+            Ok(container) => {
+                let running = container.state.as_ref().and_then(|s| s.running).unwrap_or(false);
+                let stats = match running {
+                    true => self.get_container_stats(id).await,
+                    false => None,
+                };
+                Some(Container::new_from_docker(container, stats, ContainerType::Containerd, &self.name))
+            },
+            Err(e) => {
+                debug!("Error getting container {} from {}: {}", id, self.name, e);
+                None
+            }
+        }
+    }
+
+    async fn get_container_stats(&self, id: &str) -> Option<ContainerStatistics> {
+        match sample_stats(self.command(), id).await {
+            Ok(s) => Some(s),
+            Err(e) => {
+                warn!("Error getting stats for container {}: {}", id, e);
+                None
+            }
+        }
+    }
+
+    async fn container_exists(&self, id: &str) -> bool {
+        self.inspect(id).await.is_ok()
+    }
+
+    async fn is_running(&self, id: &str) -> bool {
+        match self.inspect(id).await {
+            Ok(c) => c.state.and_then(|s| s.running).unwrap_or(false),
+            Err(_) => false,
+        }
+    }
+
+    async fn stream_container_logs(&self, id: &str, follow: bool, lines: Option<i32>, since: Option<i64>, until: Option<i64>) -> LogStream {
+        let mut cmd = self.command();
+        cmd.args(["logs", "--timestamps"]);
+        if follow {
+            cmd.arg("--follow");
+        }
+        if let Some(lines) = lines {
+            cmd.arg("--tail").arg(lines.to_string());
+        }
+        if let Some(since) = since {
+            cmd.arg("--since").arg(to_rfc3339(since));
+        }
+        if let Some(until) = until {
+            cmd.arg("--until").arg(to_rfc3339(until));
+        }
+        cmd.arg(id).stdout(Stdio::piped()).stderr(Stdio::piped());
+
+        Box::pin(stream! {
+            let mut child = match cmd.spawn() {
+                Ok(c) => c,
+                Err(e) => {
+                    yield Err(ContainerError::Runtime(format!("failed to run {}: {}", NERDCTL, e)));
+                    return;
+                }
+            };
+
+            let (tx, mut rx) = mpsc::unbounded_channel();
+            if let Some(stdout) = child.stdout.take() {
+                forward_lines(stdout, ContainerLogSource::StdOut, tx.clone());
+            }
+            if let Some(stderr) = child.stderr.take() {
+                forward_lines(stderr, ContainerLogSource::StdErr, tx);
+            }
+
+            while let Some(line) = rx.recv().await {
+                yield Ok(line);
+            }
+
+            // Keep the child alive until both pipes have closed.
+            let _ = child.wait().await;
+        })
+    }
+
+    async fn stream_container_stats(&self, id: &str) -> StatisticsStream {
+        let socket = self.socket.clone();
+        let namespace = self.namespace.clone();
+        let id = id.to_string();
+
+        Box::pin(stream! {
+            let runtime = ContainerdRuntime { name: String::new(), socket, namespace };
+            let mut interval = tokio::time::interval(STATS_INTERVAL);
+            loop {
+                interval.tick().await;
+                match sample_stats(runtime.command(), &id).await {
+                    Ok(s) => yield Ok(s),
+                    Err(e) => {
+                        yield Err(e);
+                        break;
+                    }
+                }
+            }
+        })
+    }
+
+    async fn stream_events(&self) -> EventStream {
+        let mut cmd = self.command();
+        cmd.args(["events", "--format", "{{json .}}"]).stdout(Stdio::piped()).stderr(Stdio::null());
+        let name = self.name.clone();
+        let namespace = self.namespace.clone();
+
+        Box::pin(stream! {
+            let mut child = match cmd.spawn() {
+                Ok(c) => c,
+                Err(e) => {
+                    yield Err(ContainerError::Runtime(format!("failed to run {}: {}", NERDCTL, e)));
+                    return;
+                }
+            };
+            let stdout = match child.stdout.take() {
+                Some(s) => s,
+                None => return,
+            };
+
+            let mut lines = BufReader::new(stdout).lines();
+            loop {
+                match lines.next_line().await {
+                    Ok(Some(line)) => {
+                        if let Some(event) = ContainerEvent::new_from_nerdctl(&line, &name, &namespace) {
+                            yield Ok(event);
+                        }
+                    },
+                    Ok(None) => break,
+                    Err(e) => {
+                        yield Err(ContainerError::Runtime(e.to_string()));
+                        break;
+                    }
+                }
+            }
+            let _ = child.wait().await;
+        })
+    }
+
+    async fn list_images(&self) -> Result<Vec<Image>, ContainerError> {
+        let rows: Vec<ImageRow> = self.list_rows(&["images"]).await?;
+        Ok(images_from_rows(rows, &self.name))
+    }
+
+    async fn list_volumes(&self) -> Result<Vec<RuntimeVolume>, ContainerError> {
+        let rows: Vec<VolumeRow> = self.list_rows(&["volume", "ls"]).await?;
+        Ok(rows.into_iter().map(|r| RuntimeVolume {
+            labels: parse_labels(&r.labels),
+            name: r.name,
+            driver: r.driver,
+            mount_point: r.mountpoint,
+            created: None,
+            size: None,
+            runtime: self.name.clone(),
+        }).collect())
+    }
+
+    async fn list_networks(&self) -> Result<Vec<RuntimeNetwork>, ContainerError> {
+        let rows: Vec<NetworkRow> = self.list_rows(&["network", "ls"]).await?;
+        Ok(rows.into_iter().map(|r| RuntimeNetwork {
+            id: r.id,
+            name: r.name,
+            driver: String::new(),
+            scope: String::new(),
+            internal: false,
+            subnets: Vec::new(),
+            runtime: self.name.clone(),
+        }).collect())
+    }
+
+    async fn start_container(&self, id: &str) -> Result<(), ContainerError> {
+        self.run(&["start", id]).await.map(|_| ())
+    }
+
+    async fn stop_container(&self, id: &str, timeout: Option<i64>) -> Result<(), ContainerError> {
+        match timeout {
+            Some(t) => self.run(&["stop", "--time", &t.to_string(), id]).await.map(|_| ()),
+            None => self.run(&["stop", id]).await.map(|_| ()),
+        }
+    }
+
+    async fn restart_container(&self, id: &str, timeout: Option<i64>) -> Result<(), ContainerError> {
+        match timeout {
+            Some(t) => self.run(&["restart", "--time", &t.to_string(), id]).await.map(|_| ()),
+            None => self.run(&["restart", id]).await.map(|_| ()),
+        }
+    }
+
+    async fn pause_container(&self, id: &str) -> Result<(), ContainerError> {
+        self.run(&["pause", id]).await.map(|_| ())
+    }
+
+    async fn unpause_container(&self, id: &str) -> Result<(), ContainerError> {
+        self.run(&["unpause", id]).await.map(|_| ())
+    }
+
+    async fn remove_container(&self, id: &str, force: bool, volumes: bool) -> Result<(), ContainerError> {
+        let mut args = vec!["rm"];
+        if force {
+            args.push("--force");
+        }
+        if volumes {
+            args.push("--volumes");
+        }
+        args.push(id);
+        self.run(&args).await.map(|_| ())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_sizes() {
+        assert_eq!(parse_size("0B"), 0);
+        assert_eq!(parse_size("12kB"), 12_000);
+        assert_eq!(parse_size("1.5MiB"), 1_572_864);
+        assert_eq!(parse_pair("10MiB / 2GiB"), (10_485_760, 2_147_483_648));
+    }
+
+    #[test]
+    fn parses_stats_row() {
+        let row: StatsRow = serde_json::from_str(r#"{"BlockIO":"4kB / 0B","CPUPerc":"1.25%","Container":"abc","ID":"abc","MemPerc":"0.50%","MemUsage":"1MiB / 200MiB","Name":"web","NetIO":"2kB / 1kB","PIDs":"3"}"#).unwrap();
+        let stats = ContainerStatistics::new_from_nerdctl(row);
+        assert_eq!(stats.cpu_utilization(), 1.25);
+        assert_eq!(stats.memory_usage(), 1_048_576);
+        assert_eq!(stats.network_tx_bytes(), 1_000);
+        assert_eq!(stats.block_read_bytes(), 4_000);
+    }
+
+    #[test]
+    fn groups_image_tags() {
+        let rows = vec![
+            r#"{"CreatedAt":"2023-06-13 18:30:42 +0000 UTC","ID":"abc","Repository":"nginx","Tag":"latest","Size":"10.0 MiB"}"#,
+            r#"{"CreatedAt":"2023-06-13 18:30:42 +0000 UTC","ID":"abc","Repository":"nginx","Tag":"1.25","Size":"10.0 MiB"}"#,
+            r#"{"CreatedAt":"2023-06-12 09:00:00 +0000 UTC","ID":"def","Repository":"<none>","Tag":"<none>","Size":"1 KiB"}"#,
+        ];
+        let rows: Vec<ImageRow> = rows.iter().map(|r| serde_json::from_str(r).unwrap()).collect();
+        let images = images_from_rows(rows, "containerd");
+
+        assert_eq!(images.len(), 2);
+        assert_eq!(images[0].tags(), &vec!["nginx:latest".to_string(), "nginx:1.25".to_string()]);
+        assert_eq!(images[0].size(), 10_485_760);
+        assert_eq!(images[0].created().timestamp(), 1_686_681_042);
+        assert!(images[1].dangling());
+    }
+
+    #[test]
+    fn parses_events() {
+        let start = r#"{"Timestamp":"2023-07-01T10:00:00.5Z","ID":"","Namespace":"default","Topic":"/tasks/start","Status":"unknown","Event":"{\"container_id\":\"abc\",\"pid\":42}"}"#;
+        let event = ContainerEvent::new_from_nerdctl(start, "containerd", "default").unwrap();
+        assert_eq!(event.id(), "abc");
+        assert_eq!(event.action(), &ContainerEventAction::Start);
+
+        let delete = r#"{"Timestamp":"2023-07-01T10:00:01Z","ID":"","Namespace":"default","Topic":"/containers/delete","Status":"unknown","Event":"{\"id\":\"abc\"}"}"#;
+        let event = ContainerEvent::new_from_nerdctl(delete, "containerd", "default").unwrap();
+        assert_eq!(event.action(), &ContainerEventAction::Destroy);
+
+        assert!(ContainerEvent::new_from_nerdctl(start, "containerd", "k8s.io").is_none());
+    }
+}
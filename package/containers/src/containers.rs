@@ -1,128 +1,219 @@
-use bollard::{Docker, container::{StatsOptions, LogOutput, LogsOptions, Stats}, errors::Error};
-use futures_util::{StreamExt, Stream};
+use std::sync::Arc;
+
 use log::{warn, debug};
 
-use crate::common::{Container, ContainerStatistics, ContainerLogLine};
+use crate::{common::{Container, ContainerStatistics, DiskUsage, Image, RuntimeNetwork, RuntimeVolume}, containerd::ContainerdRuntime, docker::DockerRuntime, error::ContainerError, runtime::{ContainerRuntime, EventStream, ExecControl, ExecSession, LogStream, RuntimeConfig, RuntimeKind, StatisticsStream}};
 
+/// Reads containers from every configured runtime. Operations on a single
+/// container are routed to the first runtime that knows its id.
+#[derive(Clone)]
 pub struct Containers {
-    docker: Docker,
+    runtimes: Vec<Arc<dyn ContainerRuntime>>,
+}
+
+fn connect(config: &RuntimeConfig) -> Result<Arc<dyn ContainerRuntime>, ContainerError> {
+    let name = config.name();
+    let runtime: Arc<dyn ContainerRuntime> = match config.kind() {
+        RuntimeKind::Docker => Arc::new(DockerRuntime::docker(&name, config.socket())?),
+        RuntimeKind::Podman => Arc::new(DockerRuntime::podman(&name, config.socket())?),
+        RuntimeKind::Containerd => Arc::new(ContainerdRuntime::new(&name, config.socket(), config.namespace())?),
+    };
+    Ok(runtime)
 }
 
 impl Containers {
-    pub fn new() -> Option<Containers> {
-        let c = Docker::connect_with_local_defaults();
-        match c {
-            Ok(c) => Some(Containers {
-                docker: c,
-            }),
-            Err(e) => {
-                warn!("Error connecting to docker: {}", e);
-                None
+    /// Connects to each configured runtime, skipping any that fail. Returns
+    /// `None` when no runtime could be reached.
+    pub fn new(configs: &[RuntimeConfig]) -> Option<Containers> {
+        let mut runtimes = Vec::new();
+        for config in configs {
+            match connect(config) {
+                Ok(r) => {
+                    debug!("Connected to container runtime {}", r.name());
+                    runtimes.push(r);
+                },
+                Err(e) => warn!("Error connecting to container runtime {}: {}", config.name(), e),
+            }
+        }
+
+        if runtimes.is_empty() {
+            return None;
+        }
+        Some(Containers { runtimes })
+    }
+
+    pub fn runtimes(&self) -> Vec<&str> {
+        self.runtimes.iter().map(|r| r.name()).collect()
+    }
+
+    fn runtime_named(&self, name: &str) -> Option<&Arc<dyn ContainerRuntime>> {
+        self.runtimes.iter().find(|r| r.name() == name)
+    }
+
+    async fn runtime_for(&self, id: &str) -> Result<&Arc<dyn ContainerRuntime>, ContainerError> {
+        for runtime in self.runtimes.iter() {
+            if runtime.container_exists(id).await {
+                return Ok(runtime);
             }
         }
+        Err(ContainerError::NotFound(id.to_string()))
     }
 
     pub async fn list_containers(&self) -> Vec<Container> {
         let mut result: Vec<Container> = Vec::new();
-        let containers = self.docker.list_containers::<String>(None).await;
-
-        match containers {
-            Ok(containers) => {
-                debug!("Found {} containers", containers.len());
-                for c in containers {
-                    match c.id {
-                        Some(ref id) => {
-                            let c = self.get_container(id).await;
-                            match c {
-                                Some(c) => result.push(c),
-                                None => {
-                                    debug!("Found container with no data");
-                                },
-                            }
-                        },
-                        None => { 
-                            debug!("Found container with no id");
-                        },
-                    }
-                }
-            },
-            Err(e) => warn!("Error listing containers: {}", e),
+        for runtime in self.runtimes.iter() {
+            result.extend(runtime.list_containers().await);
         }
         result
     }
 
     pub async fn get_container(&self, id: &str) -> Option<Container> {
-        let result = self.docker.inspect_container(id, None).await;
-        match result {
-            Ok(container) => {
-                let stats = self.get_container_stats(id).await;
-                let container = Container::new_from_docker(container, stats);
-                Some(container)
-            },
-            Err(e) => {
-                warn!("Error getting container {}: {}", id, e);
-                None
+        for runtime in self.runtimes.iter() {
+            if let Some(c) = runtime.get_container(id).await {
+                return Some(c);
             }
         }
+        warn!("Container {} not found in any runtime", id);
+        None
+    }
+
+    /// Samples a running container's statistics from the runtime it was read
+    /// from.
+    pub async fn get_container_stats(&self, runtime: &str, id: &str) -> Option<ContainerStatistics> {
+        match self.runtime_named(runtime) {
+            Some(r) => r.get_container_stats(id).await,
+            None => None,
+        }
     }
 
-    pub async fn stream_container_logs(&self, id: &str, follow: bool, lines: Option<i32>) -> impl Stream<Item = Result<ContainerLogLine, Error>> {
-        let mut options = Some(LogsOptions::<String>{
-            follow,
-            stdout: true,
-            timestamps: true,
-            ..Default::default()
-        });
-        if let Some(lines) = lines {
-            options.as_mut().unwrap().tail = lines.to_string();
+    pub async fn container_exists(&self, id: &str) -> bool {
+        self.runtime_for(id).await.is_ok()
+    }
+
+    pub async fn is_running(&self, id: &str) -> bool {
+        match self.runtime_for(id).await {
+            Ok(r) => r.is_running(id).await,
+            Err(_) => false,
+        }
+    }
+
+    /// Streams a container's stdout and stderr. `since` and `until` are unix
+    /// timestamps in seconds.
+    pub async fn stream_container_logs(&self, id: &str, follow: bool, lines: Option<i32>, since: Option<i64>, until: Option<i64>) -> Result<LogStream, ContainerError> {
+        let runtime = self.runtime_for(id).await?;
+        Ok(runtime.stream_container_logs(id, follow, lines, since, until).await)
+    }
+
+    pub async fn stream_container_stats(&self, id: &str) -> Result<StatisticsStream, ContainerError> {
+        let runtime = self.runtime_for(id).await?;
+        Ok(runtime.stream_container_stats(id).await)
+    }
+
+    /// Reads a container from a single runtime.
+    pub async fn get_runtime_container(&self, runtime: &str, id: &str) -> Option<Container> {
+        match self.runtime_named(runtime) {
+            Some(r) => r.get_container(id).await,
+            None => None,
+        }
+    }
+
+    /// Lists the containers of a single runtime.
+    pub async fn list_runtime_containers(&self, runtime: &str) -> Vec<Container> {
+        match self.runtime_named(runtime) {
+            Some(r) => r.list_containers().await,
+            None => Vec::new(),
         }
+    }
+
+    /// Streams container events from a single runtime as they arrive. Each
+    /// runtime has its own stream so one failing doesn't interrupt the others.
+    pub async fn stream_runtime_events(&self, runtime: &str) -> Result<EventStream, ContainerError> {
+        match self.runtime_named(runtime) {
+            Some(r) => Ok(r.stream_events().await),
+            None => Err(ContainerError::Runtime(format!("unknown runtime {}", runtime))),
+        }
+    }
 
-        let logs = self.docker.logs(id, options);
-        let l = logs.map(|l| {
-            l.map(|op| {
-                let log = ContainerLogLine::new_from_docker(op);
-                log
-            })
-        });
-        l
-    }
-
-    pub async fn stream_container_stats(&self, id: &str) -> impl Stream<Item = Result<ContainerStatistics, Error>> {
-        let options = Some(StatsOptions {
-            stream: true,
-            ..Default::default()
-        });
-        let stats = self.docker.stats(id, options);
-        let statistics = stats.map(|s| {
-            s.map(|op| {
-                let stats = ContainerStatistics::new_from_docker(op);
-                stats
-            })
-        });
-        statistics
-    }
-
-    pub async fn get_container_stats(&self, id: &str) -> Option<ContainerStatistics> {
-        let options = Some(StatsOptions {
-            stream: false,
-            one_shot: true,
-            ..Default::default()
-        });
-        let mut result = self.docker.stats(id, options);
-        match result.next().await {
-            Some(Ok(stats)) => {
-                let stats = ContainerStatistics::new_from_docker(stats);
-                Some(stats)
-            },
-            Some(Err(e)) => {
-                warn!("Error getting stats for container {}: {}", id, e);
-                None
-            },
-            None => {
-                warn!("No stats for container {}", id);
-                None
+    pub async fn list_images(&self) -> Vec<Image> {
+        let mut result = Vec::new();
+        for runtime in self.runtimes.iter() {
+            match runtime.list_images().await {
+                Ok(images) => result.extend(images),
+                Err(e) => warn!("Error listing images in {}: {}", runtime.name(), e),
             }
         }
+        result
+    }
+
+    pub async fn list_volumes(&self) -> Vec<RuntimeVolume> {
+        let mut result = Vec::new();
+        for runtime in self.runtimes.iter() {
+            match runtime.list_volumes().await {
+                Ok(volumes) => result.extend(volumes),
+                Err(e) => warn!("Error listing volumes in {}: {}", runtime.name(), e),
+            }
+        }
+        result
+    }
+
+    pub async fn list_networks(&self) -> Vec<RuntimeNetwork> {
+        let mut result = Vec::new();
+        for runtime in self.runtimes.iter() {
+            match runtime.list_networks().await {
+                Ok(networks) => result.extend(networks),
+                Err(e) => warn!("Error listing networks in {}: {}", runtime.name(), e),
+            }
+        }
+        result
+    }
+
+    /// Disk usage for each runtime that could report it.
+    pub async fn disk_usage(&self) -> Vec<DiskUsage> {
+        let mut result = Vec::new();
+        for runtime in self.runtimes.iter() {
+            match runtime.disk_usage().await {
+                Ok(usage) => result.push(usage),
+                Err(e) => warn!("Error reading disk usage of {}: {}", runtime.name(), e),
+            }
+        }
+        result
     }
 
-}
\ No newline at end of file
+    /// Runs `command` in a container, attached to its stdin and output.
+    pub async fn exec(&self, id: &str, command: &[String], tty: bool) -> Result<ExecSession, ContainerError> {
+        let runtime = self.runtime_for(id).await?;
+        let (exec_id, output, input) = runtime.exec(id, command, tty).await?;
+        Ok(ExecSession {
+            output,
+            input,
+            control: ExecControl::new(exec_id, runtime.clone()),
+        })
+    }
+
+    pub async fn start_container(&self, id: &str) -> Result<(), ContainerError> {
+        self.runtime_for(id).await?.start_container(id).await
+    }
+
+    /// Stops a container, killing it if it hasn't exited after `timeout`
+    /// seconds.
+    pub async fn stop_container(&self, id: &str, timeout: Option<i64>) -> Result<(), ContainerError> {
+        self.runtime_for(id).await?.stop_container(id, timeout).await
+    }
+
+    pub async fn restart_container(&self, id: &str, timeout: Option<i64>) -> Result<(), ContainerError> {
+        self.runtime_for(id).await?.restart_container(id, timeout).await
+    }
+
+    pub async fn pause_container(&self, id: &str) -> Result<(), ContainerError> {
+        self.runtime_for(id).await?.pause_container(id).await
+    }
+
+    pub async fn unpause_container(&self, id: &str) -> Result<(), ContainerError> {
+        self.runtime_for(id).await?.unpause_container(id).await
+    }
+
+    pub async fn remove_container(&self, id: &str, force: bool, volumes: bool) -> Result<(), ContainerError> {
+        self.runtime_for(id).await?.remove_container(id, force, volumes).await
+    }
+
+}
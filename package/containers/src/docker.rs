@@ -1,9 +1,15 @@
-use std::collections::{HashMap, hash_map::RandomState};
+use std::{collections::{HashMap, hash_map::RandomState}, env, path::Path};
 
-use bollard::{Docker, service::{ContainerSummary, PortTypeEnum, ContainerInspectResponse, ContainerStateStatusEnum, ContainerConfig, NetworkSettings, MountPoint, EndpointSettings, PortBinding}, secret::ContainerState as BollardContainerState, container::{Stats, CPUStats, MemoryStats, BlkioStats, NetworkStats, LogOutput}};
-use chrono::{DateTime, Utc};
+use async_stream::stream;
+use async_trait::async_trait;
+use bollard::{Docker, API_DEFAULT_VERSION, errors::Error, service::{ContainerSummary, PortTypeEnum, ContainerInspectResponse, ContainerStateStatusEnum, ContainerConfig, NetworkSettings, MountPoint, EndpointSettings, PortBinding}, secret::ContainerState as BollardContainerState, system::EventsOptions, models::EventMessage, container::{ListContainersOptions, Stats, CPUStats, MemoryStats, BlkioStats, NetworkStats, LogOutput, LogsOptions, StatsOptions, StartContainerOptions, StopContainerOptions, RestartContainerOptions, RemoveContainerOptions}, exec::{CreateExecOptions, StartExecOptions, StartExecResults, ResizeExecOptions}, image::ListImagesOptions, volume::ListVolumesOptions, network::ListNetworksOptions, service::{ImageSummary, SystemDataUsageResponse, Health, HealthStatusEnum, RestartPolicyNameEnum}};
+use chrono::{DateTime, TimeZone, Utc};
+use futures_util::StreamExt;
+use log::{debug, warn};
 
-use crate::common::{Port, ContainerProtocol, Volume, Network, ContainerStatistics, ContainerLogLine};
+use crate::common::{Port, ContainerProtocol, Volume, Network, ContainerStatistics, ContainerLogLine, ContainerLogSource, ContainerType, ContainerEvent, ContainerEventAction, ExecOutput, Image, RuntimeVolume, RuntimeNetwork, DiskUsage, DiskUsageEntry, ContainerHealth, HealthStatus, RestartPolicy};
+use crate::error::{ContainerError, ignore_not_modified};
+use crate::runtime::{ContainerRuntime, LogStream, StatisticsStream, EventStream, ExecOutputStream, ExecInput};
 
 use super::common::{Container, ContainerState};
 
@@ -136,12 +142,20 @@ fn networks_from_network_map(networks_settings: Option<NetworkSettings>) -> Vec<
 }
 
 impl Container {
-    pub fn new_from_docker(container: ContainerInspectResponse, statistics: Option<ContainerStatistics>) -> Container {
+    pub fn new_from_docker(container: ContainerInspectResponse, statistics: Option<ContainerStatistics>, type_: ContainerType, runtime: &str) -> Container {
         let id = handle_optional_string(container.id);
         let name = handle_optional_string(container.name);
         let image = read_container_image(container.config.clone()); // <- fix later
         let state = docker_state_to_state(container.state.clone()); // <- fix later
         let created = timestamp_to_datetime(container.created);
+        let health = container.state.as_ref().and_then(|s| s.health.clone()).and_then(health_from_docker);
+        let exit_code = match container.state.as_ref() {
+            Some(s) if matches!(s.status, Some(ContainerStateStatusEnum::EXITED | ContainerStateStatusEnum::DEAD | ContainerStateStatusEnum::RESTARTING)) => s.exit_code,
+            _ => None,
+        };
+        let oom_killed = container.state.as_ref().and_then(|s| s.oom_killed).unwrap_or(false);
+        let restart_count = container.restart_count.unwrap_or_default().max(0) as u64;
+        let restart_policy = container.host_config.and_then(|h| h.restart_policy).and_then(restart_policy_from_docker);
         let started = handle_optional_date(container.state.clone().unwrap_or_default().started_at); // <- fix later
         let finished = handle_optional_date(container.state.unwrap_or_default().finished_at);
         let ports = ports_from_network(container.network_settings.clone()); // <- fix later
@@ -187,11 +201,47 @@ impl Container {
             labels,
             environment,
             statistics,
-            type_: super::common::ContainerType::Docker,
+            type_,
+            runtime: runtime.to_string(),
+            health,
+            restart_count,
+            exit_code,
+            oom_killed,
+            restart_policy,
         }
     }
 }
 
+fn health_from_docker(health: Health) -> Option<ContainerHealth> {
+    let status = match health.status? {
+        HealthStatusEnum::STARTING => HealthStatus::Starting,
+        HealthStatusEnum::HEALTHY => HealthStatus::Healthy,
+        HealthStatusEnum::UNHEALTHY => HealthStatus::Unhealthy,
+        HealthStatusEnum::NONE | HealthStatusEnum::EMPTY => return None,
+    };
+    let last_output = health.log
+        .and_then(|l| l.last().and_then(|r| r.output.clone()))
+        .map(|o| o.trim().to_string());
+
+    Some(ContainerHealth {
+        status,
+        failing_streak: health.failing_streak.unwrap_or_default().max(0) as u64,
+        last_output,
+    })
+}
+
+fn restart_policy_from_docker(policy: bollard::service::RestartPolicy) -> Option<RestartPolicy> {
+    match policy.name? {
+        RestartPolicyNameEnum::ALWAYS => Some(RestartPolicy::Always),
+        RestartPolicyNameEnum::UNLESS_STOPPED => Some(RestartPolicy::UnlessStopped),
+        RestartPolicyNameEnum::ON_FAILURE => {
+            let retries = policy.maximum_retry_count.filter(|r| *r > 0).map(|r| r as u64);
+            Some(RestartPolicy::OnFailure(retries))
+        },
+        RestartPolicyNameEnum::NO | RestartPolicyNameEnum::EMPTY => Some(RestartPolicy::No),
+    }
+}
+
 impl ContainerStatistics {
     pub fn new_from_docker(stats: Stats) -> ContainerStatistics {
         let cpu_utilization = get_cpu_utilization(stats.cpu_stats);
@@ -264,20 +314,21 @@ fn get_network_stats(network_stats: Option<HashMap<String, NetworkStats, RandomS
 
 impl ContainerLogLine {
     pub fn new_from_docker(line: LogOutput) -> ContainerLogLine {
-        let l = match line {
-            LogOutput::StdOut { message } => message,
-            LogOutput::StdErr { message } => message,
-            LogOutput::Console { message } => message,
-            LogOutput::StdIn { message } => message,
+        // A container with a tty attached has its streams merged into one.
+        let (l, source) = match line {
+            LogOutput::StdOut { message } => (message, ContainerLogSource::StdOut),
+            LogOutput::StdErr { message } => (message, ContainerLogSource::StdErr),
+            LogOutput::Console { message } => (message, ContainerLogSource::StdOut),
+            LogOutput::StdIn { message } => (message, ContainerLogSource::StdOut),
         };
 
-        let line_string = String::from_utf8(l.to_vec()).unwrap_or_default();
+        let line_string = String::from_utf8_lossy(&l).into_owned();
         
         let (time, line) = ContainerLogLine::parse_docker_line(line_string);
-        ContainerLogLine { timestamp: time, line: line }
+        ContainerLogLine { timestamp: time, line: line.trim_end_matches(&['\r', '\n'][..]).to_owned(), source }
     }
 
-    fn parse_docker_line(line: String) -> (DateTime<Utc>, String) {
+    pub(crate) fn parse_docker_line(line: String) -> (DateTime<Utc>, String) {
         let mut parts = line.splitn(2, " ");
         let timestamp = parts.next().unwrap_or_default();
         let line = parts.next().unwrap_or("");
@@ -333,4 +384,433 @@ impl Port {
             container: container_port,
         }
     }
-}
\ No newline at end of file
+}
+
+const UNTAGGED: &str = "<none>:<none>";
+
+fn parse_optional_date(date: Option<&String>) -> Option<DateTime<Utc>> {
+    date.and_then(|d| DateTime::parse_from_rfc3339(d).ok()).map(DateTime::<Utc>::from)
+}
+
+impl Image {
+    pub fn new_from_docker(image: ImageSummary, runtime: &str) -> Image {
+        let tags = image.repo_tags.into_iter().filter(|t| t != UNTAGGED).collect();
+        // The list endpoint reports -1 unless usage was computed.
+        let containers = match image.containers {
+            c if c >= 0 => Some(c as u64),
+            _ => None,
+        };
+
+        Image {
+            id: image.id,
+            tags,
+            size: image.size.max(0) as u64,
+            created: Utc.timestamp_opt(image.created, 0).single().unwrap_or_default(),
+            containers,
+            runtime: runtime.to_string(),
+        }
+    }
+}
+
+impl RuntimeVolume {
+    pub fn new_from_docker(volume: bollard::service::Volume, runtime: &str) -> RuntimeVolume {
+        let size = volume.usage_data.as_ref().and_then(|u| match u.size {
+            s if s >= 0 => Some(s as u64),
+            _ => None,
+        });
+
+        RuntimeVolume {
+            created: parse_optional_date(volume.created_at.as_ref()),
+            name: volume.name,
+            driver: volume.driver,
+            mount_point: volume.mountpoint,
+            size,
+            labels: volume.labels,
+            runtime: runtime.to_string(),
+        }
+    }
+}
+
+impl RuntimeNetwork {
+    pub fn new_from_docker(network: bollard::service::Network, runtime: &str) -> RuntimeNetwork {
+        let subnets = network.ipam
+            .and_then(|i| i.config)
+            .unwrap_or_default()
+            .into_iter()
+            .filter_map(|c| c.subnet)
+            .collect();
+
+        RuntimeNetwork {
+            id: network.id.unwrap_or_default(),
+            name: network.name.unwrap_or_default(),
+            driver: network.driver.unwrap_or_default(),
+            scope: network.scope.unwrap_or_default(),
+            internal: network.internal.unwrap_or(false),
+            subnets,
+            runtime: runtime.to_string(),
+        }
+    }
+}
+
+impl DiskUsage {
+    pub fn new_from_docker(usage: SystemDataUsageResponse, runtime: &str) -> DiskUsage {
+        let mut images = DiskUsageEntry::default();
+        for image in usage.images.unwrap_or_default() {
+            // Layers shared with other images aren't freed by removing this one.
+            let unique = (image.size - image.shared_size.max(0)).max(0) as u64;
+            images.total += 1;
+            match image.containers > 0 {
+                true => images.active += 1,
+                false => images.reclaimable += unique,
+            }
+        }
+        images.size = usage.layers_size.unwrap_or_default().max(0) as u64;
+
+        let mut containers = DiskUsageEntry::default();
+        for container in usage.containers.unwrap_or_default() {
+            let running = container.state.as_deref() == Some("running");
+            containers.add(container.size_rw.unwrap_or_default().max(0) as u64, running);
+        }
+
+        let mut volumes = DiskUsageEntry::default();
+        for volume in usage.volumes.unwrap_or_default() {
+            let (size, refs) = match volume.usage_data {
+                Some(u) => (u.size.max(0) as u64, u.ref_count),
+                None => (0, 0),
+            };
+            volumes.add(size, refs > 0);
+        }
+
+        let mut build_cache = DiskUsageEntry::default();
+        for cache in usage.build_cache.unwrap_or_default() {
+            let size = cache.size.unwrap_or_default().max(0) as u64;
+            let in_use = cache.in_use.unwrap_or(false) || cache.shared.unwrap_or(false);
+            build_cache.add(size, in_use);
+        }
+
+        DiskUsage {
+            runtime: runtime.to_string(),
+            images,
+            containers,
+            volumes,
+            build_cache,
+        }
+    }
+}
+
+impl ExecOutput {
+    pub fn new_from_docker(output: LogOutput) -> ExecOutput {
+        match output {
+            LogOutput::StdErr { message } => ExecOutput::StdErr(message.to_vec()),
+            LogOutput::StdOut { message } => ExecOutput::StdOut(message.to_vec()),
+            LogOutput::Console { message } => ExecOutput::StdOut(message.to_vec()),
+            LogOutput::StdIn { message } => ExecOutput::StdOut(message.to_vec()),
+        }
+    }
+}
+
+impl ContainerEvent {
+    pub fn new_from_docker(message: EventMessage, runtime: &str) -> Option<ContainerEvent> {
+        let actor = message.actor?;
+        let id = actor.id?;
+        let action = ContainerEventAction::from_action(&message.action.unwrap_or_default());
+        let time = match message.time_nano {
+            Some(n) => Utc.timestamp_nanos(n),
+            None => Utc.timestamp_opt(message.time.unwrap_or_default(), 0).single().unwrap_or_default(),
+        };
+
+        Some(ContainerEvent {
+            id,
+            action,
+            time,
+            runtime: runtime.to_string(),
+            attributes: actor.attributes.unwrap_or_default(),
+        })
+    }
+}
+
+const CONNECT_TIMEOUT: u64 = 120;
+const PODMAN_ROOTFUL_SOCKET: &str = "/run/podman/podman.sock";
+
+fn connect(socket: &str) -> Result<Docker, Error> {
+    if socket.starts_with("tcp://") || socket.starts_with("http://") {
+        return Docker::connect_with_http(socket, CONNECT_TIMEOUT, API_DEFAULT_VERSION);
+    }
+    let path = socket.trim_start_matches("unix://");
+    Docker::connect_with_unix(path, CONNECT_TIMEOUT, API_DEFAULT_VERSION)
+}
+
+/// Podman listens on a per-user socket when rootless and a system socket
+/// otherwise.
+fn default_podman_socket() -> String {
+    if let Ok(dir) = env::var("XDG_RUNTIME_DIR") {
+        let rootless = format!("{}/podman/podman.sock", dir);
+        if Path::new(&rootless).exists() {
+            return rootless;
+        }
+    }
+    String::from(PODMAN_ROOTFUL_SOCKET)
+}
+
+/// A runtime that speaks the Docker Engine API. Podman is served through the
+/// Docker-compatible endpoints on its socket.
+pub struct DockerRuntime {
+    docker: Docker,
+    name: String,
+    kind: ContainerType,
+}
+
+impl DockerRuntime {
+    pub fn docker(name: &str, socket: Option<&str>) -> Result<DockerRuntime, ContainerError> {
+        let docker = match socket {
+            Some(s) => connect(s)?,
+            None => Docker::connect_with_local_defaults()?,
+        };
+        Ok(DockerRuntime { docker, name: name.to_string(), kind: ContainerType::Docker })
+    }
+
+    pub fn podman(name: &str, socket: Option<&str>) -> Result<DockerRuntime, ContainerError> {
+        let socket = match socket {
+            Some(s) => s.to_string(),
+            None => default_podman_socket(),
+        };
+        let docker = connect(&socket)?;
+        Ok(DockerRuntime { docker, name: name.to_string(), kind: ContainerType::Podman })
+    }
+}
+
+#[async_trait]
+impl ContainerRuntime for DockerRuntime {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn kind(&self) -> ContainerType {
+        self.kind.clone()
+    }
+
+    async fn list_containers(&self) -> Vec<Container> {
+        let mut result: Vec<Container> = Vec::new();
+        // Stopped containers are listed too, since events keep them in view.
+        let options = ListContainersOptions::<String> {
+            all: true,
+            ..Default::default()
+        };
+        let containers = self.docker.list_containers(Some(options)).await;
+
+        match containers {
+            Ok(containers) => {
+                debug!("Found {} containers in {}", containers.len(), self.name);
+                for c in containers {
+                    match c.id {
+                        Some(ref id) => {
+                            match self.get_container(id).await {
+                                Some(c) => result.push(c),
+                                None => debug!("Found container with no data"),
+                            }
+                        },
+                        None => debug!("Found container with no id"),
+                    }
+                }
+            },
+            Err(e) => warn!("Error listing containers in {}: {}", self.name, e),
+        }
+        result
+    }
+
+    async fn get_container(&self, id: &str) -> Option<Container> {
+        match self.docker.inspect_container(id, None).await {
+            Ok(container) => {
+                let running = container.state.as_ref().and_then(|s| s.running).unwrap_or(false);
+                let stats = match running {
+                    true => self.get_container_stats(id).await,
+                    false => None,
+                };
+                Some(Container::new_from_docker(container, stats, self.kind.clone(), &self.name))
+            },
+            Err(e) => {
+                debug!("Error getting container {} from {}: {}", id, self.name, e);
+                None
+            }
+        }
+    }
+
+    async fn get_container_stats(&self, id: &str) -> Option<ContainerStatistics> {
+        let options = Some(StatsOptions {
+            stream: false,
+            one_shot: true,
+        });
+        let mut result = self.docker.stats(id, options);
+        match result.next().await {
+            Some(Ok(stats)) => Some(ContainerStatistics::new_from_docker(stats)),
+            Some(Err(e)) => {
+                warn!("Error getting stats for container {}: {}", id, e);
+                None
+            },
+            None => {
+                warn!("No stats for container {}", id);
+                None
+            }
+        }
+    }
+
+    async fn container_exists(&self, id: &str) -> bool {
+        self.docker.inspect_container(id, None).await.is_ok()
+    }
+
+    async fn is_running(&self, id: &str) -> bool {
+        match self.docker.inspect_container(id, None).await {
+            Ok(c) => c.state.and_then(|s| s.running).unwrap_or(false),
+            Err(_) => false,
+        }
+    }
+
+    async fn stream_container_logs(&self, id: &str, follow: bool, lines: Option<i32>, since: Option<i64>, until: Option<i64>) -> LogStream {
+        let options = LogsOptions::<String>{
+            follow,
+            stdout: true,
+            stderr: true,
+            timestamps: true,
+            since: since.unwrap_or_default(),
+            until: until.unwrap_or_default(),
+            tail: lines.map(|l| l.to_string()).unwrap_or_else(|| String::from("all")),
+        };
+
+        let docker = self.docker.clone();
+        let id = id.to_string();
+        Box::pin(stream! {
+            let mut logs = docker.logs(&id, Some(options));
+            while let Some(line) = logs.next().await {
+                yield line.map(ContainerLogLine::new_from_docker).map_err(ContainerError::from);
+            }
+        })
+    }
+
+    async fn stream_container_stats(&self, id: &str) -> StatisticsStream {
+        let options = StatsOptions {
+            stream: true,
+            one_shot: false,
+        };
+
+        let docker = self.docker.clone();
+        let id = id.to_string();
+        Box::pin(stream! {
+            let mut stats = docker.stats(&id, Some(options));
+            while let Some(sample) = stats.next().await {
+                yield sample.map(ContainerStatistics::new_from_docker).map_err(ContainerError::from);
+            }
+        })
+    }
+
+    async fn stream_events(&self) -> EventStream {
+        let mut filters = HashMap::new();
+        filters.insert("type", vec!["container"]);
+        let options = EventsOptions {
+            filters,
+            ..Default::default()
+        };
+
+        let docker = self.docker.clone();
+        let name = self.name.clone();
+        Box::pin(stream! {
+            let mut events = docker.events(Some(options));
+            while let Some(message) = events.next().await {
+                match message {
+                    Ok(m) => {
+                        if let Some(event) = ContainerEvent::new_from_docker(m, &name) {
+                            yield Ok(event);
+                        }
+                    },
+                    Err(e) => yield Err(ContainerError::from(e)),
+                }
+            }
+        })
+    }
+
+    async fn start_container(&self, id: &str) -> Result<(), ContainerError> {
+        ignore_not_modified(self.docker.start_container(id, None::<StartContainerOptions<String>>).await)
+    }
+
+    async fn stop_container(&self, id: &str, timeout: Option<i64>) -> Result<(), ContainerError> {
+        let options = timeout.map(|t| StopContainerOptions { t });
+        ignore_not_modified(self.docker.stop_container(id, options).await)
+    }
+
+    async fn restart_container(&self, id: &str, timeout: Option<i64>) -> Result<(), ContainerError> {
+        let options = timeout.map(|t| RestartContainerOptions { t: t as isize });
+        ignore_not_modified(self.docker.restart_container(id, options).await)
+    }
+
+    async fn pause_container(&self, id: &str) -> Result<(), ContainerError> {
+        ignore_not_modified(self.docker.pause_container(id).await)
+    }
+
+    async fn unpause_container(&self, id: &str) -> Result<(), ContainerError> {
+        ignore_not_modified(self.docker.unpause_container(id).await)
+    }
+
+    async fn remove_container(&self, id: &str, force: bool, volumes: bool) -> Result<(), ContainerError> {
+        let options = RemoveContainerOptions {
+            force,
+            v: volumes,
+            ..Default::default()
+        };
+        ignore_not_modified(self.docker.remove_container(id, Some(options)).await)
+    }
+
+    async fn exec(&self, id: &str, command: &[String], tty: bool) -> Result<(String, ExecOutputStream, ExecInput), ContainerError> {
+        let options = CreateExecOptions {
+            cmd: Some(command.to_vec()),
+            attach_stdin: Some(true),
+            attach_stdout: Some(true),
+            attach_stderr: Some(true),
+            tty: Some(tty),
+            ..Default::default()
+        };
+        let exec_id = self.docker.create_exec(id, options).await?.id;
+
+        let start = StartExecOptions {
+            detach: false,
+            ..Default::default()
+        };
+        match self.docker.start_exec(&exec_id, Some(start)).await? {
+            StartExecResults::Attached { output, input } => {
+                let output: ExecOutputStream = Box::pin(output.map(|o| o.map(ExecOutput::new_from_docker).map_err(ContainerError::from)));
+                Ok((exec_id, output, input))
+            },
+            StartExecResults::Detached => Err(ContainerError::Runtime(format!("exec {} started detached", exec_id))),
+        }
+    }
+
+    async fn resize_exec(&self, exec_id: &str, width: u16, height: u16) -> Result<(), ContainerError> {
+        Ok(self.docker.resize_exec(exec_id, ResizeExecOptions { width, height }).await?)
+    }
+
+    async fn exec_exit_code(&self, exec_id: &str) -> Result<Option<i64>, ContainerError> {
+        let inspect = self.docker.inspect_exec(exec_id).await?;
+        match inspect.running {
+            Some(true) => Ok(None),
+            _ => Ok(inspect.exit_code),
+        }
+    }
+
+    async fn list_images(&self) -> Result<Vec<Image>, ContainerError> {
+        let images = self.docker.list_images(None::<ListImagesOptions<String>>).await?;
+        Ok(images.into_iter().map(|i| Image::new_from_docker(i, &self.name)).collect())
+    }
+
+    async fn list_volumes(&self) -> Result<Vec<RuntimeVolume>, ContainerError> {
+        let volumes = self.docker.list_volumes(None::<ListVolumesOptions<String>>).await?;
+        Ok(volumes.volumes.unwrap_or_default().into_iter().map(|v| RuntimeVolume::new_from_docker(v, &self.name)).collect())
+    }
+
+    async fn list_networks(&self) -> Result<Vec<RuntimeNetwork>, ContainerError> {
+        let networks = self.docker.list_networks(None::<ListNetworksOptions<String>>).await?;
+        Ok(networks.into_iter().map(|n| RuntimeNetwork::new_from_docker(n, &self.name)).collect())
+    }
+
+    async fn disk_usage(&self) -> Result<DiskUsage, ContainerError> {
+        let usage = self.docker.df().await?;
+        Ok(DiskUsage::new_from_docker(usage, &self.name))
+    }
+}
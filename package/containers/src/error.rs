@@ -0,0 +1,67 @@
+use std::{error::Error, fmt};
+
+use bollard::errors::Error as BollardError;
+
+#[derive(Debug)]
+pub enum ContainerError {
+    NotFound(String),
+    Conflict(String),
+    Runtime(String),
+}
+
+impl fmt::Display for ContainerError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ContainerError::NotFound(m) => write!(f, "container not found: {}", m),
+            ContainerError::Conflict(m) => write!(f, "container conflict: {}", m),
+            ContainerError::Runtime(m) => write!(f, "container runtime error: {}", m),
+        }
+    }
+}
+
+impl Error for ContainerError {}
+
+impl From<BollardError> for ContainerError {
+    fn from(err: BollardError) -> Self {
+        match err {
+            BollardError::DockerResponseServerError { status_code: 404, message } => ContainerError::NotFound(message),
+            BollardError::DockerResponseServerError { status_code: 409, message } => ContainerError::Conflict(message),
+            e => ContainerError::Runtime(e.to_string()),
+        }
+    }
+}
+
+/// Docker answers 304 when a container is already in the requested state,
+/// which callers treat as success.
+pub(crate) fn ignore_not_modified(result: Result<(), BollardError>) -> Result<(), ContainerError> {
+    match result {
+        Ok(_) => Ok(()),
+        Err(BollardError::DockerResponseServerError { status_code: 304, .. }) => Ok(()),
+        Err(e) => Err(e.into()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bollard::errors::Error as BollardError;
+
+    use super::{ignore_not_modified, ContainerError};
+
+    fn docker_error(status_code: u16) -> BollardError {
+        BollardError::DockerResponseServerError { status_code, message: format!("status {}", status_code) }
+    }
+
+    #[test]
+    fn maps_docker_statuses() {
+        assert!(matches!(ContainerError::from(docker_error(404)), ContainerError::NotFound(m) if m == "status 404"));
+        assert!(matches!(ContainerError::from(docker_error(409)), ContainerError::Conflict(m) if m == "status 409"));
+        assert!(matches!(ContainerError::from(docker_error(500)), ContainerError::Runtime(_)));
+    }
+
+    #[test]
+    fn not_modified_is_success() {
+        assert!(ignore_not_modified(Ok(())).is_ok());
+        assert!(ignore_not_modified(Err(docker_error(304))).is_ok());
+        assert!(matches!(ignore_not_modified(Err(docker_error(404))), Err(ContainerError::NotFound(_))));
+    }
+}
@@ -4,17 +4,47 @@ use futures_util::stream::StreamExt;
 mod common;
 mod docker;
 mod containers;
+mod containerd;
+mod error;
+mod runtime;
+mod stack;
 
 pub use containers::Containers;
+pub use error::ContainerError;
+pub use runtime::ContainerRuntime;
+pub use runtime::RuntimeConfig;
+pub use runtime::RuntimeKind;
+pub use runtime::LogStream;
+pub use runtime::StatisticsStream;
+pub use runtime::EventStream;
+pub use runtime::ExecControl;
+pub use runtime::ExecSession;
+pub use runtime::ExecOutputStream;
+pub use runtime::ExecInput;
 pub use common::Container;
 pub use common::ContainerStatistics;
 pub use common::ContainerState;
 pub use common::Port;
 pub use common::Volume;
 pub use common::ContainerLogLine;
+pub use common::ContainerLogSource;
 pub use common::ContainerProtocol;
 pub use common::Network;
 pub use common::ContainerType;
+pub use common::ContainerEvent;
+pub use common::ContainerEventAction;
+pub use common::ExecOutput;
+pub use common::Image;
+pub use common::RuntimeVolume;
+pub use common::RuntimeNetwork;
+pub use common::DiskUsage;
+pub use common::DiskUsageEntry;
+pub use common::ContainerHealth;
+pub use common::HealthStatus;
+pub use common::RestartPolicy;
+pub use stack::Stack;
+pub use stack::StackHealth;
+pub use stack::StackMember;
 
 
 #[cfg(test)]
@@ -27,7 +57,7 @@ mod tests {
 
     #[tokio::test]
     async fn containers() {
-        let co = containers::Containers::new().unwrap();
+        let co = containers::Containers::new(&[RuntimeConfig::default()]).unwrap();
         let containers = co.list_containers().await;
         for c in containers {
             println!("{:?}", c);
@@ -36,14 +66,14 @@ mod tests {
 
     #[tokio::test]
     async fn stat_stream() {
-        let co = containers::Containers::new().unwrap();
+        let co = containers::Containers::new(&[RuntimeConfig::default()]).unwrap();
 
         let cons = co.list_containers().await;
 
         match cons.first() {
             Some(c) => {
                 println!("Container: {:?}", c);
-                let mut s = co.stream_container_stats(&c.id).await;
+                let mut s = co.stream_container_stats(&c.id).await.unwrap();
                 loop {
                     match s.next().await {
                         Some(Ok(stat)) => {
@@ -68,14 +98,14 @@ mod tests {
 
     #[tokio::test]
     async fn log_stream() {
-        let co = containers::Containers::new().unwrap();
+        let co = containers::Containers::new(&[RuntimeConfig::default()]).unwrap();
 
         let cons = co.list_containers().await;
 
         match cons.first() {
             Some(c) => {
                 println!("Container: {:?}", c);
-                let mut s = co.stream_container_logs(&c.id, false, None).await;
+                let mut s = co.stream_container_logs(&c.id, false, None, None, None).await.unwrap();
 
                 loop {
                     match s.next().await {
@@ -0,0 +1,184 @@
+use std::{pin::Pin, sync::Arc};
+
+use async_trait::async_trait;
+use futures_util::stream::BoxStream;
+use serde::Deserialize;
+use tokio::io::AsyncWrite;
+
+use crate::{common::{Container, ContainerEvent, ContainerLogLine, ContainerStatistics, ContainerType, DiskUsage, ExecOutput, Image, RuntimeNetwork, RuntimeVolume}, error::ContainerError};
+
+pub type LogStream = BoxStream<'static, Result<ContainerLogLine, ContainerError>>;
+pub type StatisticsStream = BoxStream<'static, Result<ContainerStatistics, ContainerError>>;
+pub type EventStream = BoxStream<'static, Result<ContainerEvent, ContainerError>>;
+pub type ExecOutputStream = BoxStream<'static, Result<ExecOutput, ContainerError>>;
+pub type ExecInput = Pin<Box<dyn AsyncWrite + Send>>;
+
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum RuntimeKind {
+    Docker,
+    Podman,
+    Containerd,
+}
+
+/// A container runtime the agent should read from. When `socket` is unset
+/// the runtime's usual default is used.
+#[derive(Clone, Debug, Deserialize)]
+pub struct RuntimeConfig {
+    kind: RuntimeKind,
+    name: Option<String>,
+    socket: Option<String>,
+    /// containerd namespace to list containers from.
+    namespace: Option<String>,
+}
+
+impl RuntimeConfig {
+    pub fn new(kind: RuntimeKind, name: Option<&str>, socket: Option<&str>, namespace: Option<&str>) -> RuntimeConfig {
+        RuntimeConfig {
+            kind,
+            name: name.map(String::from),
+            socket: socket.map(String::from),
+            namespace: namespace.map(String::from),
+        }
+    }
+
+    pub fn kind(&self) -> &RuntimeKind {
+        &self.kind
+    }
+
+    /// The configured name, falling back to the kind of runtime.
+    pub fn name(&self) -> String {
+        match &self.name {
+            Some(n) => n.clone(),
+            None => match self.kind {
+                RuntimeKind::Docker => String::from("docker"),
+                RuntimeKind::Podman => String::from("podman"),
+                RuntimeKind::Containerd => String::from("containerd"),
+            },
+        }
+    }
+
+    pub fn socket(&self) -> Option<&str> {
+        self.socket.as_deref()
+    }
+
+    pub fn namespace(&self) -> Option<&str> {
+        self.namespace.as_deref()
+    }
+}
+
+impl Default for RuntimeConfig {
+    fn default() -> Self {
+        RuntimeConfig::new(RuntimeKind::Docker, None, None, None)
+    }
+}
+
+/// An attached exec session. `output` ends when the process exits, after
+/// which `control` can report its exit code.
+pub struct ExecSession {
+    pub output: ExecOutputStream,
+    pub input: ExecInput,
+    pub control: ExecControl,
+}
+
+/// Controls a running exec session independently of its streams.
+#[derive(Clone)]
+pub struct ExecControl {
+    exec_id: String,
+    runtime: Arc<dyn ContainerRuntime>,
+}
+
+impl ExecControl {
+    pub(crate) fn new(exec_id: String, runtime: Arc<dyn ContainerRuntime>) -> ExecControl {
+        ExecControl { exec_id, runtime }
+    }
+
+    pub fn id(&self) -> &str {
+        &self.exec_id
+    }
+
+    pub async fn resize(&self, width: u16, height: u16) -> Result<(), ContainerError> {
+        self.runtime.resize_exec(&self.exec_id, width, height).await
+    }
+
+    /// The process's exit code, or `None` while it is still running.
+    pub async fn exit_code(&self) -> Result<Option<i64>, ContainerError> {
+        self.runtime.exec_exit_code(&self.exec_id).await
+    }
+}
+
+fn exec_unsupported(runtime: &str) -> ContainerError {
+    ContainerError::Runtime(format!("exec is not supported by runtime {}", runtime))
+}
+
+/// Operations every container runtime backend supports.
+#[async_trait]
+pub trait ContainerRuntime: Send + Sync {
+    fn name(&self) -> &str;
+
+    fn kind(&self) -> ContainerType;
+
+    async fn list_containers(&self) -> Vec<Container>;
+
+    async fn get_container(&self, id: &str) -> Option<Container>;
+
+    /// Samples a running container's statistics once.
+    async fn get_container_stats(&self, id: &str) -> Option<ContainerStatistics>;
+
+    async fn container_exists(&self, id: &str) -> bool;
+
+    async fn is_running(&self, id: &str) -> bool;
+
+    /// Streams a container's stdout and stderr. `since` and `until` are unix
+    /// timestamps in seconds.
+    async fn stream_container_logs(&self, id: &str, follow: bool, lines: Option<i32>, since: Option<i64>, until: Option<i64>) -> LogStream;
+
+    async fn stream_container_stats(&self, id: &str) -> StatisticsStream;
+
+    /// Streams lifecycle events for every container in the runtime, starting
+    /// from when it is called.
+    async fn stream_events(&self) -> EventStream;
+
+    async fn start_container(&self, id: &str) -> Result<(), ContainerError>;
+
+    /// Stops a container, killing it if it hasn't exited after `timeout`
+    /// seconds.
+    async fn stop_container(&self, id: &str, timeout: Option<i64>) -> Result<(), ContainerError>;
+
+    async fn restart_container(&self, id: &str, timeout: Option<i64>) -> Result<(), ContainerError>;
+
+    async fn pause_container(&self, id: &str) -> Result<(), ContainerError>;
+
+    async fn unpause_container(&self, id: &str) -> Result<(), ContainerError>;
+
+    async fn remove_container(&self, id: &str, force: bool, volumes: bool) -> Result<(), ContainerError>;
+
+    async fn list_images(&self) -> Result<Vec<Image>, ContainerError>;
+
+    async fn list_volumes(&self) -> Result<Vec<RuntimeVolume>, ContainerError>;
+
+    async fn list_networks(&self) -> Result<Vec<RuntimeNetwork>, ContainerError>;
+
+    /// Space used by the runtime. Estimated from listings unless the runtime
+    /// can report it directly.
+    async fn disk_usage(&self) -> Result<DiskUsage, ContainerError> {
+        let images = self.list_images().await?;
+        let volumes = self.list_volumes().await?;
+        let containers = self.list_containers().await;
+        Ok(DiskUsage::summarize(self.name(), &images, &containers, &volumes))
+    }
+
+    /// Starts `command` in a running container with stdin, stdout and stderr
+    /// attached, returning the exec id with its streams.
+    async fn exec(&self, _id: &str, _command: &[String], _tty: bool) -> Result<(String, ExecOutputStream, ExecInput), ContainerError> {
+        Err(exec_unsupported(self.name()))
+    }
+
+    async fn resize_exec(&self, _exec_id: &str, _width: u16, _height: u16) -> Result<(), ContainerError> {
+        Err(exec_unsupported(self.name()))
+    }
+
+    async fn exec_exit_code(&self, _exec_id: &str) -> Result<Option<i64>, ContainerError> {
+        Err(exec_unsupported(self.name()))
+    }
+}
@@ -0,0 +1,238 @@
+use std::collections::BTreeMap;
+
+use crate::common::{Container, ContainerState, HealthStatus};
+
+/// Label compose sets to the project a container belongs to. Podman and
+/// nerdctl compose set it as well.
+pub const COMPOSE_PROJECT_LABEL: &str = "com.docker.compose.project";
+pub const COMPOSE_SERVICE_LABEL: &str = "com.docker.compose.service";
+pub const COMPOSE_WORKING_DIR_LABEL: &str = "com.docker.compose.project.working_dir";
+pub const COMPOSE_CONFIG_FILES_LABEL: &str = "com.docker.compose.project.config_files";
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum StackHealth {
+    /// Every container in the stack is running and none fail their health
+    /// check.
+    Healthy,
+    /// Some containers are stopped or failing their health check.
+    Degraded,
+    /// No container in the stack is running.
+    Stopped,
+}
+
+#[derive(Clone, Debug)]
+pub struct StackMember {
+    pub(crate) id: String,
+    pub(crate) name: String,
+    pub(crate) service: Option<String>,
+    pub(crate) state: ContainerState,
+    pub(crate) health: Option<HealthStatus>,
+}
+
+impl StackMember {
+    pub fn id (&self) -> &str {
+        &self.id
+    }
+
+    pub fn name (&self) -> &str {
+        &self.name
+    }
+
+    pub fn service (&self) -> Option<&str> {
+        self.service.as_deref()
+    }
+
+    pub fn state (&self) -> &ContainerState {
+        &self.state
+    }
+
+    pub fn health (&self) -> Option<&HealthStatus> {
+        self.health.as_ref()
+    }
+
+    pub fn running (&self) -> bool {
+        matches!(self.state, ContainerState::Running)
+    }
+
+    pub fn unhealthy (&self) -> bool {
+        self.health == Some(HealthStatus::Unhealthy)
+    }
+}
+
+/// Containers started from the same compose project in one runtime.
+#[derive(Clone, Debug)]
+pub struct Stack {
+    pub(crate) project: String,
+    pub(crate) runtime: String,
+    pub(crate) working_dir: Option<String>,
+    pub(crate) config_files: Vec<String>,
+    pub(crate) members: Vec<StackMember>,
+}
+
+impl Stack {
+    /// Groups containers carrying compose labels into stacks, ordered by
+    /// project name. Containers without a project label are left out.
+    pub fn group<'a>(containers: impl IntoIterator<Item = &'a Container>) -> Vec<Stack> {
+        let mut stacks: BTreeMap<(String, String), Stack> = BTreeMap::new();
+        for container in containers {
+            let project = match container.compose_project() {
+                Some(p) => p.to_string(),
+                None => continue,
+            };
+            let labels = container.labels();
+            let stack = stacks.entry((project.clone(), container.runtime().to_string())).or_insert_with(|| Stack {
+                project,
+                runtime: container.runtime().to_string(),
+                working_dir: None,
+                config_files: Vec::new(),
+                members: Vec::new(),
+            });
+
+            if stack.working_dir.is_none() {
+                stack.working_dir = labels.and_then(|l| l.get(COMPOSE_WORKING_DIR_LABEL)).cloned();
+            }
+            if stack.config_files.is_empty() {
+                if let Some(files) = labels.and_then(|l| l.get(COMPOSE_CONFIG_FILES_LABEL)) {
+                    stack.config_files = files.split(',').filter(|f| !f.is_empty()).map(String::from).collect();
+                }
+            }
+            stack.members.push(StackMember {
+                id: container.id().to_string(),
+                name: container.name().to_string(),
+                service: container.compose_service().map(String::from),
+                state: container.state().clone(),
+                health: container.health().map(|h| h.status().clone()),
+            });
+        }
+
+        stacks.into_values().map(|mut s| {
+            s.members.sort_by(|a, b| a.name.cmp(&b.name));
+            s
+        }).collect()
+    }
+
+    pub fn project (&self) -> &str {
+        &self.project
+    }
+
+    /// The name of the configured runtime the stack's containers run in.
+    pub fn runtime (&self) -> &str {
+        &self.runtime
+    }
+
+    pub fn working_dir (&self) -> Option<&str> {
+        self.working_dir.as_deref()
+    }
+
+    pub fn config_files (&self) -> &Vec<String> {
+        &self.config_files
+    }
+
+    pub fn members (&self) -> &Vec<StackMember> {
+        &self.members
+    }
+
+    /// Distinct service names, in order of first appearance.
+    pub fn services (&self) -> Vec<&str> {
+        let mut services: Vec<&str> = Vec::new();
+        for member in self.members.iter() {
+            if let Some(s) = member.service() {
+                if !services.contains(&s) {
+                    services.push(s);
+                }
+            }
+        }
+        services
+    }
+
+    pub fn running (&self) -> usize {
+        self.members.iter().filter(|m| m.running()).count()
+    }
+
+    pub fn health (&self) -> StackHealth {
+        let running = self.running();
+        if running == 0 {
+            StackHealth::Stopped
+        } else if running == self.members.len() && !self.members.iter().any(|m| m.unhealthy()) {
+            StackHealth::Healthy
+        } else {
+            StackHealth::Degraded
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use chrono::Utc;
+
+    use crate::common::{ContainerHealth, ContainerType};
+
+    use super::*;
+
+    fn container(id: &str, project: Option<&str>, service: &str, state: ContainerState) -> Container {
+        let mut labels = HashMap::new();
+        if let Some(p) = project {
+            labels.insert(COMPOSE_PROJECT_LABEL.to_string(), p.to_string());
+            labels.insert(COMPOSE_SERVICE_LABEL.to_string(), service.to_string());
+            labels.insert(COMPOSE_CONFIG_FILES_LABEL.to_string(), "/srv/app/compose.yml,/srv/app/compose.override.yml".to_string());
+        }
+        Container {
+            id: id.to_string(),
+            name: format!("{}-{}", service, id),
+            image: String::from("nginx"),
+            created: Utc::now(),
+            started: None,
+            finished: None,
+            environment: None,
+            command: None,
+            state,
+            ports: Vec::new(),
+            volumes: Vec::new(),
+            networks: Vec::new(),
+            labels: Some(labels),
+            statistics: None,
+            type_: ContainerType::Docker,
+            runtime: String::from("docker"),
+            health: None,
+            restart_count: 0,
+            exit_code: None,
+            oom_killed: false,
+            restart_policy: None,
+        }
+    }
+
+    #[test]
+    fn groups_by_project() {
+        let containers = vec![
+            container("a", Some("web"), "proxy", ContainerState::Running),
+            container("b", Some("web"), "app", ContainerState::Stopped),
+            container("c", Some("db"), "postgres", ContainerState::Running),
+            container("d", None, "standalone", ContainerState::Running),
+        ];
+        let stacks = Stack::group(containers.iter());
+
+        assert_eq!(stacks.len(), 2);
+        assert_eq!(stacks[0].project(), "db");
+        assert_eq!(stacks[0].health(), StackHealth::Healthy);
+        assert_eq!(stacks[1].project(), "web");
+        assert_eq!(stacks[1].services(), vec!["app", "proxy"]);
+        assert_eq!(stacks[1].config_files().len(), 2);
+        assert_eq!(stacks[1].health(), StackHealth::Degraded);
+    }
+
+    #[test]
+    fn unhealthy_member_degrades_stack() {
+        let mut unhealthy = container("b", Some("web"), "app", ContainerState::Running);
+        unhealthy.health = Some(ContainerHealth { status: HealthStatus::Unhealthy, failing_streak: 3, last_output: None });
+        let containers = vec![
+            container("a", Some("web"), "proxy", ContainerState::Running),
+            unhealthy,
+        ];
+        let stacks = Stack::group(containers.iter());
+
+        assert_eq!(stacks[0].running(), 2);
+        assert_eq!(stacks[0].health(), StackHealth::Degraded);
+    }
+}
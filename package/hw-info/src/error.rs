@@ -0,0 +1,60 @@
+use std::{error::Error, fmt, io, path::PathBuf};
+
+#[derive(Debug)]
+/// Represents a failure to read hardware information
+pub enum HwInfoError {
+    /// A sysfs or procfs path could not be read
+    Io { path: PathBuf, source: io::Error },
+    /// A sysfs or procfs attribute held a value that could not be parsed
+    Parse { path: PathBuf, value: String },
+    /// smartctl could not read a disk or produced output that could not be parsed
+    Smartctl(String),
+}
+
+impl fmt::Display for HwInfoError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            HwInfoError::Io { path, source } => write!(f, "failed to read {}: {}", path.display(), source),
+            HwInfoError::Parse { path, value } => write!(f, "unexpected value in {}: {:?}", path.display(), value),
+            HwInfoError::Smartctl(m) => write!(f, "smartctl failed: {}", m),
+        }
+    }
+}
+
+impl Error for HwInfoError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            HwInfoError::Io { source, .. } => Some(source),
+            HwInfoError::Parse { .. } | HwInfoError::Smartctl(_) => None,
+        }
+    }
+}
+
+#[derive(Debug)]
+/// Represents the devices that were read, along with the errors for any
+/// devices that were skipped because their attributes could not be read
+pub struct Loaded<T> {
+    entries: Vec<T>,
+    errors: Vec<HwInfoError>,
+}
+
+impl<T> Loaded<T> {
+    pub(crate) fn new(entries: Vec<T>, errors: Vec<HwInfoError>) -> Self {
+        Loaded { entries, errors }
+    }
+
+    /// Returns the devices that were read
+    pub fn entries(&self) -> &Vec<T> {
+        &self.entries
+    }
+
+    /// Returns the errors for the devices that were skipped
+    pub fn errors(&self) -> &Vec<HwInfoError> {
+        &self.errors
+    }
+
+    /// Splits into the devices that were read and the errors for those that were skipped
+    pub fn into_parts(self) -> (Vec<T>, Vec<HwInfoError>) {
+        (self.entries, self.errors)
+    }
+}
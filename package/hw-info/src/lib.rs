@@ -1,12 +1,41 @@
+mod error;
 mod linux;
 
+pub use error::HwInfoError;
+pub use error::Loaded;
+
 pub use linux::disk::Disk;
 pub use linux::disk::DiskKind;
 pub use linux::disk::DiskInterface;
 pub use linux::disk::load_disks;
+pub use linux::disk::load_disks_from;
+
+pub use linux::block::BlockDevice;
+pub use linux::block::BlockDeviceKind;
+pub use linux::block::BlockTopology;
+pub use linux::block::RaidState;
+pub use linux::block::RaidStatus;
+pub use linux::block::load_block_topology;
+pub use linux::block::load_block_topology_from;
+
+pub use linux::pci::PciDevice;
+pub use linux::pci::PciIds;
+pub use linux::pci::load_pci_devices;
+pub use linux::pci::load_pci_devices_from;
+
+pub use linux::smart::DiskHealth;
+pub use linux::smart::read_disk_health;
+pub use linux::smart::parse_smartctl;
 
 pub use linux::nic::NetworkInterface;
 pub use linux::nic::load_nics;
+pub use linux::nic::load_nics_from;
+
+pub use linux::virt::Virtualization;
+pub use linux::virt::Hypervisor;
+pub use linux::virt::ContainerKind;
+pub use linux::virt::detect_virtualization;
+pub use linux::virt::detect_virtualization_from;
 
 pub fn add(left: usize, right: usize) -> usize {
     left + right
@@ -26,6 +55,6 @@ mod tests {
 
     #[test]
     fn disk() {
-        load_disks();
+        load_disks().unwrap();
     }
 }
@@ -0,0 +1,542 @@
+use std::{collections::{BTreeMap, HashMap}, fs, path::Path};
+
+use crate::error::{HwInfoError, Loaded};
+
+use super::sysfs::{parse_attr, read_dir};
+
+/// Size of the sectors sysfs reports block device sizes in, regardless of
+/// the device's own sector size.
+const SYSFS_SECTOR_SIZE: i64 = 512;
+
+#[derive(Debug, Clone, PartialEq)]
+/// Represents what a block device is
+pub enum BlockDeviceKind {
+    Disk,
+    Partition,
+    Raid,
+    LVM,
+    Crypt,
+    DeviceMapper,
+    Loop,
+    Unknown,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+/// Represents the state of a software RAID array
+pub enum RaidState {
+    Clean,
+    Degraded,
+    Resyncing,
+    Recovering,
+    Inactive,
+}
+
+#[derive(Debug, Clone)]
+/// Represents a software RAID array as reported by /proc/mdstat
+pub struct RaidStatus {
+    /// The RAID level of the array, such as raid1
+    level: String,
+    /// Whether the array is assembled and running
+    active: bool,
+    /// The number of devices the array is made of when complete
+    devices: u32,
+    /// The number of devices currently working in the array
+    working: u32,
+    /// The member devices of the array
+    members: Vec<String>,
+    /// The member devices marked as failed
+    failed: Vec<String>,
+    /// The member devices held as spares
+    spares: Vec<String>,
+    /// The running resync, recovery, reshape or check
+    sync_action: Option<String>,
+    /// The progress of the running sync as a percentage
+    sync_progress: Option<f64>,
+}
+
+impl RaidStatus {
+    /// Returns the RAID level of the array
+    pub fn level(&self) -> &String {
+        &self.level
+    }
+
+    /// Returns the number of devices the array is made of when complete
+    pub fn devices(&self) -> u32 {
+        self.devices
+    }
+
+    /// Returns the number of devices currently working in the array
+    pub fn working(&self) -> u32 {
+        self.working
+    }
+
+    /// Returns the member devices of the array
+    pub fn members(&self) -> &Vec<String> {
+        &self.members
+    }
+
+    /// Returns the member devices marked as failed
+    pub fn failed(&self) -> &Vec<String> {
+        &self.failed
+    }
+
+    /// Returns the member devices held as spares
+    pub fn spares(&self) -> &Vec<String> {
+        &self.spares
+    }
+
+    /// Returns the running resync, recovery, reshape or check
+    pub fn sync_action(&self) -> Option<&String> {
+        self.sync_action.as_ref()
+    }
+
+    /// Returns the progress of the running sync as a percentage
+    pub fn sync_progress(&self) -> Option<f64> {
+        self.sync_progress
+    }
+
+    /// Whether the array is missing devices or has failed ones
+    pub fn degraded(&self) -> bool {
+        self.working < self.devices || !self.failed.is_empty()
+    }
+
+    /// Returns the state of the array. A running sync takes precedence over
+    /// the array being degraded, as it is what will resolve it.
+    pub fn state(&self) -> RaidState {
+        if !self.active {
+            return RaidState::Inactive;
+        }
+        match self.sync_action.as_deref() {
+            Some("recovery") => RaidState::Recovering,
+            Some(_) => RaidState::Resyncing,
+            None if self.degraded() => RaidState::Degraded,
+            None => RaidState::Clean,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+/// Represents a block device and its place in the storage stack
+pub struct BlockDevice {
+    /// The kernel name of the device, such as sda1 or dm-0
+    name: String,
+    /// What the device is
+    kind: BlockDeviceKind,
+    /// The device-mapper name of the device, such as vg0-root
+    mapper_name: Option<String>,
+    /// The size of the device in bytes
+    size: i64,
+    /// The devices this device is built on
+    parents: Vec<String>,
+    /// The devices built on this device
+    children: Vec<String>,
+    /// The array status when the device is a software RAID array
+    raid: Option<RaidStatus>,
+}
+
+impl BlockDevice {
+    /// Returns the kernel name of the device
+    pub fn name(&self) -> &String {
+        &self.name
+    }
+
+    /// Returns what the device is
+    pub fn kind(&self) -> &BlockDeviceKind {
+        &self.kind
+    }
+
+    /// Returns the device-mapper name of the device
+    pub fn mapper_name(&self) -> Option<&String> {
+        self.mapper_name.as_ref()
+    }
+
+    /// Returns the size of the device in bytes
+    pub fn size(&self) -> i64 {
+        self.size
+    }
+
+    /// Returns the devices this device is built on
+    pub fn parents(&self) -> &Vec<String> {
+        &self.parents
+    }
+
+    /// Returns the devices built on this device
+    pub fn children(&self) -> &Vec<String> {
+        &self.children
+    }
+
+    /// Returns the array status when the device is a software RAID array
+    pub fn raid(&self) -> Option<&RaidStatus> {
+        self.raid.as_ref()
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+/// Represents the tree of block devices on the system
+pub struct BlockTopology {
+    devices: BTreeMap<String, BlockDevice>,
+}
+
+impl BlockTopology {
+    /// Builds the tree from a set of block devices, linking each parent to
+    /// the devices built on it
+    pub fn new(devices: Vec<BlockDevice>) -> Self {
+        let mut devices: BTreeMap<String, BlockDevice> = devices.into_iter().map(|d| (d.name.clone(), d)).collect();
+
+        let links: Vec<(String, String)> = devices.values()
+            .flat_map(|d| d.parents.iter().map(move |p| (p.clone(), d.name.clone())))
+            .collect();
+        for (parent, child) in links {
+            if let Some(device) = devices.get_mut(&parent) {
+                device.children.push(child);
+            }
+        }
+        for device in devices.values_mut() {
+            device.children.sort();
+        }
+
+        BlockTopology { devices }
+    }
+
+    /// Returns all block devices ordered by name
+    pub fn devices(&self) -> impl Iterator<Item = &BlockDevice> {
+        self.devices.values()
+    }
+
+    /// Returns the block device with the given kernel name
+    pub fn get(&self, name: &str) -> Option<&BlockDevice> {
+        self.devices.get(name)
+    }
+
+    /// Returns the devices that aren't built on another device
+    pub fn roots(&self) -> Vec<&BlockDevice> {
+        self.devices.values().filter(|d| d.parents.is_empty()).collect()
+    }
+
+    /// Finds the block device behind a device path such as /dev/sda1,
+    /// /dev/mapper/vg0-root or /dev/vg0/root.
+    pub fn resolve(&self, path: &str) -> Option<&BlockDevice> {
+        let path = path.strip_prefix("/dev/").unwrap_or(path);
+        if let Some(mapper_name) = path.strip_prefix("mapper/") {
+            return self.by_mapper_name(mapper_name);
+        }
+        if let Some(device) = self.devices.get(path) {
+            return Some(device);
+        }
+        // LVM links volumes as /dev/<vg>/<lv>, doubling dashes in the mapper name.
+        match path.split_once('/') {
+            Some((vg, lv)) => self.by_mapper_name(&format!("{}-{}", vg.replace('-', "--"), lv.replace('-', "--"))),
+            None => None,
+        }
+    }
+
+    /// Returns the names of the disks a device is ultimately stored on
+    pub fn physical_disks(&self, name: &str) -> Vec<String> {
+        let mut disks = Vec::new();
+        let mut pending = vec![name.to_string()];
+        while let Some(name) = pending.pop() {
+            let device = match self.devices.get(&name) {
+                Some(d) => d,
+                None => continue,
+            };
+            if device.kind == BlockDeviceKind::Disk {
+                if !disks.contains(&device.name) {
+                    disks.push(device.name.clone());
+                }
+                continue;
+            }
+            pending.extend(device.parents.iter().cloned());
+        }
+        disks.sort();
+        disks
+    }
+
+    fn by_mapper_name(&self, mapper_name: &str) -> Option<&BlockDevice> {
+        self.devices.values().find(|d| d.mapper_name.as_deref() == Some(mapper_name))
+    }
+}
+
+/// Loads the block devices on the system, to be built into a `BlockTopology`
+pub fn load_block_topology() -> Result<Loaded<BlockDevice>, HwInfoError> {
+    load_block_topology_from(Path::new("/"))
+}
+
+/// Loads the block devices from the sysfs and procfs trees under `root`. A
+/// disk that can't be read is skipped along with its partitions.
+pub fn load_block_topology_from(root: &Path) -> Result<Loaded<BlockDevice>, HwInfoError> {
+    let mut arrays = match fs::read_to_string(root.join("proc/mdstat")) {
+        Ok(mdstat) => parse_mdstat(&mdstat),
+        Err(_) => HashMap::new(),
+    };
+
+    let mut devices = Vec::new();
+    let mut errors = Vec::new();
+    for entry in read_dir(&root.join("sys/block"))?.flatten() {
+        let name = entry.file_name().to_string_lossy().to_string();
+        let path = entry.path();
+
+        let mut device = match form_device(&path, &name, None) {
+            Ok(d) => d,
+            Err(e) => {
+                errors.push(e);
+                continue;
+            },
+        };
+        // Unused loop devices are always present and have no size.
+        if device.kind == BlockDeviceKind::Loop && device.size == 0 {
+            continue;
+        }
+        device.raid = arrays.remove(&name);
+
+        if let Ok(entries) = fs::read_dir(&path) {
+            for part in entries.flatten() {
+                if !part.path().join("partition").exists() {
+                    continue;
+                }
+                let part_name = part.file_name().to_string_lossy().to_string();
+                match form_device(&part.path(), &part_name, Some(&name)) {
+                    Ok(partition) => devices.push(partition),
+                    Err(e) => errors.push(e),
+                }
+            }
+        }
+        devices.push(device);
+    }
+
+    Ok(Loaded::new(devices, errors))
+}
+
+fn form_device(path: &Path, name: &str, partition_of: Option<&str>) -> Result<BlockDevice, HwInfoError> {
+    let size = parse_attr::<i64>(&path.join("size"))?.unwrap_or(0) * SYSFS_SECTOR_SIZE;
+    let mapper_name = read_trimmed(&path.join("dm").join("name"));
+
+    let parents = match partition_of {
+        Some(disk) => vec![disk.to_string()],
+        None => {
+            let mut slaves: Vec<String> = match fs::read_dir(path.join("slaves")) {
+                Ok(entries) => entries.flatten().map(|e| e.file_name().to_string_lossy().to_string()).collect(),
+                Err(_) => Vec::new(),
+            };
+            slaves.sort();
+            slaves
+        },
+    };
+
+    let kind = if partition_of.is_some() {
+        BlockDeviceKind::Partition
+    } else if path.join("md").exists() {
+        BlockDeviceKind::Raid
+    } else if mapper_name.is_some() {
+        let uuid = read_trimmed(&path.join("dm").join("uuid")).unwrap_or_default();
+        if uuid.starts_with("LVM-") {
+            BlockDeviceKind::LVM
+        } else if uuid.starts_with("CRYPT-") {
+            BlockDeviceKind::Crypt
+        } else {
+            BlockDeviceKind::DeviceMapper
+        }
+    } else if name.starts_with("loop") {
+        BlockDeviceKind::Loop
+    } else if path.join("device").exists() {
+        BlockDeviceKind::Disk
+    } else {
+        BlockDeviceKind::Unknown
+    };
+
+    Ok(BlockDevice {
+        name: name.to_string(),
+        kind,
+        mapper_name,
+        size,
+        parents,
+        children: Vec::new(),
+        raid: None,
+    })
+}
+
+fn read_trimmed(path: &Path) -> Option<String> {
+    match fs::read_to_string(path) {
+        Ok(value) if !value.trim().is_empty() => Some(value.trim().to_string()),
+        _ => None,
+    }
+}
+
+/// Parses the arrays in /proc/mdstat, keyed by array name. An array's first
+/// line holds its state, level and members, and the indented lines after it
+/// its device counts and any running sync.
+fn parse_mdstat(content: &str) -> HashMap<String, RaidStatus> {
+    let mut arrays = HashMap::new();
+    let mut lines = content.lines().peekable();
+
+    while let Some(line) = lines.next() {
+        let (name, rest) = match line.split_once(" : ") {
+            Some((name, rest)) if name.starts_with("md") => (name.trim(), rest),
+            _ => continue,
+        };
+
+        let mut words = rest.split_whitespace().peekable();
+        let active = words.next() == Some("active");
+        while words.next_if(|w| w.starts_with('(')).is_some() {}
+        let level = match words.next_if(|w| !w.contains('[')) {
+            Some(level) => level.to_string(),
+            None => "unknown".to_string(),
+        };
+
+        let mut members = Vec::new();
+        let mut failed = Vec::new();
+        let mut spares = Vec::new();
+        for word in words {
+            let (member, flags) = match word.split_once('[') {
+                Some((member, rest)) => (member.to_string(), rest),
+                None => continue,
+            };
+            if flags.contains("(F)") {
+                failed.push(member.clone());
+            } else if flags.contains("(S)") {
+                spares.push(member.clone());
+            }
+            members.push(member);
+        }
+        members.sort();
+
+        let mut counts = None;
+        let mut sync_action = None;
+        let mut sync_progress = None;
+        while let Some(detail) = lines.next_if(|l| l.starts_with(char::is_whitespace) && !l.trim().is_empty()) {
+            if counts.is_none() {
+                counts = parse_counts(detail);
+            }
+            if let Some((action, progress)) = parse_sync(detail) {
+                sync_action = Some(action);
+                sync_progress = progress;
+            }
+        }
+
+        // Levels without redundancy, such as raid0, don't report counts.
+        let (devices, working) = counts.unwrap_or_else(|| {
+            let total = (members.len() - spares.len()) as u32;
+            (total, total - failed.len() as u32)
+        });
+
+        arrays.insert(name.to_string(), RaidStatus {
+            level,
+            active,
+            devices,
+            working,
+            members,
+            failed,
+            spares,
+            sync_action,
+            sync_progress,
+        });
+    }
+
+    arrays
+}
+
+/// Parses the `[total/working]` counts of an array's status line.
+fn parse_counts(line: &str) -> Option<(u32, u32)> {
+    line.split_whitespace()
+        .filter_map(|w| w.strip_prefix('[').and_then(|w| w.strip_suffix(']')))
+        .find_map(|w| {
+            let (total, working) = w.split_once('/')?;
+            Some((total.parse().ok()?, working.parse().ok()?))
+        })
+}
+
+/// Parses a sync line such as `recovery = 27.3% (266588160/976630272)`, or
+/// `resync=DELAYED` for a sync that hasn't started.
+fn parse_sync(line: &str) -> Option<(String, Option<f64>)> {
+    for action in ["recovery", "resync", "reshape", "check", "repair"] {
+        let rest = match line.split_once(action) {
+            Some((_, rest)) => rest.trim_start(),
+            None => continue,
+        };
+        let rest = match rest.strip_prefix('=') {
+            Some(rest) => rest.trim_start(),
+            None => continue,
+        };
+        let progress = rest.split('%').next().and_then(|p| p.trim().parse::<f64>().ok());
+        return Some((action.to_string(), progress));
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{error::HwInfoError, linux::sysfs::fixture};
+
+    use super::{load_block_topology_from, parse_mdstat, BlockDeviceKind, BlockTopology, RaidState};
+
+    #[test]
+    fn storage_server() {
+        let root = fixture("storage-server");
+        let (devices, errors) = load_block_topology_from(&root).unwrap().into_parts();
+        assert!(errors.is_empty());
+        let topology = BlockTopology::new(devices);
+
+        let sda = topology.get("sda").unwrap();
+        assert_eq!(sda.kind(), &BlockDeviceKind::Disk);
+        assert_eq!(sda.children(), &vec!["sda1".to_string(), "sda2".to_string()]);
+        assert!(topology.get("loop0").is_none());
+
+        let md0 = topology.get("md0").unwrap();
+        assert_eq!(md0.kind(), &BlockDeviceKind::Raid);
+        assert_eq!(md0.parents(), &vec!["sda2".to_string(), "sdb2".to_string()]);
+        let raid = md0.raid().unwrap();
+        assert_eq!(raid.level(), "raid1");
+        assert_eq!(raid.state(), RaidState::Clean);
+
+        let md1 = topology.get("md1").unwrap().raid().unwrap();
+        assert_eq!(md1.state(), RaidState::Recovering);
+        assert!(md1.degraded());
+        assert_eq!(md1.failed(), &vec!["sde1".to_string()]);
+        assert_eq!(md1.sync_progress(), Some(27.3));
+
+        let root_lv = topology.resolve("/dev/mapper/vg0-root").unwrap();
+        assert_eq!(root_lv.kind(), &BlockDeviceKind::LVM);
+        assert_eq!(topology.resolve("/dev/vg0/root").unwrap().name(), root_lv.name());
+        assert_eq!(topology.physical_disks(root_lv.name()), vec!["sda", "sdb"]);
+
+        let data = topology.resolve("/dev/mapper/data-archive").unwrap();
+        assert_eq!(data.parents(), &vec!["dm-1".to_string()]);
+        assert_eq!(topology.get("dm-1").unwrap().kind(), &BlockDeviceKind::Crypt);
+        assert_eq!(topology.physical_disks(data.name()), vec!["sdc", "sdd", "sde"]);
+
+        let boot = topology.resolve("/dev/sda1").unwrap();
+        assert_eq!(boot.kind(), &BlockDeviceKind::Partition);
+        assert_eq!(boot.size(), 1073741824);
+    }
+
+    #[test]
+    fn corrupt_attribute_skips_the_device() {
+        let (devices, errors) = load_block_topology_from(&fixture("corrupt")).unwrap().into_parts();
+        let names: Vec<&str> = devices.iter().map(|d| d.name().as_str()).collect();
+        assert_eq!(names, vec!["sdb"]);
+
+        match errors.as_slice() {
+            [HwInfoError::Parse { path, value }] => {
+                assert!(path.ends_with("sda/size"));
+                assert_eq!(value, "not-a-number");
+            },
+            other => panic!("expected a parse error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn inactive_and_striped_arrays() {
+        let arrays = parse_mdstat("Personalities : [raid0]\n\
+            md127 : inactive sdb[1](S) sda[0](S)\n      3906764976 blocks super 1.2\n\n\
+            md2 : active raid0 sdd[1] sdc[0]\n      1953260544 blocks super 1.2 512k chunks\n\n\
+            md3 : active raid6 sdh[3] sdg[2] sdf[1] sde[0]\n      3906764800 blocks super 1.2 level 6, 512k chunk, algorithm 2 [4/4] [UUUU]\n      \tresync=DELAYED\n\n\
+            unused devices: <none>\n");
+
+        assert_eq!(arrays.len(), 3);
+        assert_eq!(arrays["md127"].state(), RaidState::Inactive);
+        assert_eq!(arrays["md2"].level(), "raid0");
+        assert_eq!(arrays["md2"].devices(), 2);
+        assert_eq!(arrays["md2"].state(), RaidState::Clean);
+        assert_eq!(arrays["md3"].state(), RaidState::Resyncing);
+        assert_eq!(arrays["md3"].sync_progress(), None);
+    }
+}
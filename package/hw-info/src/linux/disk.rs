@@ -1,4 +1,8 @@
-use std::fs;
+use std::{fs, path::Path};
+
+use crate::error::{HwInfoError, Loaded};
+
+use super::sysfs::{parse_attr, read_dir};
 
 
 #[derive(Debug)]
@@ -88,25 +92,31 @@ impl Disk {
     }
 }
 
-/// Loads all phyiscal disks on the system and returns them as a vector
-pub fn load_disks() -> Vec<Disk> {
+/// Loads all phyiscal disks on the system. Disks whose attributes cannot be
+/// read are skipped and their errors returned alongside the rest
+pub fn load_disks() -> Result<Loaded<Disk>, HwInfoError> {
+    load_disks_from(Path::new("/"))
+}
+
+/// Loads all physical disks from the sysfs tree under `root`
+pub fn load_disks_from(root: &Path) -> Result<Loaded<Disk>, HwInfoError> {
     let mut disks = Vec::new();
+    let mut errors = Vec::new();
 
-    let ls_dir =  fs::read_dir("/sys/block");
-    let hits = match ls_dir {
-        Ok(_) => ls_dir.unwrap(),
-        Err(_) => { 
-            return disks;
-        },
-    };
+    let hits = read_dir(&root.join("sys/block"))?;
 
     for dir in hits {
         match dir {
             Ok(dir) => {
-                let f_type = dir.file_type().unwrap();
+                let f_type = match dir.file_type() {
+                    Ok(f_type) => f_type,
+                    Err(_) => continue,
+                };
                 if (f_type.is_dir() || f_type.is_symlink()) && is_device(&dir) {
-                    let disk = form_disk(&dir);
-                    disks.push(disk);
+                    match form_disk(&dir) {
+                        Ok(disk) => disks.push(disk),
+                        Err(e) => errors.push(e),
+                    }
                 }
             },
             Err(_) => {
@@ -115,7 +125,8 @@ pub fn load_disks() -> Vec<Disk> {
         }
     }
 
-    disks
+    disks.sort_by(|a, b| a.device.cmp(&b.device));
+    Ok(Loaded::new(disks, errors))
 
 }
 
@@ -125,18 +136,18 @@ fn is_device(dir: &fs::DirEntry) -> bool {
     device_dir.is_ok()
 }
 
-fn form_disk(dir: &fs::DirEntry) -> Disk {
+fn form_disk(dir: &fs::DirEntry) -> Result<Disk, HwInfoError> {
     let device = get_device(dir);
     let model = get_model(dir);
     let vendor = get_vendor(dir);
     let serial = get_serial(dir);
     let interface = get_interface(dir);
-    let sector_size = get_sector_size(dir);
-    let size_raw = get_size_raw(dir);
+    let sector_size = get_sector_size(dir)?;
+    let size_raw = get_size_raw(dir)?;
     let size_actual = determine_actual_size(sector_size, size_raw);
     let kind = determine_kind(dir);
 
-    Disk {
+    Ok(Disk {
         device,
         model,
         vendor,
@@ -146,7 +157,7 @@ fn form_disk(dir: &fs::DirEntry) -> Disk {
         size_raw,
         size_actual,
         kind,
-    }
+    })
 }
 
 fn get_device(dir: &fs::DirEntry) -> String {
@@ -176,22 +187,16 @@ fn get_vendor(dir: &fs::DirEntry) -> String {
     }
 }
 
-fn get_sector_size(dir: &fs::DirEntry) -> i16 {
+fn get_sector_size(dir: &fs::DirEntry) -> Result<i16, HwInfoError> {
     let sector_size_file = dir.path().join("queue").join("logical_block_size");
-    let sector_size_file = fs::read_to_string(sector_size_file);
-    match sector_size_file {
-        Ok(sector_size) => sector_size.trim().parse::<i16>().unwrap(),
-        Err(_) => 0,
-    }
+    let sector_size = parse_attr::<i16>(&sector_size_file)?;
+    Ok(sector_size.unwrap_or(0))
 }
 
-fn get_size_raw(dir: &fs::DirEntry) -> i64 {
+fn get_size_raw(dir: &fs::DirEntry) -> Result<i64, HwInfoError> {
     let size_file = dir.path().join("size");
-    let size_file = fs::read_to_string(size_file);
-    match size_file {
-        Ok(size) => size.trim().parse::<i64>().unwrap(),
-        Err(_) => 0,
-    }
+    let size = parse_attr::<i64>(&size_file)?;
+    Ok(size.unwrap_or(0))
 }
 
 fn get_serial(dir: &fs::DirEntry) -> String {
@@ -230,7 +235,7 @@ fn determine_actual_size(sector: i16, raw: i64) -> i64 {
 }
 
 fn determine_kind(dir: &fs::DirEntry) -> DiskKind {
-    if dir.file_name().to_str().unwrap().contains("nvme") {
+    if dir.file_name().to_str().unwrap_or("").contains("nvme") {
         return DiskKind::NVME;
     }
 
@@ -251,12 +256,64 @@ fn determine_kind(dir: &fs::DirEntry) -> DiskKind {
 #[cfg(test)]
 mod tests {
     // only works on linux
-    use super::load_disks;
+    use crate::{error::HwInfoError, linux::sysfs::fixture};
+
+    use super::{load_disks, load_disks_from, DiskInterface, DiskKind};
 
     #[test]
     fn disk() {
-        let disks = load_disks();
-        assert!(!disks.is_empty());
+        let disks = load_disks().unwrap();
+        assert!(!disks.entries().is_empty());
         println!("{:?}", disks)
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn sata_disks() {
+        let (disks, errors) = load_disks_from(&fixture("sata-server")).unwrap().into_parts();
+        assert!(errors.is_empty());
+        assert_eq!(disks.len(), 2);
+
+        let hdd = &disks[0];
+        assert_eq!(hdd.get_device(), "sda");
+        assert_eq!(hdd.get_model(), "ST4000NM0035-1V4");
+        assert!(matches!(hdd.get_interface(), DiskInterface::SATA));
+        assert!(matches!(hdd.get_kind(), DiskKind::HDD));
+        assert_eq!(*hdd.get_size_raw(), 7814037168);
+
+        assert!(matches!(disks[1].get_kind(), DiskKind::SSD));
+    }
+
+    #[test]
+    fn nvme_disk() {
+        let (disks, _) = load_disks_from(&fixture("nvme-workstation")).unwrap().into_parts();
+        assert_eq!(disks.len(), 1);
+        assert!(matches!(disks[0].get_interface(), DiskInterface::PCI_E));
+        assert!(matches!(disks[0].get_kind(), DiskKind::NVME));
+        assert_eq!(disks[0].get_serial(), "S5GXNF0R123456");
+        assert_eq!(disks[0].get_vendor(), "Unknown");
+    }
+
+    #[test]
+    fn virtio_disk() {
+        let (disks, _) = load_disks_from(&fixture("qemu-vm")).unwrap().into_parts();
+        let devices: Vec<&str> = disks.iter().map(|d| d.get_device().as_str()).collect();
+        assert_eq!(devices, vec!["sr0", "vda"]);
+        assert_eq!(disks[1].get_vendor(), "0x1af4");
+        assert_eq!(*disks[1].get_sector_size(), 512);
+    }
+
+    #[test]
+    fn corrupt_attribute_skips_the_disk() {
+        let (disks, errors) = load_disks_from(&fixture("corrupt")).unwrap().into_parts();
+        let devices: Vec<&str> = disks.iter().map(|d| d.get_device().as_str()).collect();
+        assert_eq!(devices, vec!["sdb"]);
+
+        match errors.as_slice() {
+            [HwInfoError::Parse { path, value }] => {
+                assert!(path.ends_with("sda/size"));
+                assert_eq!(value, "not-a-number");
+            },
+            other => panic!("expected a parse error, got {:?}", other),
+        }
+    }
+}
@@ -1,2 +1,7 @@
+pub mod block;
 pub mod disk;
-pub mod nic;
\ No newline at end of file
+pub mod nic;
+pub mod pci;
+pub mod smart;
+pub(crate) mod sysfs;
+pub mod virt;
\ No newline at end of file
@@ -1,4 +1,8 @@
-use std::fs;
+use std::{fs, path::Path};
+
+use crate::error::{HwInfoError, Loaded};
+
+use super::{pci::PciIds, sysfs::{parse_attr, read_dir}};
 
 
 #[derive(Debug)]
@@ -12,8 +16,8 @@ pub struct NetworkInterface {
     vendor_code: String,
     /// The MAC address of the interface
     mac_address: String,
-    /// The speed of the interface
-    speed: u16,
+    /// The speed of the interface in Mb/s, 0 when unknown
+    speed: u32,
     /// The duplex of the interface
     duplex: String,
     /// The MTU of the interface
@@ -45,7 +49,7 @@ impl NetworkInterface {
         &self.mac_address
     }
 
-    pub fn speed(&self) -> &u16 {
+    pub fn speed(&self) -> &u32 {
         &self.speed
     }
 
@@ -75,42 +79,50 @@ impl NetworkInterface {
 
 }
 
-pub fn load_nics() -> Vec<NetworkInterface> {
-    let mut nics = Vec::new();
+/// Loads all physical network interfaces on the system. Interfaces whose
+/// attributes cannot be read are skipped and their errors returned alongside the rest
+pub fn load_nics() -> Result<Loaded<NetworkInterface>, HwInfoError> {
+    collect_nics(Path::new("/"), PciIds::system())
+}
 
-    let paths = fs::read_dir("/sys/class/net").unwrap();
-    for path in paths {
+/// Loads all physical network interfaces from the sysfs tree under `root`,
+/// naming vendors with the PCI ID database installed there
+pub fn load_nics_from(root: &Path) -> Result<Loaded<NetworkInterface>, HwInfoError> {
+    collect_nics(root, &PciIds::load_from(root))
+}
 
-        match path {
-            Ok(_) => {
-                let path = path.unwrap();
-                if is_physical(&path) {
-                    let nic = form_nic(&path);
-                    nics.push(nic);
-                }
-            },
-            Err(_) => {},
+fn collect_nics(root: &Path, ids: &PciIds) -> Result<Loaded<NetworkInterface>, HwInfoError> {
+    let mut nics = Vec::new();
+    let mut errors = Vec::new();
+
+    let paths = read_dir(&root.join("sys/class/net"))?;
+    for path in paths.flatten() {
+        if is_physical(&path) {
+            match form_nic(&path, ids) {
+                Ok(nic) => nics.push(nic),
+                Err(e) => errors.push(e),
+            }
         }
-
     }
 
-    nics
+    nics.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(Loaded::new(nics, errors))
 }
 
-fn form_nic(dir: &fs::DirEntry) -> NetworkInterface {
+fn form_nic(dir: &fs::DirEntry, ids: &PciIds) -> Result<NetworkInterface, HwInfoError> {
     let name = get_name(dir);
     let vendor_code = get_vendor_code(dir);
-    let vendor = vendor_code_to_vendor(&vendor_code);
+    let vendor = vendor_code_to_vendor(&vendor_code, ids);
     let mac_address = get_mac_address(dir);
-    let speed = get_speed(dir);
+    let speed = get_speed(dir)?;
     let duplex = get_duplex(dir);
-    let mtu = get_mtu(dir);
+    let mtu = get_mtu(dir)?;
     let broadcast = get_broadcast(dir);
     let active = is_active(dir);
     let device_id = get_device_id(dir);
     let subsystem_id = get_subsystem_id(dir);
 
-    NetworkInterface {
+    Ok(NetworkInterface {
         name,
         vendor_code,
         vendor,
@@ -122,7 +134,7 @@ fn form_nic(dir: &fs::DirEntry) -> NetworkInterface {
         active,
         device_id,
         subsystem_id,
-    }
+    })
 }
 
 fn get_name(dir: &fs::DirEntry) -> String {
@@ -150,11 +162,15 @@ fn get_mac_address(dir: &fs::DirEntry) -> String {
     }
 }
 
-fn get_speed(dir: &fs::DirEntry) -> u16 {
-    let speed = fs::read_to_string(dir.path().join("speed"));
+fn get_speed(dir: &fs::DirEntry) -> Result<u32, HwInfoError> {
+    // Drivers report -1 when the link speed is unknown.
+    let speed = parse_attr::<i64>(&dir.path().join("speed"))?;
     match speed {
-        Ok(speed) => speed.trim().parse::<u16>().unwrap(),
-        Err(_) => 0,
+        Some(speed) if speed > 0 => u32::try_from(speed).map_err(|_| HwInfoError::Parse {
+            path: dir.path().join("speed"),
+            value: speed.to_string(),
+        }),
+        _ => Ok(0),
     }
 }
 
@@ -165,7 +181,7 @@ fn is_active(dir: &fs::DirEntry) -> bool {
         Ok(operstate) => operstate,
         Err(_) => return false,
     };
-    if operstate == "up" {
+    if operstate.trim() == "up" {
         return true;
     }
     false
@@ -204,12 +220,9 @@ fn get_subsystem_id(dir: &fs::DirEntry) -> String {
     subsystem_id
 }
 
-fn get_mtu(dir: &fs::DirEntry) -> u16 {
-    let mtu = fs::read_to_string(dir.path().join("mtu"));
-    match mtu {
-        Ok(mtu) => mtu.trim().parse::<u16>().unwrap(),
-        Err(_) => 0,
-    }
+fn get_mtu(dir: &fs::DirEntry) -> Result<u16, HwInfoError> {
+    let mtu = parse_attr::<u16>(&dir.path().join("mtu"))?;
+    Ok(mtu.unwrap_or(0))
 }
 
 fn is_physical(dir: &fs::DirEntry) -> bool {
@@ -217,37 +230,96 @@ fn is_physical(dir: &fs::DirEntry) -> bool {
     let driver_dir = fs::read_dir(driver_dir);
     match driver_dir {
         Ok(_) => {
-            if dir.file_type().unwrap().is_symlink() {
-                return true;
+            match dir.file_type() {
+                Ok(f_type) => f_type.is_symlink(),
+                Err(_) => false,
             }
-            false
         },
         Err(_) => false,
     }
 }
 
-fn vendor_code_to_vendor(code: &str) -> String {
-    match code {
-        "0x8086"=>"Intel Corporation".to_string(),
-        "0x8087"=>"Intel Corporation".to_string(),
-        "0x14e4"=>"Broadcom Inc. and subsidiaries".to_string(),
-        "0x10ec"=>"Realtek Semiconductor Co., Ltd.".to_string(),
-        "0x1af4"=>"Red Hat, Inc.".to_string(),
-        "0x168C"=>"Qualcomm Atheros".to_string(),
-        "0x15b3"=>" Mellanox Technologies".to_string(),
-        _=>"Unknown".to_string(),
+fn vendor_code_to_vendor(code: &str, ids: &PciIds) -> String {
+    let vendor = u16::from_str_radix(code.trim_start_matches("0x"), 16)
+        .ok()
+        .and_then(|code| ids.vendor(code));
+    match vendor {
+        Some(vendor) => vendor.to_string(),
+        None => "Unknown".to_string(),
     }
 }
 
 #[cfg(test)]
 mod tests {
     // only works on linux
-    use super::load_nics;
+    use crate::{error::HwInfoError, linux::sysfs::fixture};
+
+    use super::{load_nics, load_nics_from};
 
     #[test]
     fn nics() {
-        let nics = load_nics();
-        assert!(!nics.is_empty());
+        let nics = load_nics().unwrap();
+        assert!(!nics.entries().is_empty());
         println!("{:?}", nics)
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn bond_members_are_physical() {
+        let (nics, errors) = load_nics_from(&fixture("sata-server")).unwrap().into_parts();
+        assert!(errors.is_empty());
+        let names: Vec<&str> = nics.iter().map(|n| n.name().as_str()).collect();
+        assert_eq!(names, vec!["eno1", "eno2"]);
+
+        let eno1 = &nics[0];
+        assert_eq!(eno1.vendor(), "Intel Corporation");
+        assert_eq!(eno1.device_id(), "0x15b8");
+        assert_eq!(*eno1.speed(), 1000);
+        assert_eq!(eno1.duplex(), "full");
+        assert!(*eno1.active());
+    }
+
+    #[test]
+    fn fast_and_down_links() {
+        let (nics, _) = load_nics_from(&fixture("nvme-workstation")).unwrap().into_parts();
+        assert_eq!(nics.len(), 2);
+
+        let mlx = &nics[0];
+        assert_eq!(mlx.vendor_code(), "0x15b3");
+        assert_eq!(mlx.vendor(), "Mellanox Technologies");
+        assert_eq!(*mlx.speed(), 100000);
+        assert_eq!(*mlx.mtu(), 9000);
+
+        let wifi = &nics[1];
+        assert_eq!(*wifi.speed(), 0);
+        assert!(!*wifi.active());
+    }
+
+    #[test]
+    fn virtio_unknown_speed() {
+        let (nics, _) = load_nics_from(&fixture("qemu-vm")).unwrap().into_parts();
+        assert_eq!(nics.len(), 1);
+        assert_eq!(nics[0].vendor(), "Red Hat, Inc.");
+        assert_eq!(*nics[0].speed(), 0);
+    }
+
+    #[test]
+    fn corrupt_attribute_skips_the_interface() {
+        let (nics, errors) = load_nics_from(&fixture("corrupt")).unwrap().into_parts();
+        let names: Vec<&str> = nics.iter().map(|n| n.name().as_str()).collect();
+        assert_eq!(names, vec!["eth1"]);
+        assert_eq!(*nics[0].mtu(), 1500);
+
+        match errors.as_slice() {
+            [HwInfoError::Parse { path, value }] => {
+                assert!(path.ends_with("eth0/mtu"));
+                assert_eq!(value, "jumbo");
+            },
+            other => panic!("expected a parse error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn missing_root_is_an_error() {
+        assert!(matches!(load_nics_from(&fixture("missing")), Err(HwInfoError::Io { .. })));
+    }
+}
@@ -0,0 +1,413 @@
+use std::{collections::HashMap, fs, path::Path, sync::OnceLock};
+
+use crate::error::{HwInfoError, Loaded};
+
+use super::sysfs::{parse_attr, read_dir};
+
+/// Where distributions install the PCI ID database, relative to the root
+const PCI_IDS_PATHS: [&str; 3] = ["usr/share/hwdata/pci.ids", "usr/share/misc/pci.ids", "usr/share/pci.ids"];
+
+/// A subset of the database used when the system has none
+const EMBEDDED_PCI_IDS: &str = include_str!("../../data/pci.ids");
+
+#[derive(Debug, Default)]
+/// Represents the PCI ID database, mapping vendor, device and class codes to names
+pub struct PciIds {
+    vendors: HashMap<u16, PciVendor>,
+    classes: HashMap<u8, PciClass>,
+}
+
+#[derive(Debug, Default)]
+struct PciVendor {
+    name: String,
+    devices: HashMap<u16, String>,
+}
+
+#[derive(Debug, Default)]
+struct PciClass {
+    name: String,
+    subclasses: HashMap<u8, String>,
+}
+
+impl PciIds {
+    /// Returns the system's database, loaded once on first use
+    pub fn system() -> &'static PciIds {
+        static IDS: OnceLock<PciIds> = OnceLock::new();
+        IDS.get_or_init(|| PciIds::load_from(Path::new("/")))
+    }
+
+    /// Loads the database installed under `root`, falling back to the
+    /// embedded subset when there is none.
+    pub fn load_from(root: &Path) -> PciIds {
+        for path in PCI_IDS_PATHS {
+            if let Ok(content) = fs::read_to_string(root.join(path)) {
+                return PciIds::parse(&content);
+            }
+        }
+        PciIds::parse(EMBEDDED_PCI_IDS)
+    }
+
+    /// Parses a database in the pci.ids format. Subsystem and programming
+    /// interface entries are skipped.
+    pub fn parse(content: &str) -> PciIds {
+        let mut ids = PciIds::default();
+        let mut vendor: Option<u16> = None;
+        let mut class: Option<u8> = None;
+
+        for line in content.lines() {
+            if line.starts_with('#') || line.trim().is_empty() || line.starts_with("\t\t") {
+                continue;
+            }
+
+            if let Some(entry) = line.strip_prefix('\t') {
+                let (code, name) = match split_entry(entry) {
+                    Some(e) => e,
+                    None => continue,
+                };
+                if let Some(v) = vendor {
+                    if let (Ok(device), Some(vendor)) = (u16::from_str_radix(code, 16), ids.vendors.get_mut(&v)) {
+                        vendor.devices.insert(device, name.to_string());
+                    }
+                } else if let Some(c) = class {
+                    if let (Ok(subclass), Some(class)) = (u8::from_str_radix(code, 16), ids.classes.get_mut(&c)) {
+                        class.subclasses.insert(subclass, name.to_string());
+                    }
+                }
+                continue;
+            }
+
+            if let Some(entry) = line.strip_prefix("C ") {
+                vendor = None;
+                class = None;
+                if let Some((code, name)) = split_entry(entry) {
+                    if let Ok(code) = u8::from_str_radix(code, 16) {
+                        ids.classes.insert(code, PciClass { name: name.to_string(), ..Default::default() });
+                        class = Some(code);
+                    }
+                }
+                continue;
+            }
+
+            vendor = None;
+            class = None;
+            // Top level lines that aren't a vendor code are ignored.
+            if let Some((code, name)) = split_entry(line) {
+                if let Ok(code) = u16::from_str_radix(code, 16) {
+                    ids.vendors.insert(code, PciVendor { name: name.to_string(), ..Default::default() });
+                    vendor = Some(code);
+                }
+            }
+        }
+
+        ids
+    }
+
+    /// Returns the name of a vendor
+    pub fn vendor(&self, vendor: u16) -> Option<&str> {
+        self.vendors.get(&vendor).map(|v| v.name.as_str())
+    }
+
+    /// Returns the name of a vendor's device
+    pub fn device(&self, vendor: u16, device: u16) -> Option<&str> {
+        self.vendors.get(&vendor).and_then(|v| v.devices.get(&device)).map(|d| d.as_str())
+    }
+
+    /// Returns the name of a device class
+    pub fn class(&self, class: u8) -> Option<&str> {
+        self.classes.get(&class).map(|c| c.name.as_str())
+    }
+
+    /// Returns the name of a device subclass
+    pub fn subclass(&self, class: u8, subclass: u8) -> Option<&str> {
+        self.classes.get(&class).and_then(|c| c.subclasses.get(&subclass)).map(|s| s.as_str())
+    }
+}
+
+/// Splits an entry such as `8086  Intel Corporation` into its code and name.
+fn split_entry(entry: &str) -> Option<(&str, &str)> {
+    let (code, name) = entry.split_once(char::is_whitespace)?;
+    Some((code, name.trim()))
+}
+
+#[derive(Debug, Clone)]
+/// Represents a PCI device
+pub struct PciDevice {
+    /// The address of the device, such as 0000:00:1f.6
+    address: String,
+    /// The vendor code of the device
+    vendor_id: u16,
+    /// The device code of the device
+    device_id: u16,
+    /// The vendor code of the board the device is on
+    subsystem_vendor_id: Option<u16>,
+    /// The device code of the board the device is on
+    subsystem_device_id: Option<u16>,
+    /// The class code of the device, as class, subclass and programming interface bytes
+    class_id: u32,
+    /// The revision of the device
+    revision: Option<u8>,
+    /// The name of the vendor
+    vendor: Option<String>,
+    /// The name of the device
+    device: Option<String>,
+    /// The name of the device's class
+    class: Option<String>,
+    /// The name of the device's subclass
+    subclass: Option<String>,
+    /// The kernel driver bound to the device
+    driver: Option<String>,
+    /// The NUMA node the device is attached to
+    numa_node: Option<u32>,
+    /// The current link speed, such as "8.0 GT/s PCIe"
+    link_speed: Option<String>,
+    /// The current number of lanes
+    link_width: Option<u8>,
+    /// The fastest link speed the device supports
+    max_link_speed: Option<String>,
+    /// The most lanes the device supports
+    max_link_width: Option<u8>,
+}
+
+impl PciDevice {
+    /// Returns the address of the device
+    pub fn address(&self) -> &String {
+        &self.address
+    }
+
+    /// Returns the vendor code of the device
+    pub fn vendor_id(&self) -> u16 {
+        self.vendor_id
+    }
+
+    /// Returns the device code of the device
+    pub fn device_id(&self) -> u16 {
+        self.device_id
+    }
+
+    /// Returns the vendor code of the board the device is on
+    pub fn subsystem_vendor_id(&self) -> Option<u16> {
+        self.subsystem_vendor_id
+    }
+
+    /// Returns the device code of the board the device is on
+    pub fn subsystem_device_id(&self) -> Option<u16> {
+        self.subsystem_device_id
+    }
+
+    /// Returns the class code of the device
+    pub fn class_id(&self) -> u32 {
+        self.class_id
+    }
+
+    /// Returns the revision of the device
+    pub fn revision(&self) -> Option<u8> {
+        self.revision
+    }
+
+    /// Returns the name of the vendor
+    pub fn vendor(&self) -> Option<&String> {
+        self.vendor.as_ref()
+    }
+
+    /// Returns the name of the device
+    pub fn device(&self) -> Option<&String> {
+        self.device.as_ref()
+    }
+
+    /// Returns the name of the device's class
+    pub fn class(&self) -> Option<&String> {
+        self.class.as_ref()
+    }
+
+    /// Returns the name of the device's subclass
+    pub fn subclass(&self) -> Option<&String> {
+        self.subclass.as_ref()
+    }
+
+    /// Returns the kernel driver bound to the device
+    pub fn driver(&self) -> Option<&String> {
+        self.driver.as_ref()
+    }
+
+    /// Returns the NUMA node the device is attached to
+    pub fn numa_node(&self) -> Option<u32> {
+        self.numa_node
+    }
+
+    /// Returns the current link speed
+    pub fn link_speed(&self) -> Option<&String> {
+        self.link_speed.as_ref()
+    }
+
+    /// Returns the current number of lanes
+    pub fn link_width(&self) -> Option<u8> {
+        self.link_width
+    }
+
+    /// Returns the fastest link speed the device supports
+    pub fn max_link_speed(&self) -> Option<&String> {
+        self.max_link_speed.as_ref()
+    }
+
+    /// Returns the most lanes the device supports
+    pub fn max_link_width(&self) -> Option<u8> {
+        self.max_link_width
+    }
+}
+
+/// Loads all PCI devices on the system
+pub fn load_pci_devices() -> Result<Loaded<PciDevice>, HwInfoError> {
+    collect_pci_devices(Path::new("/"), PciIds::system())
+}
+
+/// Loads all PCI devices from the sysfs tree under `root`, naming them with
+/// the database installed there
+pub fn load_pci_devices_from(root: &Path) -> Result<Loaded<PciDevice>, HwInfoError> {
+    collect_pci_devices(root, &PciIds::load_from(root))
+}
+
+fn collect_pci_devices(root: &Path, ids: &PciIds) -> Result<Loaded<PciDevice>, HwInfoError> {
+    let mut devices = Vec::new();
+    let mut errors = Vec::new();
+    for entry in read_dir(&root.join("sys/bus/pci/devices"))?.flatten() {
+        match form_device(&entry.path(), &entry.file_name().to_string_lossy(), ids) {
+            Ok(device) => devices.push(device),
+            Err(e) => errors.push(e),
+        }
+    }
+    devices.sort_by(|a, b| a.address.cmp(&b.address));
+    Ok(Loaded::new(devices, errors))
+}
+
+fn form_device(path: &Path, address: &str, ids: &PciIds) -> Result<PciDevice, HwInfoError> {
+    let vendor_id = parse_hex_attr(&path.join("vendor"))?.unwrap_or(0) as u16;
+    let device_id = parse_hex_attr(&path.join("device"))?.unwrap_or(0) as u16;
+    let subsystem_vendor_id = parse_hex_attr(&path.join("subsystem_vendor"))?.map(|v| v as u16);
+    let subsystem_device_id = parse_hex_attr(&path.join("subsystem_device"))?.map(|v| v as u16);
+    let class_id = parse_hex_attr(&path.join("class"))?.unwrap_or(0);
+    let revision = parse_hex_attr(&path.join("revision"))?.map(|v| v as u8);
+
+    let class_code = (class_id >> 16) as u8;
+    let subclass_code = (class_id >> 8) as u8;
+
+    // The kernel reports -1 when the platform has no NUMA information.
+    let numa_node = parse_attr::<i32>(&path.join("numa_node"))?
+        .and_then(|n| u32::try_from(n).ok());
+
+    let driver = fs::read_link(path.join("driver"))
+        .ok()
+        .and_then(|d| d.file_name().map(|n| n.to_string_lossy().to_string()));
+
+    Ok(PciDevice {
+        address: address.to_string(),
+        vendor_id,
+        device_id,
+        subsystem_vendor_id,
+        subsystem_device_id,
+        class_id,
+        revision,
+        vendor: ids.vendor(vendor_id).map(String::from),
+        device: ids.device(vendor_id, device_id).map(String::from),
+        class: ids.class(class_code).map(String::from),
+        subclass: ids.subclass(class_code, subclass_code).map(String::from),
+        driver,
+        numa_node,
+        link_speed: read_link_speed(&path.join("current_link_speed")),
+        link_width: parse_attr::<u8>(&path.join("current_link_width")).ok().flatten().filter(|w| *w > 0),
+        max_link_speed: read_link_speed(&path.join("max_link_speed")),
+        max_link_width: parse_attr::<u8>(&path.join("max_link_width")).ok().flatten().filter(|w| *w > 0),
+    })
+}
+
+/// Parses a hex attribute such as `0x8086`.
+fn parse_hex_attr(path: &Path) -> Result<Option<u32>, HwInfoError> {
+    let value = match fs::read_to_string(path) {
+        Ok(value) => value,
+        Err(_) => return Ok(None),
+    };
+    let value = value.trim();
+    match u32::from_str_radix(value.trim_start_matches("0x"), 16) {
+        Ok(parsed) => Ok(Some(parsed)),
+        Err(_) => Err(HwInfoError::Parse { path: path.to_path_buf(), value: value.to_string() }),
+    }
+}
+
+/// Devices without a link, such as those on the root complex, report an
+/// unknown speed.
+fn read_link_speed(path: &Path) -> Option<String> {
+    let speed = fs::read_to_string(path).ok()?;
+    let speed = speed.trim();
+    match speed.starts_with("Unknown") || speed.is_empty() {
+        true => None,
+        false => Some(speed.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{error::HwInfoError, linux::sysfs::fixture};
+
+    use super::{load_pci_devices_from, PciIds};
+
+    #[test]
+    fn embedded_ids() {
+        let ids = PciIds::load_from(&fixture("missing"));
+        assert_eq!(ids.vendor(0x8086), Some("Intel Corporation"));
+        assert_eq!(ids.class(0x02), Some("Network controller"));
+        assert_eq!(ids.subclass(0x01, 0x08), Some("Non-Volatile memory controller"));
+        assert_eq!(ids.device(0x8086, 0x15b8), None);
+    }
+
+    #[test]
+    fn system_ids_name_devices() {
+        let (devices, errors) = load_pci_devices_from(&fixture("nvme-workstation")).unwrap().into_parts();
+        assert!(errors.is_empty());
+        let addresses: Vec<&str> = devices.iter().map(|d| d.address().as_str()).collect();
+        assert_eq!(addresses, vec!["0000:00:00.0", "0000:01:00.0", "0000:04:00.0", "0000:05:00.0", "0000:0a:00.0"]);
+
+        let host = &devices[0];
+        assert_eq!(host.subclass().map(|s| s.as_str()), Some("Host bridge"));
+        assert!(host.driver().is_none());
+        assert!(host.link_speed().is_none());
+
+        let nvme = &devices[1];
+        assert_eq!(nvme.vendor().map(|s| s.as_str()), Some("Samsung Electronics Co Ltd"));
+        assert_eq!(nvme.device().map(|s| s.as_str()), Some("NVMe SSD Controller PM9A1/PM9A3/980PRO"));
+        assert_eq!(nvme.subclass().map(|s| s.as_str()), Some("Non-Volatile memory controller"));
+        assert_eq!(nvme.driver().map(|s| s.as_str()), Some("nvme"));
+        assert_eq!(nvme.link_width(), Some(4));
+
+        let gpu = &devices[4];
+        assert_eq!(gpu.class().map(|s| s.as_str()), Some("Display controller"));
+        assert_eq!(gpu.link_speed().map(|s| s.as_str()), Some("2.5 GT/s PCIe"));
+        assert_eq!(gpu.max_link_speed().map(|s| s.as_str()), Some("16.0 GT/s PCIe"));
+        assert_eq!(gpu.numa_node(), None);
+    }
+
+    #[test]
+    fn embedded_ids_name_vendors() {
+        let (devices, errors) = load_pci_devices_from(&fixture("sata-server")).unwrap().into_parts();
+        assert!(errors.is_empty());
+        let hba = devices.iter().find(|d| d.address() == "0000:03:00.0").unwrap();
+        assert_eq!(hba.vendor().map(|s| s.as_str()), Some("Broadcom / LSI"));
+        assert_eq!(hba.subclass().map(|s| s.as_str()), Some("Serial Attached SCSI controller"));
+        assert_eq!(hba.device(), None);
+        assert_eq!(hba.numa_node(), Some(0));
+        assert_eq!(hba.driver().map(|s| s.as_str()), Some("mpt3sas"));
+    }
+
+    #[test]
+    fn corrupt_attribute_skips_the_device() {
+        let (devices, errors) = load_pci_devices_from(&fixture("corrupt")).unwrap().into_parts();
+        let addresses: Vec<&str> = devices.iter().map(|d| d.address().as_str()).collect();
+        assert_eq!(addresses, vec!["0000:00:1f.6"]);
+
+        match errors.as_slice() {
+            [HwInfoError::Parse { path, value }] => {
+                assert!(path.ends_with("0000:00:19.0/class"));
+                assert_eq!(value, "ethernet");
+            },
+            other => panic!("expected a parse error, got {:?}", other),
+        }
+    }
+}
@@ -0,0 +1,218 @@
+use std::{path::PathBuf, process::Command};
+
+use serde::Deserialize;
+
+use crate::error::HwInfoError;
+
+/// SMART attribute holding the count of sectors remapped to spares
+const ATA_REALLOCATED_SECTORS: u8 = 5;
+/// SMART attribute holding the count of sectors waiting to be remapped
+const ATA_PENDING_SECTORS: u8 = 197;
+/// smartctl exit status bits for a bad command line or a device that could
+/// not be opened. Other bits describe the disk and still come with output.
+const SMARTCTL_FATAL: u8 = 0b11;
+
+#[derive(Debug, Clone, PartialEq)]
+/// Represents the health of a disk as reported by SMART or the NVMe health log
+pub struct DiskHealth {
+    /// Whether the disk passed its overall health self-assessment
+    passed: Option<bool>,
+    /// The current temperature of the disk in Celsius
+    temperature: Option<i64>,
+    /// The number of hours the disk has been powered on
+    power_on_hours: Option<u64>,
+    /// The number of sectors remapped to spares, SATA only
+    reallocated_sectors: Option<u64>,
+    /// The number of sectors waiting to be remapped, SATA only
+    pending_sectors: Option<u64>,
+    /// The estimated percentage of the disk's endurance used, NVMe only
+    percentage_used: Option<u8>,
+    /// The number of unrecovered data integrity errors, NVMe only
+    media_errors: Option<u64>,
+}
+
+impl DiskHealth {
+    /// Returns whether the disk passed its overall health self-assessment
+    pub fn get_passed(&self) -> Option<bool> {
+        self.passed
+    }
+
+    /// Returns the current temperature of the disk in Celsius
+    pub fn get_temperature(&self) -> Option<i64> {
+        self.temperature
+    }
+
+    /// Returns the number of hours the disk has been powered on
+    pub fn get_power_on_hours(&self) -> Option<u64> {
+        self.power_on_hours
+    }
+
+    /// Returns the number of sectors remapped to spares
+    pub fn get_reallocated_sectors(&self) -> Option<u64> {
+        self.reallocated_sectors
+    }
+
+    /// Returns the number of sectors waiting to be remapped
+    pub fn get_pending_sectors(&self) -> Option<u64> {
+        self.pending_sectors
+    }
+
+    /// Returns the estimated percentage of the disk's endurance used
+    pub fn get_percentage_used(&self) -> Option<u8> {
+        self.percentage_used
+    }
+
+    /// Returns the number of unrecovered data integrity errors
+    pub fn get_media_errors(&self) -> Option<u64> {
+        self.media_errors
+    }
+}
+
+#[derive(Deserialize)]
+struct SmartctlOutput {
+    smartctl: SmartctlInfo,
+    smart_status: Option<SmartStatus>,
+    temperature: Option<Temperature>,
+    power_on_time: Option<PowerOnTime>,
+    ata_smart_attributes: Option<AtaAttributes>,
+    nvme_smart_health_information_log: Option<NvmeHealthLog>,
+}
+
+#[derive(Deserialize)]
+struct SmartctlInfo {
+    #[serde(default)]
+    exit_status: u8,
+    #[serde(default)]
+    messages: Vec<SmartctlMessage>,
+}
+
+#[derive(Deserialize)]
+struct SmartctlMessage {
+    string: String,
+}
+
+#[derive(Deserialize)]
+struct SmartStatus {
+    passed: bool,
+}
+
+#[derive(Deserialize)]
+struct Temperature {
+    current: i64,
+}
+
+#[derive(Deserialize)]
+struct PowerOnTime {
+    hours: u64,
+}
+
+#[derive(Deserialize)]
+struct AtaAttributes {
+    table: Vec<AtaAttribute>,
+}
+
+#[derive(Deserialize)]
+struct AtaAttribute {
+    id: u8,
+    raw: AtaRawValue,
+}
+
+#[derive(Deserialize)]
+struct AtaRawValue {
+    value: u64,
+}
+
+#[derive(Deserialize)]
+struct NvmeHealthLog {
+    percentage_used: u8,
+    media_errors: u64,
+}
+
+/// Reads the health of a disk, such as `sda` or `nvme0n1`, with smartctl.
+/// smartctl needs to be installed and usually needs root to open the device.
+pub fn read_disk_health(device: &str) -> Result<DiskHealth, HwInfoError> {
+    let output = Command::new("smartctl")
+        .args(["--json=c", "--all", &format!("/dev/{}", device)])
+        .output()
+        .map_err(|source| HwInfoError::Io { path: PathBuf::from("smartctl"), source })?;
+
+    parse_smartctl(&output.stdout)
+}
+
+/// Parses the output of `smartctl --json --all` for SATA and NVMe disks
+pub fn parse_smartctl(json: &[u8]) -> Result<DiskHealth, HwInfoError> {
+    let output: SmartctlOutput = serde_json::from_slice(json)
+        .map_err(|e| HwInfoError::Smartctl(format!("invalid output: {}", e)))?;
+
+    if output.smartctl.exit_status & SMARTCTL_FATAL != 0 {
+        let messages: Vec<String> = output.smartctl.messages.into_iter().map(|m| m.string).collect();
+        return Err(HwInfoError::Smartctl(messages.join("; ")));
+    }
+
+    let ata_attribute = |id: u8| {
+        output.ata_smart_attributes.as_ref()
+            .and_then(|a| a.table.iter().find(|attr| attr.id == id))
+            .map(|attr| attr.raw.value)
+    };
+
+    Ok(DiskHealth {
+        passed: output.smart_status.as_ref().map(|s| s.passed),
+        temperature: output.temperature.as_ref().map(|t| t.current),
+        power_on_hours: output.power_on_time.as_ref().map(|p| p.hours),
+        reallocated_sectors: ata_attribute(ATA_REALLOCATED_SECTORS),
+        pending_sectors: ata_attribute(ATA_PENDING_SECTORS),
+        percentage_used: output.nvme_smart_health_information_log.as_ref().map(|l| l.percentage_used),
+        media_errors: output.nvme_smart_health_information_log.as_ref().map(|l| l.media_errors),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use crate::{error::HwInfoError, linux::sysfs::fixture};
+
+    use super::parse_smartctl;
+
+    fn smartctl(name: &str) -> Vec<u8> {
+        fs::read(fixture("smartctl").join(name)).unwrap()
+    }
+
+    #[test]
+    fn sata_hdd() {
+        let health = parse_smartctl(&smartctl("sata-hdd.json")).unwrap();
+        assert_eq!(health.get_passed(), Some(true));
+        assert_eq!(health.get_temperature(), Some(34));
+        assert_eq!(health.get_power_on_hours(), Some(28411));
+        assert_eq!(health.get_reallocated_sectors(), Some(0));
+        assert_eq!(health.get_pending_sectors(), Some(0));
+        assert_eq!(health.get_percentage_used(), None);
+    }
+
+    #[test]
+    fn failing_hdd() {
+        let health = parse_smartctl(&smartctl("sata-hdd-failing.json")).unwrap();
+        assert_eq!(health.get_passed(), Some(false));
+        assert_eq!(health.get_reallocated_sectors(), Some(1432));
+        assert_eq!(health.get_pending_sectors(), Some(56));
+    }
+
+    #[test]
+    fn nvme() {
+        let health = parse_smartctl(&smartctl("nvme.json")).unwrap();
+        assert_eq!(health.get_passed(), Some(true));
+        assert_eq!(health.get_temperature(), Some(41));
+        assert_eq!(health.get_power_on_hours(), Some(9120));
+        assert_eq!(health.get_percentage_used(), Some(7));
+        assert_eq!(health.get_media_errors(), Some(0));
+        assert_eq!(health.get_reallocated_sectors(), None);
+    }
+
+    #[test]
+    fn open_failure_is_an_error() {
+        match parse_smartctl(&smartctl("permission-denied.json")) {
+            Err(HwInfoError::Smartctl(message)) => assert!(message.contains("Permission denied")),
+            other => panic!("expected a smartctl error, got {:?}", other),
+        }
+    }
+}
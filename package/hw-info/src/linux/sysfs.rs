@@ -0,0 +1,28 @@
+use std::{fs, path::Path, str::FromStr};
+
+use crate::error::HwInfoError;
+
+/// Lists a directory, failing with the path that could not be read.
+pub(crate) fn read_dir(path: &Path) -> Result<fs::ReadDir, HwInfoError> {
+    fs::read_dir(path).map_err(|source| HwInfoError::Io { path: path.to_path_buf(), source })
+}
+
+/// Parses a single value attribute. Attributes that are missing or cannot be
+/// read, as with the speed of a link that is down, are `None`.
+pub(crate) fn parse_attr<T: FromStr>(path: &Path) -> Result<Option<T>, HwInfoError> {
+    let value = match fs::read_to_string(path) {
+        Ok(value) => value,
+        Err(_) => return Ok(None),
+    };
+    let value = value.trim();
+    match value.parse::<T>() {
+        Ok(parsed) => Ok(Some(parsed)),
+        Err(_) => Err(HwInfoError::Parse { path: path.to_path_buf(), value: value.to_string() }),
+    }
+}
+
+/// Resolves a fixture tree checked in under `fixtures/`.
+#[cfg(test)]
+pub(crate) fn fixture(name: &str) -> std::path::PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("fixtures").join(name)
+}
@@ -0,0 +1,296 @@
+use std::{fs, path::Path};
+
+#[derive(Debug, Clone, PartialEq)]
+/// Represents a hypervisor a machine runs under or hosts
+pub enum Hypervisor {
+    KVM,
+    QEMU,
+    VMware,
+    HyperV,
+    Xen,
+    VirtualBox,
+    Parallels,
+    Bhyve,
+    Unknown(String),
+}
+
+impl Hypervisor {
+    pub fn name(&self) -> &str {
+        match self {
+            Hypervisor::KVM => "kvm",
+            Hypervisor::QEMU => "qemu",
+            Hypervisor::VMware => "vmware",
+            Hypervisor::HyperV => "hyperv",
+            Hypervisor::Xen => "xen",
+            Hypervisor::VirtualBox => "virtualbox",
+            Hypervisor::Parallels => "parallels",
+            Hypervisor::Bhyve => "bhyve",
+            Hypervisor::Unknown(name) => name,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+/// Represents a container runtime the machine is running inside of
+pub enum ContainerKind {
+    Docker,
+    Podman,
+    LXC,
+    SystemdNspawn,
+    Unknown(String),
+}
+
+impl ContainerKind {
+    pub fn name(&self) -> &str {
+        match self {
+            ContainerKind::Docker => "docker",
+            ContainerKind::Podman => "podman",
+            ContainerKind::LXC => "lxc",
+            ContainerKind::SystemdNspawn => "systemd-nspawn",
+            ContainerKind::Unknown(name) => name,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+/// Represents how the machine is virtualized
+pub struct Virtualization {
+    /// The hypervisor the machine runs under, when it is a virtual machine
+    guest_of: Option<Hypervisor>,
+    /// The container the machine's processes run inside of
+    container: Option<ContainerKind>,
+    /// The hypervisor the machine runs guests with, when it is a host
+    host_of: Option<Hypervisor>,
+    /// The number of guests running on the machine
+    guests: usize,
+}
+
+impl Virtualization {
+    /// Returns the hypervisor the machine runs under
+    pub fn guest_of(&self) -> Option<&Hypervisor> {
+        self.guest_of.as_ref()
+    }
+
+    /// Returns the container the machine's processes run inside of
+    pub fn container(&self) -> Option<&ContainerKind> {
+        self.container.as_ref()
+    }
+
+    /// Returns the hypervisor the machine runs guests with
+    pub fn host_of(&self) -> Option<&Hypervisor> {
+        self.host_of.as_ref()
+    }
+
+    /// Returns the number of guests running on the machine
+    pub fn guests(&self) -> usize {
+        self.guests
+    }
+
+    pub fn is_virtual_machine(&self) -> bool {
+        self.guest_of.is_some()
+    }
+
+    pub fn is_hypervisor(&self) -> bool {
+        self.host_of.is_some()
+    }
+}
+
+/// Detects whether the machine is a virtual machine, runs in a container or
+/// hosts guests of its own.
+pub fn detect_virtualization() -> Virtualization {
+    detect_virtualization_from(Path::new("/"))
+}
+
+/// Detects virtualization from the sysfs and procfs trees under `root`
+pub fn detect_virtualization_from(root: &Path) -> Virtualization {
+    let xen_control = read(root, "proc/xen/capabilities").contains("control_d");
+    let guest_of = match xen_control {
+        // dom0 looks like a Xen guest but is the host.
+        true => None,
+        false => dmi_hypervisor(root).or_else(|| sys_hypervisor(root)).or_else(|| cpuid_hypervisor(root)),
+    };
+
+    let processes = process_names(root);
+    let host_of = if xen_control {
+        Some(Hypervisor::Xen)
+    } else if can_host_kvm(root, &processes) {
+        Some(Hypervisor::KVM)
+    } else {
+        None
+    };
+
+    Virtualization {
+        guest_of,
+        container: detect_container(root),
+        host_of,
+        guests: processes.iter().filter(|p| is_kvm_guest(p)).count(),
+    }
+}
+
+fn read(root: &Path, path: &str) -> String {
+    match fs::read_to_string(root.join(path)) {
+        Ok(content) => content.trim().to_string(),
+        Err(_) => "".to_string(),
+    }
+}
+
+fn dmi_hypervisor(root: &Path) -> Option<Hypervisor> {
+    let fields = ["sys_vendor", "product_name", "bios_vendor", "board_vendor"];
+    let dmi: Vec<String> = fields.iter().map(|f| read(root, &format!("sys/class/dmi/id/{}", f))).collect();
+
+    for value in dmi.iter() {
+        let hypervisor = match value.as_str() {
+            v if v.contains("QEMU") => Hypervisor::QEMU,
+            v if v.contains("KVM") || v.contains("Amazon EC2") || v.contains("Google Compute Engine") => Hypervisor::KVM,
+            v if v.contains("VMware") => Hypervisor::VMware,
+            v if v.contains("innotek GmbH") || v.contains("VirtualBox") => Hypervisor::VirtualBox,
+            v if v.contains("Xen") => Hypervisor::Xen,
+            v if v.contains("Parallels") => Hypervisor::Parallels,
+            v if v.contains("BHYVE") => Hypervisor::Bhyve,
+            _ => continue,
+        };
+        return Some(hypervisor);
+    }
+
+    // Hyper-V guests report Microsoft as the vendor, as do physical Surface devices.
+    if dmi[0].contains("Microsoft Corporation") && dmi[1].contains("Virtual Machine") {
+        return Some(Hypervisor::HyperV);
+    }
+    None
+}
+
+fn sys_hypervisor(root: &Path) -> Option<Hypervisor> {
+    match read(root, "sys/hypervisor/type").as_str() {
+        "" => None,
+        "xen" => Some(Hypervisor::Xen),
+        other => Some(Hypervisor::Unknown(other.to_string())),
+    }
+}
+
+/// The CPUID hypervisor bit shows up as a flag in /proc/cpuinfo.
+fn cpuid_hypervisor(root: &Path) -> Option<Hypervisor> {
+    let cpuinfo = read(root, "proc/cpuinfo");
+    let flagged = cpuinfo.lines()
+        .filter(|l| l.starts_with("flags"))
+        .any(|l| l.split_whitespace().any(|f| f == "hypervisor"));
+    match flagged {
+        true => Some(Hypervisor::Unknown("unknown".to_string())),
+        false => None,
+    }
+}
+
+fn detect_container(root: &Path) -> Option<ContainerKind> {
+    if root.join(".dockerenv").exists() {
+        return Some(ContainerKind::Docker);
+    }
+    if root.join("run/.containerenv").exists() {
+        return Some(ContainerKind::Podman);
+    }
+
+    let marker = read(root, "run/systemd/container");
+    let marker = match marker.is_empty() {
+        true => fs::read(root.join("proc/1/environ"))
+            .map(|env| {
+                env.split(|b| *b == 0)
+                    .filter_map(|v| std::str::from_utf8(v).ok())
+                    .find_map(|v| v.strip_prefix("container=").map(String::from))
+                    .unwrap_or_default()
+            })
+            .unwrap_or_default(),
+        false => marker,
+    };
+    match marker.as_str() {
+        "" => None,
+        "docker" => Some(ContainerKind::Docker),
+        "podman" => Some(ContainerKind::Podman),
+        "lxc" | "lxc-libvirt" => Some(ContainerKind::LXC),
+        "systemd-nspawn" => Some(ContainerKind::SystemdNspawn),
+        other => Some(ContainerKind::Unknown(other.to_string())),
+    }
+}
+
+/// A machine hosts KVM guests when /dev/kvm is present and guests or libvirt
+/// are running. The kvm module alone isn't enough, as it autoloads on any
+/// machine with hardware virtualization, nested cloud VMs included.
+fn can_host_kvm(root: &Path, processes: &[String]) -> bool {
+    root.join("dev/kvm").exists()
+        && processes.iter().any(|p| is_kvm_guest(p) || p == "libvirtd" || p == "virtqemud")
+}
+
+fn is_kvm_guest(process: &str) -> bool {
+    process.starts_with("qemu-system") || process == "qemu-kvm"
+}
+
+/// Names of running processes, read from /proc/<pid>/comm.
+fn process_names(root: &Path) -> Vec<String> {
+    let procs = match fs::read_dir(root.join("proc")) {
+        Ok(p) => p,
+        Err(_) => return Vec::new(),
+    };
+
+    procs.filter_map(|p| p.ok())
+        .filter(|p| p.file_name().to_str().is_some_and(|n| n.chars().all(|c| c.is_ascii_digit())))
+        .filter_map(|p| fs::read_to_string(p.path().join("comm")).ok())
+        .map(|comm| comm.trim().to_string())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::linux::sysfs::fixture;
+
+    use super::*;
+
+    #[test]
+    fn detects_kvm_host() {
+        let virt = detect_virtualization_from(&fixture("kvm-host"));
+        assert!(!virt.is_virtual_machine());
+        assert_eq!(virt.host_of(), Some(&Hypervisor::KVM));
+        assert_eq!(virt.guests(), 1);
+        assert_eq!(virt.container(), Some(&ContainerKind::Docker));
+    }
+
+    #[test]
+    fn libvirt_host_without_guests_is_host() {
+        let virt = detect_virtualization_from(&fixture("libvirt-host"));
+        assert_eq!(virt.host_of(), Some(&Hypervisor::KVM));
+        assert_eq!(virt.guests(), 0);
+    }
+
+    #[test]
+    fn xen_dom0_is_host() {
+        let virt = detect_virtualization_from(&fixture("xen-dom0"));
+        assert!(!virt.is_virtual_machine());
+        assert_eq!(virt.host_of(), Some(&Hypervisor::Xen));
+    }
+
+    #[test]
+    fn qemu_fixture() {
+        let virt = detect_virtualization_from(&fixture("qemu-vm"));
+        assert_eq!(virt.guest_of(), Some(&Hypervisor::QEMU));
+        assert!(!virt.is_hypervisor());
+        assert!(virt.container().is_none());
+        assert_eq!(virt.guests(), 0);
+    }
+
+    #[test]
+    fn bare_metal_is_neither_guest_nor_host() {
+        let virt = detect_virtualization_from(&fixture("sata-server"));
+        assert!(!virt.is_virtual_machine());
+        assert!(!virt.is_hypervisor());
+    }
+
+    #[test]
+    fn loaded_kvm_module_alone_is_not_host() {
+        let virt = detect_virtualization_from(&fixture("kvm-idle"));
+        assert!(!virt.is_virtual_machine());
+        assert!(!virt.is_hypervisor());
+    }
+
+    #[test]
+    fn nested_cloud_vm_is_guest_not_host() {
+        let virt = detect_virtualization_from(&fixture("kvm-nested-vm"));
+        assert_eq!(virt.guest_of(), Some(&Hypervisor::KVM));
+        assert!(!virt.is_hypervisor());
+    }
+}
@@ -1,6 +1,7 @@
 use crate::{machine_summary, PrismaClient};
 
 machine_summary::include!(machine_full_summary {
+    endpoint
     status
     tags
     system
@@ -11,5 +12,10 @@ machine_summary::include!(machine_full_summary {
     network_interfaces
     addresses
     containers
+    images
+    container_volumes
+    container_networks
+    container_disk_usage
+    stacks
 });
 pub type MachineSummaryFull = machine_full_summary::Data;
@@ -0,0 +1,48 @@
+use std::{env, process};
+
+use smithy_common::auth::key::{generate_key, hash_key};
+
+const USAGE: &str = "Usage: keygen [--read-only] <name> [expires (unix seconds)] [operation...]";
+const READ_ONLY_FLAG: &str = "--read-only";
+
+/// Generates an API key and prints a config entry holding its hash. The
+/// plaintext key is only shown once.
+fn main() {
+    let mut args: Vec<String> = env::args().skip(1).collect();
+    let read_only = args.iter().any(|a| a == READ_ONLY_FLAG);
+    args.retain(|a| a != READ_ONLY_FLAG);
+
+    let name = match args.first() {
+        Some(n) => n,
+        None => {
+            eprintln!("{}", USAGE);
+            process::exit(1);
+        }
+    };
+
+    let expires = match args.get(1).map(|e| e.parse::<u64>()) {
+        None => None,
+        Some(Ok(e)) => Some(e),
+        Some(Err(_)) => {
+            eprintln!("{}", USAGE);
+            process::exit(1);
+        }
+    };
+
+    let key = generate_key();
+
+    println!("# key: {}", key);
+    println!("[[server.keys]]");
+    println!("name = \"{}\"", name);
+    println!("hash = \"{}\"", hash_key(&key));
+    if let Some(e) = expires {
+        println!("expires = {}", e);
+    }
+    if read_only {
+        println!("read_only = true");
+    }
+    if args.len() > 2 {
+        let ops: Vec<String> = args[2..].iter().map(|o| format!("\"{}\"", o)).collect();
+        println!("operations = [{}]", ops.join(", "));
+    }
+}
@@ -1,58 +1,296 @@
+use std::{
+    net::IpAddr,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+use aws_smithy_http_server::shape_id::ShapeId;
 use http::HeaderValue;
-use log::debug;
+use log::{debug, info};
+use serde::Deserialize;
+use subtle::ConstantTimeEq;
+
+use super::{
+    key::{digest, parse_hash},
+    limiter::FailureLimiter,
+};
+
+const SCHEMES: [&str; 2] = ["Bearer", "ApiKey"];
+const MAX_FAILURES: u32 = 10;
+const FAILURE_WINDOW: Duration = Duration::from_secs(60);
+
+/// An API key as stored in configuration. Only the salted hash of the key is
+/// kept; see [`super::key::hash_key`].
+#[derive(Clone, Debug, Deserialize)]
+pub struct ApiKey {
+    name: String,
+    hash: String,
+    /// Unix timestamp in seconds after which the key is rejected.
+    expires: Option<u64>,
+    /// Operation names (`GetOverview`) or absolute shape IDs
+    /// (`awlsring.geth.agent#GetOverview`) this key may invoke. All
+    /// operations are allowed when unset.
+    operations: Option<Vec<String>>,
+    /// Read-only keys are refused the server's write operations, whatever
+    /// `operations` allows.
+    #[serde(default)]
+    read_only: bool,
+}
+
+impl ApiKey {
+    pub fn new(name: &str, hash: &str, expires: Option<u64>, operations: Option<Vec<String>>) -> Self {
+        ApiKey {
+            name: name.to_string(),
+            hash: hash.to_string(),
+            expires,
+            operations,
+            read_only: false,
+        }
+    }
+
+    pub fn with_read_only(mut self, read_only: bool) -> Self {
+        self.read_only = read_only;
+        self
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn matches(&self, presented: &str) -> bool {
+        match parse_hash(&self.hash) {
+            Some((salt, expected)) => digest(salt, presented).as_bytes().ct_eq(expected.as_bytes()).into(),
+            None => false,
+        }
+    }
+
+    fn is_expired(&self, now: u64) -> bool {
+        matches!(self.expires, Some(expires) if now >= expires)
+    }
+
+    fn allows(&self, operation: &ShapeId, write: bool) -> bool {
+        if self.read_only && write {
+            return false;
+        }
+        match &self.operations {
+            None => true,
+            Some(ops) => ops.iter().any(|o| o == operation.name() || o == operation.absolute()),
+        }
+    }
+}
+
+/// Why a request was refused. Reported to the client in the
+/// `UnauthorizedException` reason.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AuthFailure {
+    Missing,
+    Malformed,
+    Unknown,
+    Expired,
+    Forbidden,
+}
+
+impl AuthFailure {
+    pub fn reason(&self) -> &'static str {
+        match self {
+            AuthFailure::Missing => "MISSING",
+            AuthFailure::Malformed => "MALFORMED",
+            AuthFailure::Unknown => "UNKNOWN",
+            AuthFailure::Expired => "EXPIRED",
+            AuthFailure::Forbidden => "FORBIDDEN",
+        }
+    }
+
+    pub fn message(&self) -> &'static str {
+        match self {
+            AuthFailure::Missing => "No API key was provided",
+            AuthFailure::Malformed => "Authorization header must be of the form 'Bearer <key>' or 'ApiKey <key>'",
+            AuthFailure::Unknown => "API key is not recognized",
+            AuthFailure::Expired => "API key has expired",
+            AuthFailure::Forbidden => "API key is not allowed to invoke this operation",
+        }
+    }
+}
 
-#[derive(Clone, Debug)]
+#[derive(Debug)]
 pub struct AuthController {
-    allowed_keys: Vec<String>,
+    keys: Vec<ApiKey>,
     no_auth_operations: Vec<String>,
+    write_operations: Vec<String>,
+    limiter: FailureLimiter,
 }
 
 impl AuthController {
-    pub fn new(no_auth_operations: &Vec<String>, allowed_keys: &Vec<String>) -> Self {
+    pub fn new(no_auth_operations: &Vec<String>, keys: &Vec<ApiKey>) -> Self {
         AuthController {
-            allowed_keys: allowed_keys.clone(),
+            keys: keys.clone(),
             no_auth_operations: no_auth_operations.clone(),
+            write_operations: Vec::new(),
+            limiter: FailureLimiter::new(MAX_FAILURES, FAILURE_WINDOW),
         }
     }
 
-    pub async fn auth(&self, operation: &str, key: Option<&HeaderValue>) -> bool {
-        debug!("Determining auth for operation: {}", operation);
-        if self.no_auth_operations.contains(&operation.to_string()) {
-            debug!("Operation {} does not require auth", operation);
-            return true;
+    /// Names the operations that change state, which read-only keys may not
+    /// invoke.
+    pub fn with_write_operations(mut self, operations: &[&str]) -> Self {
+        self.write_operations = operations.iter().map(|o| o.to_string()).collect();
+        self
+    }
+
+    fn is_write(&self, operation: &ShapeId) -> bool {
+        self.write_operations.iter().any(|o| o == operation.name())
+    }
+
+    fn requires_auth(&self, operation: &ShapeId) -> bool {
+        !self.no_auth_operations.iter().any(|o| o == operation.name())
+    }
+
+    /// Whether the peer has failed authentication too often recently and
+    /// should be turned away before its key is checked.
+    pub fn is_throttled(&self, operation: &ShapeId, peer: Option<IpAddr>) -> bool {
+        match peer {
+            Some(ip) => self.requires_auth(operation) && self.limiter.is_blocked(ip, Instant::now()),
+            None => false,
         }
+    }
 
-        if let Some(auth_header) = key {
-            let key = extract_api_key(auth_header);
+    pub async fn auth(&self, operation: &ShapeId, peer: Option<IpAddr>, header: Option<&HeaderValue>) -> Result<(), AuthFailure> {
+        debug!("Determining auth for operation: {}", operation.name());
+        if !self.requires_auth(operation) {
+            debug!("Operation {} does not require auth", operation.name());
+            return Ok(());
+        }
 
-            match key {
-                Some(key) => {
-                    if self.allowed_keys.contains(&key.to_string()) {
-                        debug!("Key is in allowlist");
-                        return true;
-                    }
-                },
-                None => {
-                    debug!("No key found in header");
-                },
+        let result = self.check(operation, header);
+        match &result {
+            Ok(name) => {
+                info!(target: "audit", "operation={} key={} peer={} allowed=true", operation.name(), name, display_peer(peer));
+            }
+            Err(failure) => {
+                info!(target: "audit", "operation={} key=<{}> peer={} allowed=false", operation.name(), failure.reason().to_lowercase(), display_peer(peer));
+                if let Some(ip) = peer {
+                    self.limiter.record_failure(ip, Instant::now());
+                }
             }
         }
 
-        debug!("authorization denied");
-        false
+        result.map(|_| ())
     }
-}
 
-fn extract_api_key(header: &HeaderValue) -> Option<String> {
-    let key = header.to_str().unwrap_or("none");
-    match key {
-        "none" => None,
-        _ => {
-            let parts = key.split(" ").collect::<Vec<&str>>();
-            match parts.len() {
-                2 => Some(parts[1].to_string()),
-                _ => None,
+    fn check(&self, operation: &ShapeId, header: Option<&HeaderValue>) -> Result<&str, AuthFailure> {
+        let presented = extract_api_key(header)?;
+
+        let key = self.find_key(presented).ok_or(AuthFailure::Unknown)?;
+        if key.is_expired(now()) {
+            return Err(AuthFailure::Expired);
+        }
+        if !key.allows(operation, self.is_write(operation)) {
+            return Err(AuthFailure::Forbidden);
+        }
+
+        Ok(key.name())
+    }
+
+    fn find_key(&self, presented: &str) -> Option<&ApiKey> {
+        // Check every key so timing doesn't reveal where a match was found.
+        let mut found = None;
+        for key in self.keys.iter() {
+            if key.matches(presented) && found.is_none() {
+                found = Some(key);
             }
-        },
+        }
+        found
+    }
+}
+
+fn display_peer(peer: Option<IpAddr>) -> String {
+    peer.map(|p| p.to_string()).unwrap_or_else(|| String::from("unknown"))
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+/// Pulls the key out of an `Authorization` header using either the `Bearer`
+/// or `ApiKey` scheme declared in the models.
+fn extract_api_key(header: Option<&HeaderValue>) -> Result<&str, AuthFailure> {
+    let header = header.ok_or(AuthFailure::Missing)?;
+    let value = header.to_str().map_err(|_| AuthFailure::Malformed)?;
+
+    let (scheme, key) = value.split_once(' ').ok_or(AuthFailure::Malformed)?;
+    let known_scheme = SCHEMES.iter().any(|s| s.eq_ignore_ascii_case(scheme));
+    if !known_scheme || key.is_empty() || key.contains(char::is_whitespace) {
+        return Err(AuthFailure::Malformed);
     }
-}
\ No newline at end of file
+
+    Ok(key)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::auth::key::hash_key;
+
+    const OP: ShapeId = ShapeId::new("awlsring.geth.agent#GetOverview", "awlsring.geth.agent", "GetOverview");
+    const LOGS: ShapeId = ShapeId::new("awlsring.geth.agent#StreamContainerLogs", "awlsring.geth.agent", "StreamContainerLogs");
+
+    #[test]
+    fn matches_only_the_original_key() {
+        let key = ApiKey::new("test", &hash_key("toes"), None, None);
+
+        assert!(key.matches("toes"));
+        assert!(!key.matches("toe"));
+    }
+
+    #[test]
+    fn expired_keys_are_rejected() {
+        let key = ApiKey::new("test", &hash_key("toes"), Some(100), None);
+
+        assert!(!key.is_expired(99));
+        assert!(key.is_expired(100));
+    }
+
+    #[test]
+    fn extracts_keys_from_both_schemes() {
+        let bearer = HeaderValue::from_static("Bearer toes");
+        let api_key = HeaderValue::from_static("ApiKey toes");
+
+        assert_eq!(extract_api_key(Some(&bearer)), Ok("toes"));
+        assert_eq!(extract_api_key(Some(&api_key)), Ok("toes"));
+    }
+
+    #[test]
+    fn rejects_malformed_headers() {
+        let missing = extract_api_key(None);
+        let no_scheme = HeaderValue::from_static("toes");
+        let wrong_scheme = HeaderValue::from_static("Basic toes");
+        let extra_space = HeaderValue::from_static("Bearer  toes");
+        let non_ascii = HeaderValue::from_bytes(b"Bearer t\xf6es").unwrap();
+
+        assert_eq!(missing, Err(AuthFailure::Missing));
+        assert_eq!(extract_api_key(Some(&no_scheme)), Err(AuthFailure::Malformed));
+        assert_eq!(extract_api_key(Some(&wrong_scheme)), Err(AuthFailure::Malformed));
+        assert_eq!(extract_api_key(Some(&extra_space)), Err(AuthFailure::Malformed));
+        assert_eq!(extract_api_key(Some(&non_ascii)), Err(AuthFailure::Malformed));
+    }
+
+    #[test]
+    fn scoped_keys_only_allow_listed_operations() {
+        let key = ApiKey::new("read-only", &hash_key("toes"), None, Some(vec!["GetOverview".to_string()]));
+
+        assert!(key.allows(&OP, false));
+        assert!(!key.allows(&LOGS, false));
+    }
+
+    #[test]
+    fn read_only_keys_are_refused_write_operations() {
+        let key = ApiKey::new("viewer", &hash_key("toes"), None, None).with_read_only(true);
+        let controller = AuthController::new(&vec![], &vec![key]).with_write_operations(&["StreamContainerLogs"]);
+        let header = HeaderValue::from_static("Bearer toes");
+
+        assert_eq!(controller.check(&OP, Some(&header)), Ok("viewer"));
+        assert_eq!(controller.check(&LOGS, Some(&header)), Err(AuthFailure::Forbidden));
+    }
+}
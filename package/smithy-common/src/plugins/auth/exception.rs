@@ -1,27 +1,99 @@
-use aws_smithy_http_server::{body::BoxBody, response::{IntoResponse}, proto::{aws_json_10::AwsJson1_0, aws_json_11::AwsJson1_1, aws_json::{runtime_error::RuntimeError}, rest_xml::{RestXml, runtime_error::RuntimeError as XmlError}, rest_json_1::{RestJson1, runtime_error::RuntimeError as RestError}}};
+use aws_smithy_http_server::{body::{to_boxed, BoxBody}, response::IntoResponse, proto::{aws_json_10::AwsJson1_0, aws_json_11::AwsJson1_1, rest_xml::RestXml, rest_json_1::RestJson1}};
+use http::{header::CONTENT_TYPE, StatusCode};
 
-pub struct UnauthorizedException;
+use super::controller::AuthFailure;
+
+const UNAUTHORIZED: &str = "UnauthorizedException";
+const THROTTLING: &str = "ThrottlingException";
+const THROTTLING_MESSAGE: &str = "Too many failed authentication attempts, try again later";
+
+/// The modeled `awlsring.geth.common#UnauthorizedException`.
+pub struct UnauthorizedException {
+    failure: AuthFailure,
+}
+
+impl UnauthorizedException {
+    pub fn new(failure: AuthFailure) -> Self {
+        UnauthorizedException { failure }
+    }
+
+    fn json_fields(&self) -> String {
+        format!(r#""message":"{}","reason":"{}""#, self.failure.message(), self.failure.reason())
+    }
+}
+
+/// The modeled `awlsring.geth.common#ThrottlingException`, returned to peers
+/// that have failed authentication too many times.
+pub struct ThrottlingException;
+
+fn error_response(status: StatusCode, content_type: &str, error_type: Option<&str>, body: String) -> http::Response<BoxBody> {
+    let mut builder = http::Response::builder()
+        .status(status)
+        .header(CONTENT_TYPE, content_type);
+
+    if let Some(t) = error_type {
+        builder = builder.header("X-Amzn-Errortype", t);
+    }
+
+    builder
+        .body(to_boxed(body))
+        .expect("static error response is valid")
+}
+
+fn aws_json_body(error_type: &str, fields: &str) -> String {
+    format!(r#"{{"__type":"{}",{}}}"#, error_type, fields)
+}
+
+fn xml_body(error_type: &str, message: &str) -> String {
+    format!("<ErrorResponse><Error><Type>Sender</Type><Code>{}</Code><Message>{}</Message></Error></ErrorResponse>", error_type, message)
+}
 
 impl IntoResponse<AwsJson1_0> for UnauthorizedException {
     fn into_response(self) -> http::Response<BoxBody> {
-        IntoResponse::<AwsJson1_0>::into_response(RuntimeError::NotAcceptable)
+        error_response(StatusCode::UNAUTHORIZED, "application/x-amz-json-1.0", None, aws_json_body(UNAUTHORIZED, &self.json_fields()))
     }
 }
 
 impl IntoResponse<AwsJson1_1> for UnauthorizedException {
     fn into_response(self) -> http::Response<BoxBody> {
-        IntoResponse::<AwsJson1_1>::into_response(RuntimeError::NotAcceptable)
+        error_response(StatusCode::UNAUTHORIZED, "application/x-amz-json-1.1", None, aws_json_body(UNAUTHORIZED, &self.json_fields()))
     }
 }
 
 impl IntoResponse<RestXml> for UnauthorizedException {
     fn into_response(self) -> http::Response<BoxBody> {
-        IntoResponse::<RestXml>::into_response(XmlError::NotAcceptable)
+        error_response(StatusCode::UNAUTHORIZED, "application/xml", None, xml_body(UNAUTHORIZED, self.failure.message()))
     }
 }
 
 impl IntoResponse<RestJson1> for UnauthorizedException {
     fn into_response(self) -> http::Response<BoxBody> {
-        IntoResponse::<RestJson1>::into_response(RestError::NotAcceptable)
+        error_response(StatusCode::UNAUTHORIZED, "application/json", Some(UNAUTHORIZED), format!("{{{}}}", self.json_fields()))
     }
-}
\ No newline at end of file
+}
+
+impl IntoResponse<AwsJson1_0> for ThrottlingException {
+    fn into_response(self) -> http::Response<BoxBody> {
+        let fields = format!(r#""message":"{}""#, THROTTLING_MESSAGE);
+        error_response(StatusCode::TOO_MANY_REQUESTS, "application/x-amz-json-1.0", None, aws_json_body(THROTTLING, &fields))
+    }
+}
+
+impl IntoResponse<AwsJson1_1> for ThrottlingException {
+    fn into_response(self) -> http::Response<BoxBody> {
+        let fields = format!(r#""message":"{}""#, THROTTLING_MESSAGE);
+        error_response(StatusCode::TOO_MANY_REQUESTS, "application/x-amz-json-1.1", None, aws_json_body(THROTTLING, &fields))
+    }
+}
+
+impl IntoResponse<RestXml> for ThrottlingException {
+    fn into_response(self) -> http::Response<BoxBody> {
+        error_response(StatusCode::TOO_MANY_REQUESTS, "application/xml", None, xml_body(THROTTLING, THROTTLING_MESSAGE))
+    }
+}
+
+impl IntoResponse<RestJson1> for ThrottlingException {
+    fn into_response(self) -> http::Response<BoxBody> {
+        error_response(StatusCode::TOO_MANY_REQUESTS, "application/json", Some(THROTTLING), format!(r#"{{"message":"{}"}}"#, THROTTLING_MESSAGE))
+    }
+}
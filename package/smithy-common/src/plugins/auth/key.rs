@@ -0,0 +1,45 @@
+use rand::{rngs::OsRng, RngCore};
+use sha2::{Digest, Sha256};
+
+const SCHEME: &str = "sha256";
+const SALT_LEN: usize = 16;
+const KEY_LEN: usize = 32;
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn random_hex(len: usize) -> String {
+    let mut bytes = vec![0u8; len];
+    OsRng.fill_bytes(&mut bytes);
+    to_hex(&bytes)
+}
+
+pub(crate) fn digest(salt: &str, key: &str) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(salt.as_bytes());
+    hasher.update(key.as_bytes());
+    to_hex(&hasher.finalize())
+}
+
+/// Splits a stored hash of the form `sha256$<salt>$<digest>` into its salt
+/// and digest.
+pub(crate) fn parse_hash(hash: &str) -> Option<(&str, &str)> {
+    let mut parts = hash.split('$');
+    match (parts.next(), parts.next(), parts.next(), parts.next()) {
+        (Some(SCHEME), Some(salt), Some(digest), None) => Some((salt, digest)),
+        _ => None,
+    }
+}
+
+/// Hashes a plaintext key with a fresh random salt, producing the value to
+/// store in an [`super::controller::ApiKey`].
+pub fn hash_key(key: &str) -> String {
+    let salt = random_hex(SALT_LEN);
+    format!("{}${}${}", SCHEME, salt, digest(&salt, key))
+}
+
+/// Generates a new random plaintext key.
+pub fn generate_key() -> String {
+    random_hex(KEY_LEN)
+}
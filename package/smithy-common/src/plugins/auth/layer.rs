@@ -1,12 +1,13 @@
 use std::{marker::PhantomData, sync::Arc};
 
+use aws_smithy_http_server::shape_id::ShapeId;
 use tower::Layer;
 
 use super::{service::AuthService, controller::AuthController};
 
 pub struct AuthLayer<Protocol, Operation, Config> {
     controller: Arc<AuthController>,
-    operation: &'static str,
+    operation: ShapeId,
     config: Config,
     _protocol: PhantomData<Protocol>,
     _operation: PhantomData<Operation>,
@@ -21,7 +22,7 @@ where
     fn layer(&self, service: Service) -> Self::Service {
         AuthService::new(
             service,
-            self.operation,
+            self.operation.clone(),
             self.controller.clone(),
             self.config.clone(),
         )
@@ -0,0 +1,68 @@
+use std::{
+    collections::HashMap,
+    net::IpAddr,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+#[derive(Debug)]
+struct Failures {
+    count: u32,
+    since: Instant,
+}
+
+/// Counts authentication failures per peer address within a fixed window. A
+/// peer that reaches the limit is refused until its window runs out.
+#[derive(Debug)]
+pub struct FailureLimiter {
+    max_failures: u32,
+    window: Duration,
+    peers: Mutex<HashMap<IpAddr, Failures>>,
+}
+
+impl FailureLimiter {
+    pub fn new(max_failures: u32, window: Duration) -> Self {
+        FailureLimiter {
+            max_failures: max_failures.max(1),
+            window,
+            peers: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn is_blocked(&self, peer: IpAddr, now: Instant) -> bool {
+        let peers = self.peers.lock().unwrap_or_else(|e| e.into_inner());
+        match peers.get(&peer) {
+            Some(f) => f.count >= self.max_failures && now.duration_since(f.since) < self.window,
+            None => false,
+        }
+    }
+
+    pub fn record_failure(&self, peer: IpAddr, now: Instant) {
+        let mut peers = self.peers.lock().unwrap_or_else(|e| e.into_inner());
+        peers.retain(|_, f| now.duration_since(f.since) < self.window);
+
+        let failures = peers.entry(peer).or_insert(Failures { count: 0, since: now });
+        failures.count = failures.count.saturating_add(1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn blocks_after_limit_until_window_passes() {
+        let limiter = FailureLimiter::new(2, Duration::from_secs(60));
+        let peer: IpAddr = "10.0.0.1".parse().unwrap();
+        let start = Instant::now();
+
+        limiter.record_failure(peer, start);
+        assert!(!limiter.is_blocked(peer, start));
+
+        limiter.record_failure(peer, start);
+        assert!(limiter.is_blocked(peer, start));
+        assert!(!limiter.is_blocked("10.0.0.2".parse().unwrap(), start));
+
+        assert!(!limiter.is_blocked(peer, start + Duration::from_secs(61)));
+    }
+}
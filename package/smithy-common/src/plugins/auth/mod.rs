@@ -3,4 +3,6 @@ pub mod service;
 pub mod layer;
 pub mod config;
 pub mod exception;
-pub mod controller;
\ No newline at end of file
+pub mod controller;
+pub mod key;
+pub mod limiter;
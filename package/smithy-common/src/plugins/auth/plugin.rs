@@ -34,7 +34,7 @@ Op: OperationShape,
     type Service = AuthService<Protocol, Op, Svc, Config>;
 
     fn apply(&self, inner: Svc) -> Self::Service {
-        AuthService::new(inner, Op::ID.name(), self.controller.clone(), self.config.clone())
+        AuthService::new(inner, Op::ID, self.controller.clone(), self.config.clone())
     }
 }
 
@@ -1,22 +1,24 @@
 use std::{marker::PhantomData, task::{Context, Poll}, sync::Arc};
 
-use aws_smithy_http_server::{body::BoxBody, response::{Response, IntoResponse}};
+use std::net::SocketAddr;
+
+use aws_smithy_http_server::{body::BoxBody, request::connect_info::ConnectInfo, response::{Response, IntoResponse}, shape_id::ShapeId};
 use http_body::{Body as HttpBody};
 use tower::Service;
 
-use super::{exception::UnauthorizedException, controller::AuthController};
+use super::{exception::{ThrottlingException, UnauthorizedException}, controller::AuthController};
 
 pub struct AuthService<Protocol, Operation, Service, Config> {
     inner: Service,
     controller: Arc<AuthController>,
-    operation: &'static str,
+    operation: ShapeId,
     config: Config,
     _protocol: PhantomData<Protocol>,
     _operation: PhantomData<Operation>,
 }
 
 impl <Protocol, Operation, Service, Config> AuthService<Protocol, Operation, Service, Config> {
-    pub fn new(inner: Service, operation: &'static str, auth: Arc<AuthController>, config: Config) -> AuthService<Protocol, Operation, Service, Config> {
+    pub fn new(inner: Service, operation: ShapeId, auth: Arc<AuthController>, config: Config) -> AuthService<Protocol, Operation, Service, Config> {
         AuthService {
             inner,
             controller: auth,
@@ -37,7 +39,7 @@ where
         AuthService {
             inner: self.inner.clone(),
             controller: self.controller.clone(),
-            operation: self.operation,
+            operation: self.operation.clone(),
             config: self.config.clone(),
             _protocol: PhantomData,
             _operation: PhantomData,
@@ -55,6 +57,7 @@ where
     S: Clone + 'static,
 
     UnauthorizedException: IntoResponse<Protocol>,
+    ThrottlingException: IntoResponse<Protocol>,
 {
     type Response = S::Response;
     type Error = S::Error;
@@ -72,20 +75,21 @@ where
         let mut inner = std::mem::replace(&mut self.inner, inner);
 
         let auth_controller = self.controller.clone();
-        let op = self.operation;
+        let op = self.operation.clone();
 
         let f = async move {
-            let headers = req.headers();
-            let auth_header = headers.get("authorization");
+            let peer = req.extensions().get::<ConnectInfo<SocketAddr>>().map(|c| c.0.ip());
+            if auth_controller.is_throttled(&op, peer) {
+                return Ok(IntoResponse::<Protocol>::into_response(ThrottlingException));
+            }
 
-            if auth_controller.auth(op, auth_header).await {
-                return inner.call(req).await;
+            let result = auth_controller.auth(&op, peer, req.headers().get("authorization")).await;
+            match result {
+                Ok(_) => inner.call(req).await,
+                Err(failure) => Ok(IntoResponse::<Protocol>::into_response(UnauthorizedException::new(failure))),
             }
-            
-            // This sucks, fix later
-            Ok(IntoResponse::<Protocol>::into_response(UnauthorizedException))
         };
-        
+
         Box::pin(f)
     }
 }
\ No newline at end of file